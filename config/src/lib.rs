@@ -10,11 +10,12 @@ use serde::{Deserialize, Serialize};
 use std::env;
 use std::fmt::{Display, Formatter};
 use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::path::PathBuf;
 use tracing::info;
 
 /// The application configuration.
 ///
-/// This struct is the central point for the entire application configuration. It holds the [`ServerConfig`] as well as [`DatabaseConfig`]and can be extended with any application-specific configuration settings that will be read from the main `app.toml` and the environment-specific configuration files.
+/// This struct is the central point for the entire application configuration. It holds the [`ServerConfig`], [`DatabaseConfig`], [`AuthConfig`], [`PasswordHashingConfig`], [`LoginThrottlingConfig`], [`TwoFactorConfig`], [`MailerConfig`], [`AccountEmailsConfig`], [`NotesConfig`] as well as [`AttachmentsConfig`] and can be extended with any application-specific configuration settings that will be read from the main `app.toml` and the environment-specific configuration files.
 ///
 /// For any setting that appears in both the `app.toml` and the environment-specific file, the latter will override the former so that default settings can be kept in `app.toml` that are overridden per environment if necessary.
 #[derive(Deserialize, Clone, Debug)]
@@ -23,6 +24,22 @@ pub struct Config {
     pub server: ServerConfig,
     /// the database configuration: [`DatabaseConfig`]
     pub database: DatabaseConfig,
+    /// the authentication configuration: [`AuthConfig`]
+    pub auth: AuthConfig,
+    /// the password hashing configuration: [`PasswordHashingConfig`]
+    pub password_hashing: PasswordHashingConfig,
+    /// the login throttling configuration: [`LoginThrottlingConfig`]
+    pub login_throttling: LoginThrottlingConfig,
+    /// the two-factor authentication configuration: [`TwoFactorConfig`]
+    pub two_factor: TwoFactorConfig,
+    /// the mailer configuration: [`MailerConfig`]
+    pub mailer: MailerConfig,
+    /// the account emails configuration: [`AccountEmailsConfig`]
+    pub account_emails: AccountEmailsConfig,
+    /// the notes configuration: [`NotesConfig`]
+    pub notes: NotesConfig,
+    /// the attachments configuration: [`AttachmentsConfig`]
+    pub attachments: AttachmentsConfig,
     // add your config settings here…
 }
 
@@ -97,6 +114,390 @@ pub struct DatabaseConfig {
     pub url: String,
 }
 
+/// The secret that is used for signing tokens unless another one is configured.
+///
+/// This is only meant for development and tests – the application refuses to boot in [`Environment::Production`] with this secret (see [`AuthConfig::validate`]).
+pub const DEFAULT_JWT_SECRET: &str = "dummy_secret_key";
+
+/// The algorithms that can be used for signing tokens.
+///
+/// The `HS*` algorithms use the shared [`AuthConfig::secret`] while all others use the PEM encoded keys at [`AuthConfig::private_key_path`] and [`AuthConfig::public_key_path`].
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, Default, PartialEq)]
+pub enum JwtAlgorithm {
+    /// HMAC using SHA-256
+    #[default]
+    HS256,
+    /// HMAC using SHA-384
+    HS384,
+    /// HMAC using SHA-512
+    HS512,
+    /// RSASSA-PKCS1-v1_5 using SHA-256
+    RS256,
+    /// RSASSA-PKCS1-v1_5 using SHA-384
+    RS384,
+    /// RSASSA-PKCS1-v1_5 using SHA-512
+    RS512,
+    /// ECDSA using P-256 and SHA-256
+    ES256,
+    /// ECDSA using P-384 and SHA-384
+    ES384,
+    /// Edwards-curve Digital Signature Algorithm
+    EdDSA,
+}
+
+impl JwtAlgorithm {
+    /// Whether the algorithm signs with a shared secret rather than a key pair.
+    pub fn is_symmetric(&self) -> bool {
+        matches!(
+            self,
+            JwtAlgorithm::HS256 | JwtAlgorithm::HS384 | JwtAlgorithm::HS512
+        )
+    }
+}
+
+/// The authentication configuration.
+///
+/// This struct keeps all settings for issuing and verifying the tokens handed out by `/login`. All settings have defaults suitable for development so the `[auth]` section can be omitted entirely outside of production, e.g.:
+///
+/// ```toml
+/// [auth]
+/// algorithm = "RS256"
+/// private_key_path = "keys/private.pem"
+/// public_key_path = "keys/public.pem"
+/// issuer = "forge-api"
+/// access_token_ttl_secs = 300
+/// ```
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[cfg_attr(test, derive(PartialEq))]
+pub struct AuthConfig {
+    /// The algorithm tokens are signed with
+    pub algorithm: JwtAlgorithm,
+    /// The shared secret used by the `HS*` algorithms
+    pub secret: Option<String>,
+    /// The path to the PEM encoded private key used by all other algorithms for signing tokens
+    pub private_key_path: Option<PathBuf>,
+    /// The path to the PEM encoded public key used by all other algorithms for verifying tokens
+    pub public_key_path: Option<PathBuf>,
+    /// The `iss` claim set on issued tokens and required on verified tokens, if any
+    pub issuer: Option<String>,
+    /// The `aud` claim set on issued tokens and required on verified tokens, if any
+    pub audience: Option<String>,
+    /// The number of seconds access tokens are valid for
+    pub access_token_ttl_secs: u64,
+    /// The number of seconds refresh tokens are valid for
+    pub refresh_token_ttl_secs: u64,
+}
+
+impl Default for AuthConfig {
+    fn default() -> Self {
+        Self {
+            algorithm: JwtAlgorithm::default(),
+            secret: Some(String::from(DEFAULT_JWT_SECRET)),
+            private_key_path: None,
+            public_key_path: None,
+            issuer: None,
+            audience: None,
+            access_token_ttl_secs: 120,
+            refresh_token_ttl_secs: 60 * 60 * 24 * 30,
+        }
+    }
+}
+
+impl AuthConfig {
+    /// Checks that the configuration is fit for the passed environment.
+    ///
+    /// In [`Environment::Production`], the `HS*` algorithms require a secret other than [`DEFAULT_JWT_SECRET`] and all other algorithms require both key paths to be set. Other environments accept any configuration.
+    pub fn validate(&self, env: &Environment) -> Result<(), anyhow::Error> {
+        if *env != Environment::Production {
+            return Ok(());
+        }
+
+        if self.algorithm.is_symmetric() {
+            match self.secret.as_deref() {
+                None | Some("") => Err(anyhow!("No JWT secret configured!")),
+                Some(DEFAULT_JWT_SECRET) => Err(anyhow!(
+                    "The default JWT secret must not be used in production!"
+                )),
+                Some(_) => Ok(()),
+            }
+        } else if self.private_key_path.is_none() || self.public_key_path.is_none() {
+            Err(anyhow!(
+                "The {:?} algorithm requires a private and a public key!",
+                self.algorithm
+            ))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// The password hashing configuration.
+///
+/// Passwords are hashed with Argon2id using these parameters. Changing them doesn't invalidate existing hashes – they are upgraded to the new parameters the next time their users log in. The defaults follow the [OWASP recommendations](https://cheatsheetseries.owasp.org/cheatsheets/Password_Storage_Cheat_Sheet.html#argon2id) so the `[password_hashing]` section can be omitted entirely, e.g.:
+///
+/// ```toml
+/// [password_hashing]
+/// memory_kib = 47104
+/// iterations = 1
+/// parallelism = 1
+/// ```
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[cfg_attr(test, derive(PartialEq))]
+pub struct PasswordHashingConfig {
+    /// The amount of memory used for hashing a password in KiB
+    pub memory_kib: u32,
+    /// The number of passes over the memory
+    pub iterations: u32,
+    /// The number of lanes that are hashed in parallel
+    pub parallelism: u32,
+}
+
+impl Default for PasswordHashingConfig {
+    fn default() -> Self {
+        Self {
+            memory_kib: 19 * 1024,
+            iterations: 2,
+            parallelism: 1,
+        }
+    }
+}
+
+/// The login throttling configuration.
+///
+/// Failed logins are counted per account and per client IP address. Once either count reaches its limit, further logins for the account or from the address are locked out for [`LoginThrottlingConfig::base_lockout_secs`], doubling with every further failure up to [`LoginThrottlingConfig::max_lockout_secs`]. A successful login resets the account's count while counts are forgotten once no login failed for [`LoginThrottlingConfig::reset_after_secs`]. All settings have defaults so the `[login_throttling]` section can be omitted entirely, e.g.:
+///
+/// ```toml
+/// [login_throttling]
+/// max_failures_per_account = 5
+/// max_failures_per_ip = 20
+/// base_lockout_secs = 30
+/// max_lockout_secs = 3600
+/// reset_after_secs = 86400
+/// ```
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[cfg_attr(test, derive(PartialEq))]
+pub struct LoginThrottlingConfig {
+    /// The number of failed logins for an account before it is locked out
+    pub max_failures_per_account: u32,
+    /// The number of failed logins from a client IP address before it is locked out
+    pub max_failures_per_ip: u32,
+    /// The duration of the first lockout in seconds
+    pub base_lockout_secs: u64,
+    /// The maximum duration of a lockout in seconds
+    pub max_lockout_secs: u64,
+    /// The number of seconds without failed logins after which the count of failures is reset
+    pub reset_after_secs: u64,
+}
+
+impl Default for LoginThrottlingConfig {
+    fn default() -> Self {
+        Self {
+            max_failures_per_account: 5,
+            max_failures_per_ip: 20,
+            base_lockout_secs: 30,
+            max_lockout_secs: 60 * 60,
+            reset_after_secs: 24 * 60 * 60,
+        }
+    }
+}
+
+/// The two-factor authentication configuration.
+///
+/// Users can protect their accounts with time-based one-time passwords (TOTP) in addition to their password. For such users, `/login` hands out a challenge token that has to be exchanged for tokens along with a code from their authenticator app. All settings have defaults so the `[two_factor]` section can be omitted entirely, e.g.:
+///
+/// ```toml
+/// [two_factor]
+/// issuer = "forge-api"
+/// challenge_ttl_secs = 300
+/// ```
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[cfg_attr(test, derive(PartialEq))]
+pub struct TwoFactorConfig {
+    /// The name authenticator apps show for the application, must not contain `:`
+    pub issuer: String,
+    /// The number of seconds challenge tokens handed out by `/login` are valid for
+    pub challenge_ttl_secs: u64,
+}
+
+impl Default for TwoFactorConfig {
+    fn default() -> Self {
+        Self {
+            issuer: String::from("forge-api"),
+            challenge_ttl_secs: 5 * 60,
+        }
+    }
+}
+
+/// The transports emails can be sent with.
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum MailTransport {
+    /// Emails are sent via the SMTP server configured in [`MailerConfig`].
+    Smtp,
+    /// Emails are written to files in [`MailerConfig::file_dir`] instead of being sent, e.g. during development.
+    #[default]
+    File,
+}
+
+/// How connections to the SMTP server are secured.
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum SmtpSecurity {
+    /// The connection uses TLS right from the start, usually on port 465.
+    Tls,
+    /// The connection is upgraded to TLS via `STARTTLS`, usually on port 587.
+    #[default]
+    StartTls,
+    /// The connection is not encrypted – only suitable for local mail catchers.
+    None,
+}
+
+/// The mailer configuration.
+///
+/// Emails are written to files by default so that nothing is sent during development. In production, emails are usually sent via SMTP, e.g.:
+///
+/// ```toml
+/// [mailer]
+/// transport = "smtp"
+/// from = "Forge <no-reply@example.com>"
+/// smtp_host = "smtp.example.com"
+/// smtp_username = "forge"
+/// ```
+///
+/// The password should be passed via the `APP_MAILER__SMTP_PASSWORD` environment variable rather than a configuration file.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[cfg_attr(test, derive(PartialEq))]
+pub struct MailerConfig {
+    /// How emails are sent
+    pub transport: MailTransport,
+    /// The sender of all emails, e.g. `Forge <no-reply@example.com>`
+    pub from: String,
+    /// The directory emails are written to by the `file` transport
+    pub file_dir: PathBuf,
+    /// The host name of the SMTP server
+    pub smtp_host: String,
+    /// The port of the SMTP server
+    pub smtp_port: u16,
+    /// How connections to the SMTP server are secured
+    pub smtp_security: SmtpSecurity,
+    /// The user name to authenticate with at the SMTP server, if any
+    pub smtp_username: Option<String>,
+    /// The password to authenticate with at the SMTP server, if any
+    pub smtp_password: Option<String>,
+}
+
+impl Default for MailerConfig {
+    fn default() -> Self {
+        Self {
+            transport: MailTransport::default(),
+            from: String::from("forge-api <no-reply@localhost>"),
+            file_dir: PathBuf::from("storage/mail"),
+            smtp_host: String::from("localhost"),
+            smtp_port: 587,
+            smtp_security: SmtpSecurity::default(),
+            smtp_username: None,
+            smtp_password: None,
+        }
+    }
+}
+
+/// The account emails configuration.
+///
+/// This struct keeps the settings for the emails sent to users to reset their password or verify their email address. The links in these emails point to [`AccountEmailsConfig::link_base_url`], which is expected to serve pages that pass the token from the link on to `/password/reset` or `/email/verify` respectively. All settings have defaults so the `[account_emails]` section can be omitted entirely, e.g.:
+///
+/// ```toml
+/// [account_emails]
+/// link_base_url = "https://notes.example.com"
+/// password_reset_ttl_secs = 3600
+/// email_verification_ttl_secs = 172800
+/// ```
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[cfg_attr(test, derive(PartialEq))]
+pub struct AccountEmailsConfig {
+    /// The URL links in emails start with
+    pub link_base_url: String,
+    /// The number of seconds password reset links are valid for
+    pub password_reset_ttl_secs: u64,
+    /// The number of seconds email verification links are valid for
+    pub email_verification_ttl_secs: u64,
+}
+
+impl Default for AccountEmailsConfig {
+    fn default() -> Self {
+        Self {
+            link_base_url: String::from("http://localhost:3000"),
+            password_reset_ttl_secs: 60 * 60,
+            email_verification_ttl_secs: 2 * 24 * 60 * 60,
+        }
+    }
+}
+
+/// The notes configuration.
+///
+/// All settings have defaults so the `[notes]` section can be omitted entirely, e.g.:
+///
+/// ```toml
+/// [notes]
+/// trash_retention_days = 7
+/// ```
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[cfg_attr(test, derive(PartialEq))]
+pub struct NotesConfig {
+    /// The number of days notes are kept in the trash before they are purged
+    pub trash_retention_days: u32,
+}
+
+impl Default for NotesConfig {
+    fn default() -> Self {
+        Self {
+            trash_retention_days: 30,
+        }
+    }
+}
+
+/// The attachments configuration.
+///
+/// All settings have defaults so the `[attachments]` section can be omitted entirely, e.g.:
+///
+/// ```toml
+/// [attachments]
+/// storage_root = "/var/lib/forge-api/attachments"
+/// max_size_bytes = 1048576
+/// allowed_content_types = ["image/png", "application/pdf"]
+/// ```
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[cfg_attr(test, derive(PartialEq))]
+pub struct AttachmentsConfig {
+    /// The directory the local blob store keeps the contents of attachments in
+    pub storage_root: PathBuf,
+    /// The maximum size of a single attachment in bytes
+    pub max_size_bytes: u64,
+    /// The MIME types attachments may have
+    pub allowed_content_types: Vec<String>,
+}
+
+impl Default for AttachmentsConfig {
+    fn default() -> Self {
+        Self {
+            storage_root: PathBuf::from("storage/attachments"),
+            max_size_bytes: 10 * 1024 * 1024,
+            allowed_content_types: [
+                "application/pdf",
+                "image/gif",
+                "image/jpeg",
+                "image/png",
+                "image/webp",
+                "text/csv",
+                "text/markdown",
+                "text/plain",
+            ]
+            .into_iter()
+            .map(String::from)
+            .collect(),
+        }
+    }
+}
+
 /// Loads the application configuration for a particular environment.
 ///
 /// Depending on the environment, this function will behave differently:
@@ -146,6 +547,14 @@ where
 
     let config: T = Figment::new()
         .merge(Serialized::defaults(ServerConfig::default()).key("server"))
+        .merge(Serialized::defaults(AuthConfig::default()).key("auth"))
+        .merge(Serialized::defaults(PasswordHashingConfig::default()).key("password_hashing"))
+        .merge(Serialized::defaults(LoginThrottlingConfig::default()).key("login_throttling"))
+        .merge(Serialized::defaults(TwoFactorConfig::default()).key("two_factor"))
+        .merge(Serialized::defaults(MailerConfig::default()).key("mailer"))
+        .merge(Serialized::defaults(AccountEmailsConfig::default()).key("account_emails"))
+        .merge(Serialized::defaults(NotesConfig::default()).key("notes"))
+        .merge(Serialized::defaults(AttachmentsConfig::default()).key("attachments"))
         .merge(Toml::file("config/app.toml"))
         .merge(Toml::file(format!(
             "config/environments/{}",
@@ -357,4 +766,284 @@ mod tests {
             Ok(())
         });
     }
+
+    #[test]
+    fn test_load_config_auth() {
+        #[derive(Deserialize)]
+        pub struct Config {
+            pub auth: AuthConfig,
+        }
+
+        figment::Jail::expect_with(|jail| {
+            let config_dir = jail.create_dir("config")?;
+            jail.create_file(
+                config_dir.join("app.toml"),
+                r#"
+                [auth]
+                algorithm = "RS256"
+                private_key_path = "keys/private.pem"
+                public_key_path = "keys/public.pem"
+            "#,
+            )?;
+
+            jail.set_env("APP_AUTH__ISSUER", "forge-api");
+            let config = load_config::<Config>(&Environment::Development).unwrap();
+
+            assert_that!(
+                config.auth,
+                eq(&AuthConfig {
+                    algorithm: JwtAlgorithm::RS256,
+                    secret: Some(String::from(DEFAULT_JWT_SECRET)),
+                    private_key_path: Some(PathBuf::from("keys/private.pem")),
+                    public_key_path: Some(PathBuf::from("keys/public.pem")),
+                    issuer: Some(String::from("forge-api")),
+                    audience: None,
+                    access_token_ttl_secs: 120,
+                    refresh_token_ttl_secs: 60 * 60 * 24 * 30,
+                })
+            );
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_load_config_password_hashing() {
+        #[derive(Deserialize)]
+        pub struct Config {
+            pub password_hashing: PasswordHashingConfig,
+        }
+
+        figment::Jail::expect_with(|jail| {
+            let config = load_config::<Config>(&Environment::Development).unwrap();
+            assert_that!(
+                config.password_hashing,
+                eq(&PasswordHashingConfig::default())
+            );
+
+            jail.set_env("APP_PASSWORD_HASHING__MEMORY_KIB", "47104");
+            jail.set_env("APP_PASSWORD_HASHING__ITERATIONS", "1");
+            let config = load_config::<Config>(&Environment::Development).unwrap();
+            assert_that!(
+                config.password_hashing,
+                eq(&PasswordHashingConfig {
+                    memory_kib: 47104,
+                    iterations: 1,
+                    parallelism: 1,
+                })
+            );
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_load_config_login_throttling() {
+        #[derive(Deserialize)]
+        pub struct Config {
+            pub login_throttling: LoginThrottlingConfig,
+        }
+
+        figment::Jail::expect_with(|jail| {
+            let config = load_config::<Config>(&Environment::Development).unwrap();
+            assert_that!(
+                config.login_throttling,
+                eq(&LoginThrottlingConfig::default())
+            );
+
+            jail.set_env("APP_LOGIN_THROTTLING__MAX_FAILURES_PER_ACCOUNT", "3");
+            jail.set_env("APP_LOGIN_THROTTLING__MAX_LOCKOUT_SECS", "600");
+            let config = load_config::<Config>(&Environment::Development).unwrap();
+            assert_that!(
+                config.login_throttling,
+                eq(&LoginThrottlingConfig {
+                    max_failures_per_account: 3,
+                    max_failures_per_ip: 20,
+                    base_lockout_secs: 30,
+                    max_lockout_secs: 600,
+                    reset_after_secs: 24 * 60 * 60,
+                })
+            );
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_load_config_two_factor() {
+        #[derive(Deserialize)]
+        pub struct Config {
+            pub two_factor: TwoFactorConfig,
+        }
+
+        figment::Jail::expect_with(|jail| {
+            let config = load_config::<Config>(&Environment::Development).unwrap();
+            assert_that!(config.two_factor, eq(&TwoFactorConfig::default()));
+
+            jail.set_env("APP_TWO_FACTOR__ISSUER", "Forge Notes");
+            let config = load_config::<Config>(&Environment::Development).unwrap();
+            assert_that!(
+                config.two_factor,
+                eq(&TwoFactorConfig {
+                    issuer: String::from("Forge Notes"),
+                    challenge_ttl_secs: 5 * 60,
+                })
+            );
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_load_config_mailer() {
+        #[derive(Deserialize)]
+        pub struct Config {
+            pub mailer: MailerConfig,
+            pub account_emails: AccountEmailsConfig,
+        }
+
+        figment::Jail::expect_with(|jail| {
+            let config = load_config::<Config>(&Environment::Development).unwrap();
+            assert_that!(config.mailer, eq(&MailerConfig::default()));
+            assert_that!(config.account_emails, eq(&AccountEmailsConfig::default()));
+
+            let config_dir = jail.create_dir("config")?;
+            jail.create_file(
+                config_dir.join("app.toml"),
+                r#"
+                [mailer]
+                transport = "smtp"
+                smtp_host = "smtp.example.com"
+                smtp_security = "tls"
+                smtp_port = 465
+                smtp_username = "forge"
+
+                [account_emails]
+                link_base_url = "https://notes.example.com"
+            "#,
+            )?;
+            jail.set_env("APP_MAILER__SMTP_PASSWORD", "s3cret");
+            let config = load_config::<Config>(&Environment::Development).unwrap();
+            assert_that!(
+                config.mailer,
+                eq(&MailerConfig {
+                    transport: MailTransport::Smtp,
+                    smtp_host: String::from("smtp.example.com"),
+                    smtp_port: 465,
+                    smtp_security: SmtpSecurity::Tls,
+                    smtp_username: Some(String::from("forge")),
+                    smtp_password: Some(String::from("s3cret")),
+                    ..MailerConfig::default()
+                })
+            );
+            assert_that!(
+                config.account_emails,
+                eq(&AccountEmailsConfig {
+                    link_base_url: String::from("https://notes.example.com"),
+                    ..AccountEmailsConfig::default()
+                })
+            );
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_load_config_notes() {
+        #[derive(Deserialize)]
+        pub struct Config {
+            pub notes: NotesConfig,
+        }
+
+        figment::Jail::expect_with(|jail| {
+            let config = load_config::<Config>(&Environment::Development).unwrap();
+            assert_that!(config.notes, eq(&NotesConfig::default()));
+
+            jail.set_env("APP_NOTES__TRASH_RETENTION_DAYS", "7");
+            let config = load_config::<Config>(&Environment::Development).unwrap();
+            assert_that!(
+                config.notes,
+                eq(&NotesConfig {
+                    trash_retention_days: 7,
+                })
+            );
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_load_config_attachments() {
+        #[derive(Deserialize)]
+        pub struct Config {
+            pub attachments: AttachmentsConfig,
+        }
+
+        figment::Jail::expect_with(|jail| {
+            let config = load_config::<Config>(&Environment::Development).unwrap();
+            assert_that!(config.attachments, eq(&AttachmentsConfig::default()));
+
+            let config_dir = jail.create_dir("config")?;
+            jail.create_file(
+                config_dir.join("app.toml"),
+                r#"
+                [attachments]
+                allowed_content_types = ["image/png"]
+            "#,
+            )?;
+            jail.set_env("APP_ATTACHMENTS__STORAGE_ROOT", "/tmp/attachments");
+            let config = load_config::<Config>(&Environment::Development).unwrap();
+            assert_that!(
+                config.attachments,
+                eq(&AttachmentsConfig {
+                    storage_root: PathBuf::from("/tmp/attachments"),
+                    max_size_bytes: 10 * 1024 * 1024,
+                    allowed_content_types: vec![String::from("image/png")],
+                })
+            );
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_auth_config_validate_default_secret() {
+        let auth = AuthConfig::default();
+
+        assert_that!(auth.validate(&Environment::Development), ok(anything()));
+        assert_that!(auth.validate(&Environment::Test), ok(anything()));
+        assert_that!(auth.validate(&Environment::Production), err(anything()));
+    }
+
+    #[test]
+    fn test_auth_config_validate_production() {
+        let missing_secret = AuthConfig {
+            secret: None,
+            ..AuthConfig::default()
+        };
+        assert_that!(
+            missing_secret.validate(&Environment::Production),
+            err(anything())
+        );
+
+        let custom_secret = AuthConfig {
+            secret: Some(String::from("a-proper-secret")),
+            ..AuthConfig::default()
+        };
+        assert_that!(
+            custom_secret.validate(&Environment::Production),
+            ok(anything())
+        );
+
+        let missing_keys = AuthConfig {
+            algorithm: JwtAlgorithm::EdDSA,
+            secret: None,
+            private_key_path: Some(PathBuf::from("keys/private.pem")),
+            ..AuthConfig::default()
+        };
+        assert_that!(
+            missing_keys.validate(&Environment::Production),
+            err(anything())
+        );
+    }
 }
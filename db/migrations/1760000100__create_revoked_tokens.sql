@@ -0,0 +1,10 @@
+CREATE TABLE revoked_tokens (
+    jti uuid PRIMARY KEY,
+    expires_at timestamptz NOT NULL
+);
+
+CREATE INDEX revoked_tokens_expires_at_idx ON revoked_tokens (expires_at);
+
+-- Logging out everywhere increments the generation, which revokes all access tokens issued for earlier
+-- generations regardless of when they were issued.
+ALTER TABLE users ADD COLUMN token_generation integer NOT NULL default 0;
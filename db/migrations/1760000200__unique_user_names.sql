@@ -0,0 +1,15 @@
+-- Names that only differ by case can't be told apart once names are compared case-insensitively.
+-- All but one of them get the start of their id appended so that the unique index can be created.
+-- Users don't record when they were created, so the name that sorts first byte-wise is kept, ties
+-- being broken by id.
+UPDATE users
+SET name = left(users.name, 246) || '-' || left(users.id::text, 8)
+FROM (
+    SELECT
+        id,
+        row_number() OVER (PARTITION BY lower(name) ORDER BY name COLLATE "C", id) AS position
+    FROM users
+) AS ranked
+WHERE users.id = ranked.id AND ranked.position > 1;
+
+CREATE UNIQUE INDEX users_name_lower_idx ON users (lower(name));
@@ -0,0 +1,16 @@
+ALTER TABLE notes ADD COLUMN owner_id uuid REFERENCES users (id) ON DELETE CASCADE;
+
+-- Notes created before notes had owners are kept by assigning them to a dedicated account named
+-- `legacy-notes-<random suffix>`, from which they can be reassigned to their actual owners. The
+-- account can't be logged into as its password hash is empty.
+WITH legacy_owner AS (
+    INSERT INTO users (name, token, pass)
+    SELECT 'legacy-notes-' || left(md5(random()::text), 8), '', ''
+    WHERE EXISTS (SELECT 1 FROM notes WHERE owner_id IS NULL)
+    RETURNING id
+)
+UPDATE notes SET owner_id = (SELECT id FROM legacy_owner) WHERE owner_id IS NULL;
+
+ALTER TABLE notes ALTER COLUMN owner_id SET NOT NULL;
+
+CREATE INDEX notes_owner_id_idx ON notes (owner_id);
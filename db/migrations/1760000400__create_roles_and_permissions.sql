@@ -0,0 +1,43 @@
+CREATE TABLE roles (
+    id uuid PRIMARY KEY default gen_random_uuid(),
+    name varchar(64) NOT NULL UNIQUE
+);
+
+CREATE TABLE permissions (
+    id uuid PRIMARY KEY default gen_random_uuid(),
+    name varchar(64) NOT NULL UNIQUE
+);
+
+CREATE TABLE role_permissions (
+    role_id uuid NOT NULL REFERENCES roles (id) ON DELETE CASCADE,
+    permission_id uuid NOT NULL REFERENCES permissions (id) ON DELETE CASCADE,
+    PRIMARY KEY (role_id, permission_id)
+);
+
+CREATE TABLE user_roles (
+    user_id uuid NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    role_id uuid NOT NULL REFERENCES roles (id) ON DELETE CASCADE,
+    PRIMARY KEY (user_id, role_id)
+);
+
+CREATE INDEX user_roles_role_id_idx ON user_roles (role_id);
+
+INSERT INTO permissions (name)
+VALUES
+    ('notes:read'),
+    ('notes:create'),
+    ('notes:update'),
+    ('notes:delete');
+
+-- The role every user is assigned on sign up.
+INSERT INTO roles (name) VALUES ('user');
+
+INSERT INTO role_permissions (role_id, permission_id)
+SELECT roles.id, permissions.id
+FROM roles CROSS JOIN permissions
+WHERE roles.name = 'user' AND permissions.name LIKE 'notes:%';
+
+INSERT INTO user_roles (user_id, role_id)
+SELECT users.id, roles.id
+FROM users CROSS JOIN roles
+WHERE roles.name = 'user';
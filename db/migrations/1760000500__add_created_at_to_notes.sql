@@ -0,0 +1,4 @@
+ALTER TABLE notes ADD COLUMN created_at timestamptz NOT NULL default now();
+
+CREATE INDEX notes_owner_id_created_at_idx ON notes (owner_id, created_at, id);
+DROP INDEX notes_owner_id_idx;
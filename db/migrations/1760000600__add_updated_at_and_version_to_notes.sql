@@ -0,0 +1,4 @@
+ALTER TABLE notes ADD COLUMN updated_at timestamptz NOT NULL default now();
+ALTER TABLE notes ADD COLUMN version integer NOT NULL default 1;
+
+UPDATE notes SET updated_at = created_at;
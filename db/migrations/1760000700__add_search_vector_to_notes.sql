@@ -0,0 +1,5 @@
+ALTER TABLE notes
+    ADD COLUMN search_vector tsvector
+    GENERATED ALWAYS AS (to_tsvector('english', text)) STORED;
+
+CREATE INDEX notes_search_vector_idx ON notes USING GIN (search_vector);
@@ -0,0 +1,9 @@
+CREATE TABLE note_revisions (
+    id uuid PRIMARY KEY default gen_random_uuid(),
+    note_id uuid NOT NULL REFERENCES notes (id) ON DELETE CASCADE,
+    version integer NOT NULL,
+    text text NOT NULL,
+    created_at timestamptz NOT NULL
+);
+
+CREATE UNIQUE INDEX note_revisions_note_id_version_idx ON note_revisions (note_id, version);
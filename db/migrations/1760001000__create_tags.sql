@@ -0,0 +1,24 @@
+CREATE TABLE tags (
+    id uuid PRIMARY KEY default gen_random_uuid(),
+    owner_id uuid NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    name varchar(32) NOT NULL
+);
+
+CREATE UNIQUE INDEX tags_owner_id_name_idx ON tags (owner_id, lower(name));
+
+CREATE TABLE note_tags (
+    note_id uuid NOT NULL REFERENCES notes (id) ON DELETE CASCADE,
+    tag_id uuid NOT NULL REFERENCES tags (id) ON DELETE CASCADE,
+    PRIMARY KEY (note_id, tag_id)
+);
+
+CREATE INDEX note_tags_tag_id_idx ON note_tags (tag_id);
+
+-- The names of a note's tags in alphabetical order, used wherever notes are loaded.
+CREATE FUNCTION note_tag_names(note_id uuid) RETURNS text[] LANGUAGE sql STABLE AS $$
+    SELECT ARRAY(
+        SELECT tags.name FROM note_tags JOIN tags ON tags.id = note_tags.tag_id
+        WHERE note_tags.note_id = $1
+        ORDER BY lower(tags.name)
+    )
+$$;
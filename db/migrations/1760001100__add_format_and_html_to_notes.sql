@@ -0,0 +1,5 @@
+CREATE TYPE note_format AS ENUM ('plain', 'markdown');
+
+ALTER TABLE notes ADD COLUMN format note_format NOT NULL default 'plain';
+-- The HTML rendering of the text, NULL until the note is first requested as HTML after a change.
+ALTER TABLE notes ADD COLUMN html text;
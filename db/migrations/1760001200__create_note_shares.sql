@@ -0,0 +1,24 @@
+CREATE TYPE share_permission AS ENUM ('read', 'write');
+
+CREATE TABLE note_shares (
+    note_id uuid NOT NULL REFERENCES notes (id) ON DELETE CASCADE,
+    user_id uuid NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    permission share_permission NOT NULL,
+    created_at timestamptz NOT NULL default now(),
+    PRIMARY KEY (note_id, user_id)
+);
+
+CREATE INDEX note_shares_user_id_idx ON note_shares (user_id);
+
+-- The access a user has to a note: 'write' for its owner, the permission of the note's share with
+-- the user for others and NULL if the user has no access at all.
+CREATE FUNCTION note_access(note_id uuid, user_id uuid) RETURNS share_permission LANGUAGE sql STABLE AS $$
+    SELECT CASE
+        WHEN notes.owner_id = $2 THEN 'write'::share_permission
+        ELSE (
+            SELECT note_shares.permission FROM note_shares
+            WHERE note_shares.note_id = $1 AND note_shares.user_id = $2
+        )
+    END
+    FROM notes WHERE notes.id = $1
+$$;
@@ -0,0 +1,12 @@
+CREATE TABLE note_links (
+    id uuid PRIMARY KEY default gen_random_uuid(),
+    note_id uuid NOT NULL REFERENCES notes (id) ON DELETE CASCADE,
+    token_hash varchar(64) NOT NULL,
+    expires_at timestamptz,
+    max_views integer,
+    view_count integer NOT NULL default 0,
+    created_at timestamptz NOT NULL default now()
+);
+
+CREATE UNIQUE INDEX note_links_token_hash_idx ON note_links (token_hash);
+CREATE INDEX note_links_note_id_idx ON note_links (note_id);
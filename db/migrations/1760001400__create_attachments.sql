@@ -0,0 +1,12 @@
+CREATE TABLE attachments (
+    id uuid PRIMARY KEY default gen_random_uuid(),
+    note_id uuid NOT NULL REFERENCES notes (id) ON DELETE CASCADE,
+    blob_key varchar(64) NOT NULL,
+    file_name text NOT NULL,
+    content_type text NOT NULL,
+    size bigint NOT NULL,
+    created_at timestamptz NOT NULL default now()
+);
+
+CREATE UNIQUE INDEX attachments_blob_key_idx ON attachments (blob_key);
+CREATE INDEX attachments_note_id_idx ON attachments (note_id);
@@ -0,0 +1,12 @@
+CREATE TYPE login_failure_scope AS ENUM ('account', 'ip');
+
+CREATE TABLE login_failures (
+    scope login_failure_scope NOT NULL,
+    subject text NOT NULL,
+    failure_count integer NOT NULL default 0,
+    last_failed_at timestamptz NOT NULL default now(),
+    locked_until timestamptz,
+    PRIMARY KEY (scope, subject)
+);
+
+CREATE INDEX login_failures_last_failed_at_idx ON login_failures (last_failed_at);
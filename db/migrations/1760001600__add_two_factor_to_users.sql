@@ -0,0 +1,27 @@
+-- The TOTP secret is set when enrollment starts and only takes effect once `totp_enabled_at` is set
+-- after the user confirmed a code. The last used time step is kept so that codes can't be replayed.
+ALTER TABLE users
+    ADD COLUMN totp_secret text,
+    ADD COLUMN totp_enabled_at timestamptz,
+    ADD COLUMN totp_last_used_step bigint;
+
+CREATE TABLE recovery_codes (
+    id uuid PRIMARY KEY default gen_random_uuid(),
+    user_id uuid NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    code_hash varchar(64) NOT NULL,
+    used_at timestamptz,
+    created_at timestamptz NOT NULL default now()
+);
+
+CREATE UNIQUE INDEX recovery_codes_user_id_code_hash_idx ON recovery_codes (user_id, code_hash);
+
+CREATE TABLE login_challenges (
+    id uuid PRIMARY KEY default gen_random_uuid(),
+    user_id uuid NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    token_hash varchar(64) NOT NULL,
+    expires_at timestamptz NOT NULL,
+    created_at timestamptz NOT NULL default now()
+);
+
+CREATE UNIQUE INDEX login_challenges_token_hash_idx ON login_challenges (token_hash);
+CREATE INDEX login_challenges_expires_at_idx ON login_challenges (expires_at);
@@ -0,0 +1,26 @@
+-- Email addresses are optional and only verified addresses are unique regardless of case, so that
+-- registering someone else's address doesn't keep its owner from verifying it for their own account.
+-- Only verified addresses are used for password resets so that nobody can take over an account by
+-- registering someone else's address.
+ALTER TABLE users
+    ADD COLUMN email varchar(254),
+    ADD COLUMN email_verified_at timestamptz;
+
+CREATE UNIQUE INDEX users_email_idx ON users (lower(email)) WHERE email_verified_at IS NOT NULL;
+
+CREATE TYPE email_token_purpose AS ENUM ('password_reset', 'email_verification');
+
+CREATE TABLE email_tokens (
+    id uuid PRIMARY KEY default gen_random_uuid(),
+    user_id uuid NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    purpose email_token_purpose NOT NULL,
+    token_hash varchar(64) NOT NULL,
+    email varchar(254) NOT NULL,
+    expires_at timestamptz NOT NULL,
+    used_at timestamptz,
+    created_at timestamptz NOT NULL default now()
+);
+
+CREATE UNIQUE INDEX email_tokens_token_hash_idx ON email_tokens (token_hash);
+CREATE INDEX email_tokens_user_id_idx ON email_tokens (user_id);
+CREATE INDEX email_tokens_expires_at_idx ON email_tokens (expires_at);
@@ -0,0 +1,18 @@
+-- Keys are looked up by their public prefix and only the SHA-256 hash of the whole key is stored.
+-- The scopes are names of permissions the key may be used for. They restrict the key to a subset of
+-- what the user's roles grant and never add permissions.
+CREATE TABLE api_keys (
+    id uuid PRIMARY KEY default gen_random_uuid(),
+    user_id uuid NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    name varchar(100) NOT NULL,
+    prefix varchar(32) NOT NULL,
+    key_hash varchar(64) NOT NULL,
+    scopes text[] NOT NULL,
+    expires_at timestamptz,
+    last_used_at timestamptz,
+    revoked_at timestamptz,
+    created_at timestamptz NOT NULL default now()
+);
+
+CREATE UNIQUE INDEX api_keys_prefix_idx ON api_keys (prefix);
+CREATE UNIQUE INDEX api_keys_user_id_name_idx ON api_keys (user_id, lower(name)) WHERE revoked_at IS NULL;
@@ -1,4 +1,18 @@
+-- The password of the seeded user is `password1`.
 INSERT INTO users
-(name, token)
+(name, token, pass)
 VALUES
-    ('someone', '9974812642a36dbee625fa06b2463dbff832e17dcce3836dbb');
+    ('someone', '9974812642a36dbee625fa06b2463dbff832e17dcce3836dbb', '$2b$12$4Sjc9B0j5P4RcnCmS2t4j.qE.rVmSpPGDs58eQDdReWK6iFTbSGUa');
+
+-- The admin role is granted every permission there is.
+INSERT INTO roles (name) VALUES ('admin');
+
+INSERT INTO role_permissions (role_id, permission_id)
+SELECT roles.id, permissions.id
+FROM roles CROSS JOIN permissions
+WHERE roles.name = 'admin';
+
+INSERT INTO user_roles (user_id, role_id)
+SELECT users.id, roles.id
+FROM users CROSS JOIN roles
+WHERE users.name = 'someone' AND roles.name IN ('user', 'admin');
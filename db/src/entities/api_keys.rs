@@ -0,0 +1,194 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::Postgres;
+use uuid::Uuid;
+use validator::Validate;
+
+/// A personal API key that machine clients, e.g. scripts or CI jobs, authenticate with on behalf of a user.
+///
+/// Only the public prefix of the key and the SHA-256 hash of the whole key are stored, so the key itself can't be recovered after it was created.
+#[derive(Serialize, Debug, utoipa::ToSchema, Deserialize)]
+pub struct ApiKey {
+    /// The id of the key.
+    pub id: Uuid,
+    /// The name the user gave the key to tell it apart from their other keys.
+    pub name: String,
+    /// The start of the key that identifies it without revealing it.
+    pub prefix: String,
+    /// The names of the permissions the key can be used for.
+    pub scopes: Vec<String>,
+    /// The time after which the key cannot be used anymore, if any.
+    pub expires_at: Option<DateTime<Utc>>,
+    /// The time the key was last used, roughly to the minute, if it has been used.
+    pub last_used_at: Option<DateTime<Utc>>,
+    /// The time the key was created.
+    pub created_at: DateTime<Utc>,
+}
+
+/// The properties of a new key.
+#[derive(Deserialize, Validate, Clone, utoipa::ToSchema)]
+#[cfg_attr(feature = "test-helpers", derive(Serialize))]
+pub struct ApiKeyChangeset {
+    /// The name of the key, unique among the user's keys
+    #[validate(length(min = 1, max = 100))]
+    pub name: String,
+    /// The names of the permissions the key can be used for, e.g. `notes:read`
+    #[validate(length(min = 1))]
+    pub scopes: Vec<String>,
+    /// The time after which the key cannot be used anymore. The key doesn't expire if this is missing.
+    #[validate(custom(function = "super::note_links::validate_in_future"))]
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+/// What's needed to authenticate a request with a key.
+#[derive(Debug, Clone)]
+pub struct ApiKeyCredentials {
+    /// The id of the key.
+    pub id: Uuid,
+    /// The id of the user the key belongs to.
+    pub user_id: Uuid,
+    /// The SHA-256 hash of the whole key.
+    pub key_hash: String,
+    /// The names of the permissions the key can be used for.
+    pub scopes: Vec<String>,
+}
+
+/// Creates a key for a user.
+///
+/// Names are unique among the keys of a user that haven't been revoked, regardless of case – if the name is already taken, [`crate::Error::Conflict`] is returned.
+pub async fn create(
+    user_id: Uuid,
+    prefix: &str,
+    key_hash: &str,
+    key: ApiKeyChangeset,
+    executor: impl sqlx::Executor<'_, Database = Postgres>,
+) -> Result<ApiKey, crate::Error> {
+    key.validate()?;
+
+    let key = sqlx::query_as!(
+        ApiKey,
+        r#"
+        INSERT INTO api_keys (user_id, name, prefix, key_hash, scopes, expires_at)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        RETURNING id, name, prefix, scopes, expires_at, last_used_at, created_at
+        "#,
+        user_id,
+        key.name,
+        prefix,
+        key_hash,
+        &key.scopes,
+        key.expires_at
+    )
+    .fetch_one(executor)
+    .await
+    .map_err(crate::Error::conflict_on("name"))?;
+
+    Ok(key)
+}
+
+/// Loads all keys of a user that haven't been revoked, most recent first.
+///
+/// Expired keys are included so that the user can see when they were last used.
+pub async fn load_all(
+    user_id: Uuid,
+    executor: impl sqlx::Executor<'_, Database = Postgres>,
+) -> Result<Vec<ApiKey>, crate::Error> {
+    let keys = sqlx::query_as!(
+        ApiKey,
+        r#"
+        SELECT id, name, prefix, scopes, expires_at, last_used_at, created_at
+        FROM api_keys
+        WHERE user_id = $1 AND revoked_at IS NULL
+        ORDER BY created_at DESC, id
+        "#,
+        user_id
+    )
+    .fetch_all(executor)
+    .await
+    .map_err(crate::Error::DbError)?;
+
+    Ok(keys)
+}
+
+/// Loads the credentials of the key with the passed prefix.
+///
+/// If there's no such key, it has been revoked or it has expired, [`Option::None`] is returned.
+pub async fn load_by_prefix(
+    prefix: &str,
+    executor: impl sqlx::Executor<'_, Database = Postgres>,
+) -> Result<Option<ApiKeyCredentials>, crate::Error> {
+    let credentials = sqlx::query_as!(
+        ApiKeyCredentials,
+        r#"
+        SELECT id, user_id, key_hash, scopes FROM api_keys
+        WHERE prefix = $1 AND revoked_at IS NULL AND (expires_at IS NULL OR expires_at > now())
+        "#,
+        prefix
+    )
+    .fetch_optional(executor)
+    .await
+    .map_err(crate::Error::DbError)?;
+
+    Ok(credentials)
+}
+
+/// Records that a key has been used.
+///
+/// The time is only updated if it's more than a minute old so that busy clients don't cause a write on every request.
+pub async fn touch(
+    id: Uuid,
+    executor: impl sqlx::Executor<'_, Database = Postgres>,
+) -> Result<(), crate::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE api_keys SET last_used_at = now()
+        WHERE id = $1 AND (last_used_at IS NULL OR last_used_at < now() - interval '1 minute')
+        "#,
+        id
+    )
+    .execute(executor)
+    .await
+    .map_err(crate::Error::DbError)?;
+
+    Ok(())
+}
+
+/// Revokes a key of a user so it can't be used anymore.
+///
+/// If the user has no such key or it has been revoked already, [`crate::Error::NoRecordFound`] is returned.
+pub async fn revoke(
+    id: Uuid,
+    user_id: Uuid,
+    executor: impl sqlx::Executor<'_, Database = Postgres>,
+) -> Result<(), crate::Error> {
+    let result = sqlx::query!(
+        "UPDATE api_keys SET revoked_at = now() WHERE id = $1 AND user_id = $2 AND revoked_at IS NULL",
+        id,
+        user_id
+    )
+    .execute(executor)
+    .await
+    .map_err(crate::Error::DbError)?;
+
+    if result.rows_affected() == 0 {
+        return Err(crate::Error::NoRecordFound);
+    }
+
+    Ok(())
+}
+
+/// Revokes all keys of the passed user, e.g. when their password has been reset.
+pub async fn revoke_all_for_user(
+    user_id: Uuid,
+    executor: impl sqlx::Executor<'_, Database = Postgres>,
+) -> Result<(), crate::Error> {
+    sqlx::query!(
+        "UPDATE api_keys SET revoked_at = now() WHERE user_id = $1 AND revoked_at IS NULL",
+        user_id
+    )
+    .execute(executor)
+    .await
+    .map_err(crate::Error::DbError)?;
+
+    Ok(())
+}
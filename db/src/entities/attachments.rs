@@ -0,0 +1,150 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::Postgres;
+use uuid::Uuid;
+use validator::Validate;
+
+/// A file attached to a note.
+///
+/// Only the file's metadata is stored in the database – its contents are kept in a blob store under [`Attachment::blob_key`].
+#[derive(Serialize, Debug, utoipa::ToSchema, Deserialize)]
+pub struct Attachment {
+    /// The id of the attachment.
+    pub id: Uuid,
+    /// The id of the note the file is attached to.
+    pub note_id: Uuid,
+    /// The key the file's contents are stored under in the blob store.
+    #[serde(skip)]
+    pub blob_key: String,
+    /// The name of the file as it was uploaded.
+    pub file_name: String,
+    /// The MIME type of the file.
+    pub content_type: String,
+    /// The size of the file in bytes.
+    pub size: i64,
+    /// The time the file was attached.
+    pub created_at: DateTime<Utc>,
+}
+
+/// The metadata of a file that has been stored in the blob store and is to be attached to a note.
+#[derive(Validate, Clone, Debug)]
+pub struct NewAttachment {
+    /// The key the file's contents are stored under in the blob store.
+    pub blob_key: String,
+    /// The name of the file as it was uploaded.
+    #[validate(length(min = 1, max = 255))]
+    pub file_name: String,
+    /// The MIME type of the file.
+    pub content_type: String,
+    /// The size of the file in bytes.
+    pub size: i64,
+}
+
+/// Attaches a file to a note that the user owns or that is shared with them for writing.
+///
+/// If the note does not exist, is in the trash or the user may not change it, [`crate::Error::NoRecordFound`] is returned.
+pub async fn create(
+    note_id: Uuid,
+    user_id: Uuid,
+    attachment: NewAttachment,
+    executor: impl sqlx::Executor<'_, Database = Postgres>,
+) -> Result<Attachment, crate::Error> {
+    attachment.validate()?;
+
+    let attachment = sqlx::query_as!(
+        Attachment,
+        r#"
+        INSERT INTO attachments (note_id, blob_key, file_name, content_type, size)
+        SELECT id, $3, $4, $5, $6 FROM notes
+        WHERE id = $1 AND note_access(id, $2) = 'write' AND deleted_at IS NULL
+        RETURNING id, note_id, blob_key, file_name, content_type, size, created_at
+        "#,
+        note_id,
+        user_id,
+        attachment.blob_key,
+        attachment.file_name,
+        attachment.content_type,
+        attachment.size
+    )
+    .fetch_optional(executor)
+    .await
+    .map_err(crate::Error::DbError)?
+    .ok_or(crate::Error::NoRecordFound)?;
+
+    Ok(attachment)
+}
+
+/// Loads all attachments of a note that the user owns or that is shared with them, in the order they were attached.
+pub async fn load_all(
+    note_id: Uuid,
+    user_id: Uuid,
+    executor: impl sqlx::Executor<'_, Database = Postgres>,
+) -> Result<Vec<Attachment>, crate::Error> {
+    let attachments = sqlx::query_as!(
+        Attachment,
+        r#"
+        SELECT attachments.id, attachments.note_id, attachments.blob_key, attachments.file_name, attachments.content_type, attachments.size, attachments.created_at
+        FROM attachments JOIN notes ON notes.id = attachments.note_id
+        WHERE attachments.note_id = $1 AND note_access(notes.id, $2) IS NOT NULL AND notes.deleted_at IS NULL
+        ORDER BY attachments.created_at, attachments.id
+        "#,
+        note_id,
+        user_id
+    )
+    .fetch_all(executor)
+    .await?;
+
+    Ok(attachments)
+}
+
+/// Loads an attachment of a note that the user owns or that is shared with them.
+///
+/// If there's no such attachment or the note is in the trash, [`crate::Error::NoRecordFound`] is returned.
+pub async fn load(
+    id: Uuid,
+    note_id: Uuid,
+    user_id: Uuid,
+    executor: impl sqlx::Executor<'_, Database = Postgres>,
+) -> Result<Attachment, crate::Error> {
+    sqlx::query_as!(
+        Attachment,
+        r#"
+        SELECT attachments.id, attachments.note_id, attachments.blob_key, attachments.file_name, attachments.content_type, attachments.size, attachments.created_at
+        FROM attachments JOIN notes ON notes.id = attachments.note_id
+        WHERE attachments.id = $1 AND attachments.note_id = $2 AND note_access(notes.id, $3) IS NOT NULL AND notes.deleted_at IS NULL
+        "#,
+        id,
+        note_id,
+        user_id
+    )
+    .fetch_optional(executor)
+    .await
+    .map_err(crate::Error::DbError)?
+    .ok_or(crate::Error::NoRecordFound)
+}
+
+/// Deletes an attachment of a note that the user owns or that is shared with them for writing, returning the key of the blob that is not referenced anymore.
+///
+/// Only the attachment's record is deleted – the caller is responsible for deleting the blob. If there's no such attachment, the note is in the trash or the user may not change it, [`crate::Error::NoRecordFound`] is returned.
+pub async fn delete(
+    id: Uuid,
+    note_id: Uuid,
+    user_id: Uuid,
+    executor: impl sqlx::Executor<'_, Database = Postgres>,
+) -> Result<String, crate::Error> {
+    sqlx::query_scalar!(
+        r#"
+        DELETE FROM attachments USING notes
+        WHERE attachments.id = $1 AND attachments.note_id = $2
+        AND notes.id = attachments.note_id AND note_access(notes.id, $3) = 'write' AND notes.deleted_at IS NULL
+        RETURNING attachments.blob_key
+        "#,
+        id,
+        note_id,
+        user_id
+    )
+    .fetch_optional(executor)
+    .await
+    .map_err(crate::Error::DbError)?
+    .ok_or(crate::Error::NoRecordFound)
+}
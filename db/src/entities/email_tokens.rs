@@ -0,0 +1,105 @@
+use chrono::{DateTime, Utc};
+use sqlx::Postgres;
+use uuid::Uuid;
+
+/// What a token sent by email can be used for.
+#[derive(sqlx::Type, Debug, Clone, Copy, PartialEq, Eq)]
+#[sqlx(type_name = "email_token_purpose", rename_all = "snake_case")]
+pub enum EmailTokenPurpose {
+    /// The token allows setting a new password for the user.
+    PasswordReset,
+    /// The token confirms that the user controls the email address it was sent to.
+    EmailVerification,
+}
+
+/// A token that has been sent to a user by email.
+#[derive(Debug, Clone)]
+pub struct EmailToken {
+    /// The id of the user the token was issued to.
+    pub user_id: Uuid,
+    /// The email address the token was sent to.
+    pub email: String,
+}
+
+/// Stores a token that is sent to the passed email address of a user.
+///
+/// Only the SHA-256 hash of the token is stored.
+pub async fn create(
+    user_id: Uuid,
+    purpose: EmailTokenPurpose,
+    token_hash: &str,
+    email: &str,
+    expires_at: DateTime<Utc>,
+    executor: impl sqlx::Executor<'_, Database = Postgres>,
+) -> Result<(), crate::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO email_tokens (user_id, purpose, token_hash, email, expires_at)
+        VALUES ($1, $2, $3, $4, $5)
+        "#,
+        user_id,
+        purpose as EmailTokenPurpose,
+        token_hash,
+        email,
+        expires_at
+    )
+    .execute(executor)
+    .await
+    .map_err(crate::Error::DbError)?;
+
+    Ok(())
+}
+
+/// Marks the token with the passed hash as used, returning the user and email address it was issued for.
+///
+/// Every token can only be used once. If there's no such token for the purpose, it has expired or it has been used before, also by a concurrent request, [`crate::Error::NoRecordFound`] is returned.
+pub async fn consume(
+    purpose: EmailTokenPurpose,
+    token_hash: &str,
+    executor: impl sqlx::Executor<'_, Database = Postgres>,
+) -> Result<EmailToken, crate::Error> {
+    sqlx::query_as!(
+        EmailToken,
+        r#"
+        UPDATE email_tokens SET used_at = now()
+        WHERE purpose = $1 AND token_hash = $2 AND used_at IS NULL AND expires_at > now()
+        RETURNING user_id, email
+        "#,
+        purpose as EmailTokenPurpose,
+        token_hash
+    )
+    .fetch_optional(executor)
+    .await
+    .map_err(crate::Error::DbError)?
+    .ok_or(crate::Error::NoRecordFound)
+}
+
+/// Invalidates all unused tokens of a user for the passed purpose, e.g. once one of them has been used.
+pub async fn invalidate_all(
+    user_id: Uuid,
+    purpose: EmailTokenPurpose,
+    executor: impl sqlx::Executor<'_, Database = Postgres>,
+) -> Result<(), crate::Error> {
+    sqlx::query!(
+        "UPDATE email_tokens SET used_at = now() WHERE user_id = $1 AND purpose = $2 AND used_at IS NULL",
+        user_id,
+        purpose as EmailTokenPurpose
+    )
+    .execute(executor)
+    .await
+    .map_err(crate::Error::DbError)?;
+
+    Ok(())
+}
+
+/// Removes all tokens that have expired, returning the number of removed tokens.
+pub async fn delete_expired(
+    executor: impl sqlx::Executor<'_, Database = Postgres>,
+) -> Result<u64, crate::Error> {
+    let result = sqlx::query!("DELETE FROM email_tokens WHERE expires_at <= now()")
+        .execute(executor)
+        .await
+        .map_err(crate::Error::DbError)?;
+
+    Ok(result.rows_affected())
+}
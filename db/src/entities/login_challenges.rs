@@ -0,0 +1,76 @@
+use chrono::{DateTime, Utc};
+use sqlx::Postgres;
+use uuid::Uuid;
+
+/// Stores a challenge for a user who passed the first step of logging in and still has to present their second factor.
+///
+/// Only the SHA-256 hash of the challenge token is stored.
+pub async fn create(
+    user_id: Uuid,
+    token_hash: &str,
+    expires_at: DateTime<Utc>,
+    executor: impl sqlx::Executor<'_, Database = Postgres>,
+) -> Result<(), crate::Error> {
+    sqlx::query!(
+        "INSERT INTO login_challenges (user_id, token_hash, expires_at) VALUES ($1, $2, $3)",
+        user_id,
+        token_hash,
+        expires_at
+    )
+    .execute(executor)
+    .await
+    .map_err(crate::Error::DbError)?;
+
+    Ok(())
+}
+
+/// Loads the id of the user a challenge was issued to by the hash of its token.
+///
+/// If there's no such challenge or it has expired, [`crate::Error::NoRecordFound`] is returned.
+pub async fn load_user_id(
+    token_hash: &str,
+    executor: impl sqlx::Executor<'_, Database = Postgres>,
+) -> Result<Uuid, crate::Error> {
+    sqlx::query_scalar!(
+        "SELECT user_id FROM login_challenges WHERE token_hash = $1 AND expires_at > now()",
+        token_hash
+    )
+    .fetch_optional(executor)
+    .await
+    .map_err(crate::Error::DbError)?
+    .ok_or(crate::Error::NoRecordFound)
+}
+
+/// Deletes a challenge once it has been passed so that it can't be used again.
+///
+/// If there's no such challenge or it has expired, e.g. because a concurrent request passed it already, [`crate::Error::NoRecordFound`] is returned.
+pub async fn complete(
+    token_hash: &str,
+    executor: impl sqlx::Executor<'_, Database = Postgres>,
+) -> Result<(), crate::Error> {
+    let result = sqlx::query!(
+        "DELETE FROM login_challenges WHERE token_hash = $1 AND expires_at > now()",
+        token_hash
+    )
+    .execute(executor)
+    .await
+    .map_err(crate::Error::DbError)?;
+
+    if result.rows_affected() == 0 {
+        return Err(crate::Error::NoRecordFound);
+    }
+
+    Ok(())
+}
+
+/// Removes all challenges that have expired, returning the number of removed challenges.
+pub async fn delete_expired(
+    executor: impl sqlx::Executor<'_, Database = Postgres>,
+) -> Result<u64, crate::Error> {
+    let result = sqlx::query!("DELETE FROM login_challenges WHERE expires_at <= now()")
+        .execute(executor)
+        .await
+        .map_err(crate::Error::DbError)?;
+
+    Ok(result.rows_affected())
+}
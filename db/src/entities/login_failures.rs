@@ -0,0 +1,119 @@
+use chrono::{DateTime, Utc};
+use sqlx::Postgres;
+
+/// What failed logins are counted for.
+#[derive(sqlx::Type, Debug, Clone, Copy, PartialEq, Eq)]
+#[sqlx(type_name = "login_failure_scope", rename_all = "lowercase")]
+pub enum LoginFailureScope {
+    /// Failures are counted per account, identified by the lowercased user name.
+    Account,
+    /// Failures are counted per client IP address.
+    Ip,
+}
+
+/// Loads the time until which logins for the passed account or from the passed IP address are locked out.
+///
+/// If both are locked out, the later time is returned. If neither is, `None` is returned.
+pub async fn locked_until(
+    account: &str,
+    ip: &str,
+    executor: impl sqlx::Executor<'_, Database = Postgres>,
+) -> Result<Option<DateTime<Utc>>, crate::Error> {
+    let locked_until = sqlx::query_scalar!(
+        r#"
+        SELECT max(locked_until) FROM login_failures
+        WHERE ((scope = 'account' AND subject = $1) OR (scope = 'ip' AND subject = $2))
+        AND locked_until > now()
+        "#,
+        account,
+        ip
+    )
+    .fetch_one(executor)
+    .await
+    .map_err(crate::Error::DbError)?;
+
+    Ok(locked_until)
+}
+
+/// Counts a failed login, returning the number of failures counted so far.
+///
+/// If the last failure was counted before `reset_before`, counting starts over and any lockout is lifted.
+pub async fn record(
+    scope: LoginFailureScope,
+    subject: &str,
+    reset_before: DateTime<Utc>,
+    executor: impl sqlx::Executor<'_, Database = Postgres>,
+) -> Result<i32, crate::Error> {
+    let failure_count = sqlx::query_scalar!(
+        r#"
+        INSERT INTO login_failures (scope, subject, failure_count) VALUES ($1, $2, 1)
+        ON CONFLICT (scope, subject) DO UPDATE SET
+            failure_count = CASE WHEN login_failures.last_failed_at < $3 THEN 1 ELSE login_failures.failure_count + 1 END,
+            locked_until = CASE WHEN login_failures.last_failed_at < $3 THEN NULL ELSE login_failures.locked_until END,
+            last_failed_at = now()
+        RETURNING failure_count
+        "#,
+        scope as LoginFailureScope,
+        subject,
+        reset_before
+    )
+    .fetch_one(executor)
+    .await
+    .map_err(crate::Error::DbError)?;
+
+    Ok(failure_count)
+}
+
+/// Locks out logins for the passed subject until the passed time.
+pub async fn lock(
+    scope: LoginFailureScope,
+    subject: &str,
+    until: DateTime<Utc>,
+    executor: impl sqlx::Executor<'_, Database = Postgres>,
+) -> Result<(), crate::Error> {
+    sqlx::query!(
+        "UPDATE login_failures SET locked_until = $3 WHERE scope = $1 AND subject = $2",
+        scope as LoginFailureScope,
+        subject,
+        until
+    )
+    .execute(executor)
+    .await
+    .map_err(crate::Error::DbError)?;
+
+    Ok(())
+}
+
+/// Forgets all failed logins for the passed subject, e.g. after a successful login.
+pub async fn reset(
+    scope: LoginFailureScope,
+    subject: &str,
+    executor: impl sqlx::Executor<'_, Database = Postgres>,
+) -> Result<(), crate::Error> {
+    sqlx::query!(
+        "DELETE FROM login_failures WHERE scope = $1 AND subject = $2",
+        scope as LoginFailureScope,
+        subject
+    )
+    .execute(executor)
+    .await
+    .map_err(crate::Error::DbError)?;
+
+    Ok(())
+}
+
+/// Removes the entries of all subjects without failures since `before` that aren't locked out anymore, returning the number of removed entries.
+pub async fn delete_stale(
+    before: DateTime<Utc>,
+    executor: impl sqlx::Executor<'_, Database = Postgres>,
+) -> Result<u64, crate::Error> {
+    let result = sqlx::query!(
+        "DELETE FROM login_failures WHERE last_failed_at < $1 AND (locked_until IS NULL OR locked_until <= now())",
+        before
+    )
+    .execute(executor)
+    .await
+    .map_err(crate::Error::DbError)?;
+
+    Ok(result.rows_affected())
+}
@@ -1,4 +1,30 @@
+/// All functionality related to the [`api_keys::ApiKey`] entity
+pub mod api_keys;
+/// All functionality related to the [`attachments::Attachment`] entity
+pub mod attachments;
+/// All functionality related to the single-use tokens sent to users by email
+pub mod email_tokens;
+/// All functionality related to the challenges users who log in with a second factor have to pass
+pub mod login_challenges;
+/// All functionality related to counting failed logins and locking out accounts and IP addresses
+pub mod login_failures;
+/// All functionality related to the [`note_links::NoteLink`] entity
+pub mod note_links;
+/// All functionality related to the [`note_revisions::NoteRevision`] entity
+pub mod note_revisions;
+/// All functionality related to sharing notes with other users
+pub mod note_shares;
 /// All functionality related to the [`notes::Note`] entity
 pub mod notes;
+/// All functionality related to the recovery codes users can log in with instead of their second factor
+pub mod recovery_codes;
+/// All functionality related to the [`refresh_tokens::RefreshToken`] entity
+pub mod refresh_tokens;
+/// All functionality related to the revocation list for access tokens
+pub mod revoked_tokens;
+/// All functionality related to roles and the permissions they grant
+pub mod roles;
+/// All functionality related to tags and the notes they are attached to
+pub mod tags;
 /// All functionality related to the [`users::User`] entity
 pub mod users;
@@ -0,0 +1,186 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::Postgres;
+use uuid::Uuid;
+use validator::{Validate, ValidationError};
+
+use super::notes::NoteFormat;
+
+/// A link that gives anyone who knows its token read access to a note, without an account.
+///
+/// Only the SHA-256 hash of the token is stored, so the token itself can't be recovered after the link was created.
+#[derive(Serialize, Debug, utoipa::ToSchema, Deserialize)]
+pub struct NoteLink {
+    /// The id of the link.
+    pub id: Uuid,
+    /// The id of the linked note.
+    pub note_id: Uuid,
+    /// The time after which the link cannot be used anymore, if any.
+    pub expires_at: Option<DateTime<Utc>>,
+    /// How often the link can be used before it stops working, if limited.
+    pub max_views: Option<i32>,
+    /// How often the link has been used.
+    pub view_count: i32,
+    /// The time the link was created.
+    pub created_at: DateTime<Utc>,
+}
+
+/// The limits of a new link.
+#[derive(Deserialize, Validate, Clone, Default, utoipa::ToSchema)]
+#[cfg_attr(feature = "test-helpers", derive(Serialize))]
+pub struct NoteLinkChangeset {
+    /// The time after which the link cannot be used anymore. The link doesn't expire if this is missing.
+    #[validate(custom(function = "validate_in_future"))]
+    pub expires_at: Option<DateTime<Utc>>,
+    /// How often the link can be used before it stops working. The number of views is unlimited if this is missing.
+    #[validate(range(min = 1))]
+    pub max_views: Option<i32>,
+}
+
+pub(crate) fn validate_in_future(time: &DateTime<Utc>) -> Result<(), ValidationError> {
+    if *time > Utc::now() {
+        Ok(())
+    } else {
+        Err(ValidationError::new("expires_at").with_message("must be in the future".into()))
+    }
+}
+
+/// A note as seen through a link.
+///
+/// Unlike [`super::notes::Note`], this leaves out who owns the note and how they organize it.
+#[derive(Serialize, Debug, utoipa::ToSchema, Deserialize)]
+pub struct LinkedNote {
+    pub id: Uuid,
+    pub text: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    /// The version of the note, starting at 1 and incremented on every update.
+    pub version: i32,
+    /// How the text of the note is formatted.
+    pub format: NoteFormat,
+}
+
+/// Creates a link to a note of the owner.
+///
+/// If the note does not exist, is owned by someone else or is in the trash, [`crate::Error::NoRecordFound`] is returned.
+pub async fn create(
+    note_id: Uuid,
+    owner_id: Uuid,
+    token_hash: &str,
+    link: NoteLinkChangeset,
+    executor: impl sqlx::Executor<'_, Database = Postgres>,
+) -> Result<NoteLink, crate::Error> {
+    link.validate()?;
+
+    let link = sqlx::query_as!(
+        NoteLink,
+        r#"
+        INSERT INTO note_links (note_id, token_hash, expires_at, max_views)
+        SELECT id, $3, $4, $5 FROM notes WHERE id = $1 AND owner_id = $2 AND deleted_at IS NULL
+        RETURNING id, note_id, expires_at, max_views, view_count, created_at
+        "#,
+        note_id,
+        owner_id,
+        token_hash,
+        link.expires_at,
+        link.max_views
+    )
+    .fetch_optional(executor)
+    .await
+    .map_err(crate::Error::DbError)?
+    .ok_or(crate::Error::NoRecordFound)?;
+
+    Ok(link)
+}
+
+/// Loads all links to a note of the owner, most recent first.
+///
+/// Expired and used up links are included so that the owner can see how their links were used.
+pub async fn load_all(
+    note_id: Uuid,
+    owner_id: Uuid,
+    executor: impl sqlx::Executor<'_, Database = Postgres>,
+) -> Result<Vec<NoteLink>, crate::Error> {
+    let links = sqlx::query_as!(
+        NoteLink,
+        r#"
+        SELECT note_links.id, note_links.note_id, note_links.expires_at, note_links.max_views, note_links.view_count, note_links.created_at
+        FROM note_links JOIN notes ON notes.id = note_links.note_id
+        WHERE note_links.note_id = $1 AND notes.owner_id = $2 AND notes.deleted_at IS NULL
+        ORDER BY note_links.created_at DESC, note_links.id
+        "#,
+        note_id,
+        owner_id
+    )
+    .fetch_all(executor)
+    .await?;
+
+    Ok(links)
+}
+
+/// Loads the note a link with the passed token hash points to, counting it as a view of the link.
+///
+/// The note is returned along with the cached HTML rendering of its text (see [`super::notes::cache_html`]). If there's no such link, it has expired or its views are used up, or the note is in the trash, [`crate::Error::NoRecordFound`] is returned and the view is not counted.
+pub async fn view(
+    token_hash: &str,
+    executor: impl sqlx::Executor<'_, Database = Postgres>,
+) -> Result<(LinkedNote, Option<String>), crate::Error> {
+    let record = sqlx::query!(
+        r#"
+        WITH link AS (
+            UPDATE note_links SET view_count = view_count + 1
+            FROM notes
+            WHERE note_links.token_hash = $1 AND notes.id = note_links.note_id AND notes.deleted_at IS NULL
+            AND (note_links.expires_at IS NULL OR note_links.expires_at > now())
+            AND (note_links.max_views IS NULL OR note_links.view_count < note_links.max_views)
+            RETURNING note_links.note_id
+        )
+        SELECT notes.id, notes.text, notes.created_at, notes.updated_at, notes.version, notes.format AS "format: NoteFormat", notes.html
+        FROM notes JOIN link ON link.note_id = notes.id
+        "#,
+        token_hash
+    )
+    .fetch_optional(executor)
+    .await
+    .map_err(crate::Error::DbError)?
+    .ok_or(crate::Error::NoRecordFound)?;
+
+    let note = LinkedNote {
+        id: record.id,
+        text: record.text,
+        created_at: record.created_at,
+        updated_at: record.updated_at,
+        version: record.version,
+        format: record.format,
+    };
+    Ok((note, record.html))
+}
+
+/// Revokes a link to a note of the owner so it can't be used anymore.
+///
+/// If there's no such link, [`crate::Error::NoRecordFound`] is returned.
+pub async fn revoke(
+    id: Uuid,
+    note_id: Uuid,
+    owner_id: Uuid,
+    executor: impl sqlx::Executor<'_, Database = Postgres>,
+) -> Result<(), crate::Error> {
+    let result = sqlx::query!(
+        r#"
+        DELETE FROM note_links USING notes
+        WHERE note_links.id = $1 AND note_links.note_id = $2
+        AND notes.id = note_links.note_id AND notes.owner_id = $3
+        "#,
+        id,
+        note_id,
+        owner_id
+    )
+    .execute(executor)
+    .await
+    .map_err(crate::Error::DbError)?;
+
+    if result.rows_affected() == 0 {
+        return Err(crate::Error::NoRecordFound);
+    }
+    Ok(())
+}
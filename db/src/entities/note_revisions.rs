@@ -0,0 +1,101 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::Postgres;
+use uuid::Uuid;
+
+/// A previous version of a note.
+///
+/// Revisions are recorded before every update of a note (see [`record`]) so that the note can be reverted to any of its previous versions.
+#[derive(Serialize, Debug, utoipa::ToSchema, Deserialize)]
+pub struct NoteRevision {
+    /// The id of the note this is a revision of.
+    pub note_id: Uuid,
+    /// The version of the note this revision captures.
+    pub version: i32,
+    /// The text of the note at that version.
+    pub text: String,
+    /// The time this version of the note was saved.
+    pub created_at: DateTime<Utc>,
+}
+
+/// Records the current version of a note that the user owns or that is shared with them for writing as a revision.
+///
+/// This is meant to be called right before updating the note, in the same transaction. The note is locked until the transaction ends so that concurrent updates can't record the same version twice. Nothing is recorded if the note does not exist, the user may not change it or it is in the trash – the update following will fail in that case anyway.
+pub async fn record(
+    note_id: Uuid,
+    user_id: Uuid,
+    executor: impl sqlx::Executor<'_, Database = Postgres>,
+) -> Result<(), crate::Error> {
+    sqlx::query!(
+        r#"
+        WITH note AS (
+            SELECT id, version, text, updated_at FROM notes
+            WHERE id = $1 AND note_access(id, $2) = 'write' AND deleted_at IS NULL
+            FOR UPDATE
+        )
+        INSERT INTO note_revisions (note_id, version, text, created_at)
+        SELECT id, version, text, updated_at FROM note
+        "#,
+        note_id,
+        user_id
+    )
+    .execute(executor)
+    .await
+    .map_err(crate::Error::DbError)?;
+
+    Ok(())
+}
+
+/// Loads all revisions of a note that the user owns or that is shared with them, most recent first.
+pub async fn load_all(
+    note_id: Uuid,
+    user_id: Uuid,
+    executor: impl sqlx::Executor<'_, Database = Postgres>,
+) -> Result<Vec<NoteRevision>, crate::Error> {
+    let revisions = sqlx::query_as!(
+        NoteRevision,
+        r#"
+        SELECT note_revisions.note_id, note_revisions.version, note_revisions.text, note_revisions.created_at
+        FROM note_revisions JOIN notes ON notes.id = note_revisions.note_id
+        WHERE note_revisions.note_id = $1 AND note_access(notes.id, $2) IS NOT NULL
+        AND notes.deleted_at IS NULL
+        ORDER BY note_revisions.version DESC
+        "#,
+        note_id,
+        user_id
+    )
+    .fetch_all(executor)
+    .await?;
+
+    Ok(revisions)
+}
+
+/// Loads a particular revision of a note that the user owns or that is shared with them.
+///
+/// If the note has no revision for the version, [`crate::Error::NoRecordFound`] is returned.
+pub async fn load(
+    note_id: Uuid,
+    version: i32,
+    user_id: Uuid,
+    executor: impl sqlx::Executor<'_, Database = Postgres>,
+) -> Result<NoteRevision, crate::Error> {
+    match sqlx::query_as!(
+        NoteRevision,
+        r#"
+        SELECT note_revisions.note_id, note_revisions.version, note_revisions.text, note_revisions.created_at
+        FROM note_revisions JOIN notes ON notes.id = note_revisions.note_id
+        WHERE note_revisions.note_id = $1 AND note_revisions.version = $2
+        AND note_access(notes.id, $3) IS NOT NULL AND notes.deleted_at IS NULL
+        "#,
+        note_id,
+        version,
+        user_id
+    )
+    .fetch_optional(executor)
+    .await
+    .map_err(crate::Error::DbError)?
+    {
+        Some(revision) => Ok(revision),
+        None => Err(crate::Error::NoRecordFound),
+    }
+}
@@ -0,0 +1,194 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::Postgres;
+use uuid::Uuid;
+use validator::{Validate, ValidationError, ValidationErrors};
+
+/// What a user that a note is shared with may do with it.
+#[derive(
+    Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, sqlx::Type, utoipa::ToSchema,
+)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(type_name = "share_permission", rename_all = "lowercase")]
+pub enum SharePermission {
+    /// The user may read the note.
+    Read,
+    /// The user may read and update the note.
+    Write,
+}
+
+/// A note shared by its owner with another user.
+#[derive(Serialize, Debug, utoipa::ToSchema, Deserialize)]
+pub struct NoteShare {
+    /// The id of the shared note.
+    pub note_id: Uuid,
+    /// The id of the user the note is shared with.
+    pub user_id: Uuid,
+    /// The name of the user the note is shared with.
+    pub user_name: String,
+    /// What the user may do with the note.
+    pub permission: SharePermission,
+    /// The time the note was first shared with the user.
+    pub created_at: DateTime<Utc>,
+}
+
+/// The user to share a note with and what they may do with it.
+#[derive(Deserialize, Validate, Clone, utoipa::ToSchema)]
+#[cfg_attr(feature = "test-helpers", derive(Serialize))]
+pub struct NoteShareChangeset {
+    /// The name of the user to share the note with.
+    #[validate(length(min = 1))]
+    pub user_name: String,
+    /// What the user may do with the note.
+    pub permission: SharePermission,
+}
+
+/// Loads all shares of a note of the owner, sorted by the names of the users the note is shared with.
+pub async fn load_all(
+    note_id: Uuid,
+    owner_id: Uuid,
+    executor: impl sqlx::Executor<'_, Database = Postgres>,
+) -> Result<Vec<NoteShare>, crate::Error> {
+    let shares = sqlx::query_as!(
+        NoteShare,
+        r#"
+        SELECT
+            note_shares.note_id,
+            note_shares.user_id,
+            users.name AS user_name,
+            note_shares.permission AS "permission: SharePermission",
+            note_shares.created_at
+        FROM note_shares
+        JOIN notes ON notes.id = note_shares.note_id
+        JOIN users ON users.id = note_shares.user_id
+        WHERE note_shares.note_id = $1 AND notes.owner_id = $2 AND notes.deleted_at IS NULL
+        ORDER BY lower(users.name)
+        "#,
+        note_id,
+        owner_id
+    )
+    .fetch_all(executor)
+    .await?;
+
+    Ok(shares)
+}
+
+/// Loads what a user may do with a note – [`SharePermission::Write`] if they own it, the permission of the note's share with them otherwise.
+///
+/// If the note does not exist, is in the trash or is neither owned by nor shared with the user, [`crate::Error::NoRecordFound`] is returned.
+pub async fn load_access(
+    note_id: Uuid,
+    user_id: Uuid,
+    executor: impl sqlx::Executor<'_, Database = Postgres>,
+) -> Result<SharePermission, crate::Error> {
+    sqlx::query_scalar!(
+        r#"
+        SELECT note_access(id, $2) AS "permission!: SharePermission" FROM notes
+        WHERE id = $1 AND note_access(id, $2) IS NOT NULL AND deleted_at IS NULL
+        "#,
+        note_id,
+        user_id
+    )
+    .fetch_optional(executor)
+    .await
+    .map_err(crate::Error::DbError)?
+    .ok_or(crate::Error::NoRecordFound)
+}
+
+/// Shares a note of the owner with another user, replacing the permission if the note is already shared with them.
+///
+/// If the note does not exist, is owned by someone else or is in the trash, [`crate::Error::NoRecordFound`] is returned. If there's no user with the name (compared case-insensitively) or it's the owner's own name, [`crate::Error::ValidationError`] is returned.
+pub async fn grant(
+    note_id: Uuid,
+    owner_id: Uuid,
+    share: NoteShareChangeset,
+    db: impl sqlx::Acquire<'_, Database = Postgres>,
+) -> Result<NoteShare, crate::Error> {
+    share.validate()?;
+
+    let mut tx = db.begin().await.map_err(crate::Error::DbError)?;
+
+    let record = sqlx::query!(
+        r#"
+        SELECT users.id AS "user_id?", users.name AS "user_name?"
+        FROM notes LEFT JOIN users ON lower(users.name) = lower($3)
+        WHERE notes.id = $1 AND notes.owner_id = $2 AND notes.deleted_at IS NULL
+        "#,
+        note_id,
+        owner_id,
+        share.user_name
+    )
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(crate::Error::DbError)?
+    .ok_or(crate::Error::NoRecordFound)?;
+
+    let (Some(user_id), Some(user_name)) = (record.user_id, record.user_name) else {
+        return Err(invalid_user_name("does not exist"));
+    };
+    if user_id == owner_id {
+        return Err(invalid_user_name("must not be the owner of the note"));
+    }
+
+    let created_at = sqlx::query_scalar!(
+        r#"
+        INSERT INTO note_shares (note_id, user_id, permission) VALUES ($1, $2, $3)
+        ON CONFLICT (note_id, user_id) DO UPDATE SET permission = EXCLUDED.permission
+        RETURNING created_at
+        "#,
+        note_id,
+        user_id,
+        share.permission as SharePermission
+    )
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(crate::Error::DbError)?;
+
+    tx.commit().await.map_err(crate::Error::DbError)?;
+
+    Ok(NoteShare {
+        note_id,
+        user_id,
+        user_name,
+        permission: share.permission,
+        created_at,
+    })
+}
+
+fn invalid_user_name(message: &'static str) -> crate::Error {
+    let mut errors = ValidationErrors::new();
+    errors.add(
+        "user_name",
+        ValidationError::new("user_name").with_message(message.into()),
+    );
+    crate::Error::ValidationError(errors)
+}
+
+/// Stops sharing a note of the owner with a user.
+///
+/// If the note is not shared with the user, [`crate::Error::NoRecordFound`] is returned.
+pub async fn revoke(
+    note_id: Uuid,
+    user_id: Uuid,
+    owner_id: Uuid,
+    executor: impl sqlx::Executor<'_, Database = Postgres>,
+) -> Result<(), crate::Error> {
+    let result = sqlx::query!(
+        r#"
+        DELETE FROM note_shares USING notes
+        WHERE note_shares.note_id = $1 AND note_shares.user_id = $2
+        AND notes.id = note_shares.note_id AND notes.owner_id = $3 AND notes.deleted_at IS NULL
+        "#,
+        note_id,
+        user_id,
+        owner_id
+    )
+    .execute(executor)
+    .await
+    .map_err(crate::Error::DbError)?;
+
+    if result.rows_affected() == 0 {
+        return Err(crate::Error::NoRecordFound);
+    }
+    Ok(())
+}
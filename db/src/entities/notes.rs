@@ -1,102 +1,758 @@
+use chrono::{DateTime, Utc};
 #[cfg(feature = "test-helpers")]
 use fake::{faker::lorem::en::*, Dummy};
 use serde::Deserialize;
 use serde::Serialize;
-use sqlx::Postgres;
+use sqlx::{Postgres, QueryBuilder};
 use uuid::Uuid;
-use validator::Validate;
+use validator::{Validate, ValidationError, ValidationErrors};
+
+use super::tags;
 
 #[derive(Serialize, Debug, utoipa::ToSchema, Deserialize)]
 pub struct Note {
     pub id: Uuid,
     pub text: String,
+    pub owner_id: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    /// The version of the note, starting at 1 and incremented on every update.
+    pub version: i32,
+    /// How the text of the note is formatted.
+    pub format: NoteFormat,
+    /// The names of the note's tags in alphabetical order.
+    pub tags: Vec<String>,
+}
+
+/// How the text of a note is formatted, which determines how it is rendered to HTML.
+#[derive(
+    Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq, sqlx::Type, utoipa::ToSchema,
+)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(type_name = "note_format", rename_all = "lowercase")]
+pub enum NoteFormat {
+    /// Plain text that is displayed as-is.
+    #[default]
+    Plain,
+    /// CommonMark Markdown.
+    Markdown,
 }
 
-#[derive(Deserialize, Validate, Clone)]
+#[derive(Deserialize, Validate, Clone, utoipa::ToSchema)]
 #[cfg_attr(feature = "test-helpers", derive(Serialize, Dummy))]
 pub struct NoteChangeset {
     #[cfg_attr(feature = "test-helpers", dummy(faker = "Sentence(3..8)"))]
     #[validate(length(min = 1))]
     pub text: String,
+    /// The names of the tags the note should have, replacing its current tags. The tags are left unchanged if this is missing.
+    #[cfg_attr(feature = "test-helpers", dummy(default))]
+    #[validate(length(max = 20), custom(function = "validate_tag_names"))]
+    pub tags: Option<Vec<String>>,
+    /// How the text of the note is formatted. New notes are plain text and the format of existing notes is left unchanged if this is missing.
+    #[cfg_attr(feature = "test-helpers", dummy(default))]
+    pub format: Option<NoteFormat>,
+}
+
+fn validate_tag_names(names: &[String]) -> Result<(), ValidationError> {
+    let valid = names.iter().all(|name| {
+        let length = name.trim().chars().count();
+        (1..=tags::MAX_NAME_LENGTH).contains(&length)
+    });
+    if valid {
+        Ok(())
+    } else {
+        Err(ValidationError::new("tag_name")
+            .with_message(format!("must be 1 to {} characters long", tags::MAX_NAME_LENGTH).into()))
+    }
 }
 
 pub async fn load_all(
+    owner_id: Uuid,
     executor: impl sqlx::Executor<'_, Database = Postgres>,
 ) -> Result<Vec<Note>, crate::Error> {
-    let notes = sqlx::query_as!(Note, "SELECT id, text FROM notes")
-        .fetch_all(executor)
-        .await?;
+    let notes = sqlx::query_as!(
+        Note,
+        r#"SELECT id, text, owner_id, created_at, updated_at, version, format AS "format: NoteFormat", note_tag_names(id) AS "tags!" FROM notes WHERE owner_id = $1 AND deleted_at IS NULL ORDER BY created_at, id"#,
+        owner_id
+    )
+    .fetch_all(executor)
+    .await?;
     Ok(notes)
 }
 
+/// The order in which [`load_page`] returns notes.
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq, utoipa::ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    /// Oldest notes first.
+    Asc,
+    /// Newest notes first.
+    #[default]
+    Desc,
+}
+
+/// How [`load_page`] combines the tags that notes are filtered by.
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq, utoipa::ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum TagMode {
+    /// Only notes having all of the tags.
+    #[default]
+    All,
+    /// Notes having at least one of the tags.
+    Any,
+}
+
+/// The criteria that the notes returned by [`load_page`] must match.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoteFilter<'a> {
+    /// Only notes containing this text, compared case-insensitively.
+    pub text: Option<&'a str>,
+    /// Only notes having all or any of these tags (depending on `tag_mode`), compared case-insensitively. Notes are not filtered by tags if this is empty.
+    pub tags: &'a [String],
+    /// Whether notes must have all of `tags` or any of them.
+    pub tag_mode: TagMode,
+}
+
+/// The position of a note in the list of notes sorted by creation time that [`load_page`] continues after.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cursor {
+    /// The creation time of the last note of the previous page.
+    pub created_at: DateTime<Utc>,
+    /// The id of the last note of the previous page, breaking ties between notes created at the same time.
+    pub id: Uuid,
+}
+
+impl From<&Note> for Cursor {
+    fn from(note: &Note) -> Self {
+        Self {
+            created_at: note.created_at,
+            id: note.id,
+        }
+    }
+}
+
+/// Loads a page of up to `limit` notes of the owner, sorted by creation time.
+///
+/// Only notes matching `filter` are returned. The page starts after the note identified by `after` if that is passed and with the first note otherwise. Along with the notes, the cursor to pass for loading the next page is returned – it is [`Option::None`] if there are no more notes.
+pub async fn load_page(
+    owner_id: Uuid,
+    filter: NoteFilter<'_>,
+    order: SortOrder,
+    after: Option<Cursor>,
+    limit: i64,
+    executor: impl sqlx::Executor<'_, Database = Postgres>,
+) -> Result<(Vec<Note>, Option<Cursor>), crate::Error> {
+    let text = filter.text.map(escape_like);
+    let tags: Vec<String> = tags::normalize(filter.tags)
+        .iter()
+        .map(|name| name.to_lowercase())
+        .collect();
+    let match_all = filter.tag_mode == TagMode::All;
+    let after_created_at = after.map(|cursor| cursor.created_at);
+    let after_id = after.map(|cursor| cursor.id);
+
+    // Load one more note than requested to find out whether there is a next page.
+    let mut notes = match order {
+        SortOrder::Asc => {
+            sqlx::query_as!(
+                Note,
+                r#"
+                SELECT id, text, owner_id, created_at, updated_at, version, format AS "format: NoteFormat", note_tag_names(id) AS "tags!" FROM notes
+                WHERE owner_id = $1 AND deleted_at IS NULL
+                AND ($2::text IS NULL OR text ILIKE '%' || $2 || '%')
+                AND ($3::timestamptz IS NULL OR (created_at, id) > ($3, $4::uuid))
+                AND (cardinality($6::text[]) = 0 OR (
+                    SELECT count(*) FROM note_tags JOIN tags ON tags.id = note_tags.tag_id
+                    WHERE note_tags.note_id = notes.id AND lower(tags.name) = ANY($6)
+                ) >= CASE WHEN $7 THEN cardinality($6) ELSE 1 END)
+                ORDER BY created_at ASC, id ASC
+                LIMIT $5
+                "#,
+                owner_id,
+                text,
+                after_created_at,
+                after_id,
+                limit + 1,
+                &tags,
+                match_all
+            )
+            .fetch_all(executor)
+            .await?
+        }
+        SortOrder::Desc => {
+            sqlx::query_as!(
+                Note,
+                r#"
+                SELECT id, text, owner_id, created_at, updated_at, version, format AS "format: NoteFormat", note_tag_names(id) AS "tags!" FROM notes
+                WHERE owner_id = $1 AND deleted_at IS NULL
+                AND ($2::text IS NULL OR text ILIKE '%' || $2 || '%')
+                AND ($3::timestamptz IS NULL OR (created_at, id) < ($3, $4::uuid))
+                AND (cardinality($6::text[]) = 0 OR (
+                    SELECT count(*) FROM note_tags JOIN tags ON tags.id = note_tags.tag_id
+                    WHERE note_tags.note_id = notes.id AND lower(tags.name) = ANY($6)
+                ) >= CASE WHEN $7 THEN cardinality($6) ELSE 1 END)
+                ORDER BY created_at DESC, id DESC
+                LIMIT $5
+                "#,
+                owner_id,
+                text,
+                after_created_at,
+                after_id,
+                limit + 1,
+                &tags,
+                match_all
+            )
+            .fetch_all(executor)
+            .await?
+        }
+    };
+
+    let next = if notes.len() as i64 > limit {
+        notes.truncate(limit as usize);
+        notes.last().map(Cursor::from)
+    } else {
+        None
+    };
+
+    Ok((notes, next))
+}
+
+fn escape_like(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
+/// A note matching a search along with how well it matches.
+#[derive(Serialize, Debug, utoipa::ToSchema, Deserialize)]
+pub struct SearchResult {
+    #[serde(flatten)]
+    pub note: Note,
+    /// How well the note matches the search, higher is better.
+    pub rank: f32,
+    /// An HTML fragment of the note's text with the matches wrapped in `<mark>` elements.
+    pub snippet: String,
+}
+
+/// Searches the owner's notes for the passed query, returning up to `limit` results, best matches first.
+///
+/// All words in the query must occur in a note for it to match, with words being matched regardless of their inflection (e.g. `notes` also matches `note`). Words in double quotes must occur as a phrase, and words ending in `*` match all words they are a prefix of. If the query contains no words to search for, [`crate::Error::ValidationError`] is returned.
+pub async fn search(
+    owner_id: Uuid,
+    query: &str,
+    limit: i64,
+    executor: impl sqlx::Executor<'_, Database = Postgres>,
+) -> Result<Vec<SearchResult>, crate::Error> {
+    let Some(tsquery) = build_tsquery(query) else {
+        let mut errors = ValidationErrors::new();
+        errors.add(
+            "q",
+            ValidationError::new("search_terms").with_message("must contain a word".into()),
+        );
+        return Err(crate::Error::ValidationError(errors));
+    };
+
+    let records = sqlx::query!(
+        r#"
+        SELECT
+            id, text, owner_id, created_at, updated_at, version, format AS "format: NoteFormat", note_tag_names(id) AS "tags!",
+            ts_rank(search_vector, query) AS "rank!",
+            ts_headline(
+                'english',
+                replace(replace(replace(text, '&', '&amp;'), '<', '&lt;'), '>', '&gt;'),
+                query,
+                'StartSel=<mark>, StopSel=</mark>'
+            ) AS "snippet!"
+        FROM notes, to_tsquery('english', $2) AS query
+        WHERE owner_id = $1 AND deleted_at IS NULL AND search_vector @@ query
+        ORDER BY "rank!" DESC, created_at DESC, id DESC
+        LIMIT $3
+        "#,
+        owner_id,
+        tsquery,
+        limit
+    )
+    .fetch_all(executor)
+    .await?;
+
+    Ok(records
+        .into_iter()
+        .map(|record| SearchResult {
+            note: Note {
+                id: record.id,
+                text: record.text,
+                owner_id: record.owner_id,
+                created_at: record.created_at,
+                updated_at: record.updated_at,
+                version: record.version,
+                format: record.format,
+                tags: record.tags,
+            },
+            rank: record.rank,
+            snippet: record.snippet,
+        })
+        .collect())
+}
+
+/// Translates a search query into the syntax of Postgres' `to_tsquery`, see [`search`].
+///
+/// Only letters and digits are kept so that user input can't inject `tsquery` operators. Returns [`Option::None`] if there's nothing left to search for.
+fn build_tsquery(query: &str) -> Option<String> {
+    fn words(input: &str) -> impl Iterator<Item = &str> {
+        input
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|word| !word.is_empty())
+    }
+
+    let mut terms = vec![];
+    // Every other part of the query is enclosed in double quotes.
+    for (i, part) in query.split('"').enumerate() {
+        if i % 2 == 1 {
+            let phrase: Vec<String> = words(part).map(|word| format!("'{}'", word)).collect();
+            if !phrase.is_empty() {
+                terms.push(format!("({})", phrase.join(" <-> ")));
+            }
+        } else {
+            for token in part.split_whitespace() {
+                let mut token_words: Vec<String> =
+                    words(token).map(|word| format!("'{}'", word)).collect();
+                if token.ends_with('*') {
+                    if let Some(last) = token_words.last_mut() {
+                        last.push_str(":*");
+                    }
+                }
+                terms.extend(token_words);
+            }
+        }
+    }
+
+    if terms.is_empty() {
+        None
+    } else {
+        Some(terms.join(" & "))
+    }
+}
+
+/// Loads a note that the user owns or that is shared with them (see [`crate::entities::note_shares`]).
 pub async fn load(
     id: Uuid,
+    user_id: Uuid,
     executor: impl sqlx::Executor<'_, Database = Postgres>,
 ) -> Result<Note, crate::Error> {
-    match sqlx::query_as!(Note, "SELECT id, text FROM notes WHERE id = $1", id)
-        .fetch_optional(executor)
-        .await
-        .map_err(crate::Error::DbError)?
+    match sqlx::query_as!(
+        Note,
+        r#"SELECT id, text, owner_id, created_at, updated_at, version, format AS "format: NoteFormat", note_tag_names(id) AS "tags!" FROM notes WHERE id = $1 AND note_access(id, $2) IS NOT NULL AND deleted_at IS NULL"#,
+        id,
+        user_id
+    )
+    .fetch_optional(executor)
+    .await
+    .map_err(crate::Error::DbError)?
     {
         Some(note) => Ok(note),
         None => Err(crate::Error::NoRecordFound),
     }
 }
 
+/// Loads a note that the user owns or that is shared with them along with the cached HTML rendering of its text.
+///
+/// The HTML is [`Option::None`] if the note hasn't been rendered since its text or format last changed, in which case it is up to the caller to render and cache it (see [`cache_html`]).
+pub async fn load_with_html(
+    id: Uuid,
+    user_id: Uuid,
+    executor: impl sqlx::Executor<'_, Database = Postgres>,
+) -> Result<(Note, Option<String>), crate::Error> {
+    let record = sqlx::query!(
+        r#"
+        SELECT id, text, owner_id, created_at, updated_at, version, format AS "format: NoteFormat", note_tag_names(id) AS "tags!", html
+        FROM notes
+        WHERE id = $1 AND note_access(id, $2) IS NOT NULL AND deleted_at IS NULL
+        "#,
+        id,
+        user_id
+    )
+    .fetch_optional(executor)
+    .await
+    .map_err(crate::Error::DbError)?
+    .ok_or(crate::Error::NoRecordFound)?;
+
+    let note = Note {
+        id: record.id,
+        text: record.text,
+        owner_id: record.owner_id,
+        created_at: record.created_at,
+        updated_at: record.updated_at,
+        version: record.version,
+        format: record.format,
+        tags: record.tags,
+    };
+    Ok((note, record.html))
+}
+
+/// Caches the HTML rendering of the text of a note as of the passed version.
+///
+/// Nothing is cached if the note has been changed since so that a rendering of an outdated text can't replace the invalidation done on update.
+pub async fn cache_html(
+    id: Uuid,
+    version: i32,
+    html: &str,
+    executor: impl sqlx::Executor<'_, Database = Postgres>,
+) -> Result<(), crate::Error> {
+    sqlx::query!(
+        "UPDATE notes SET html = $3 WHERE id = $1 AND version = $2",
+        id,
+        version,
+        html
+    )
+    .execute(executor)
+    .await
+    .map_err(crate::Error::DbError)?;
+
+    Ok(())
+}
+
+/// Creates a note for the owner along with its tags.
+///
+/// Tags that the owner doesn't have yet are created. The note and its tags are written in a single transaction.
 pub async fn create(
     note: NoteChangeset,
-    executor: impl sqlx::Executor<'_, Database = Postgres>,
+    owner_id: Uuid,
+    db: impl sqlx::Acquire<'_, Database = Postgres>,
 ) -> Result<Note, crate::Error> {
     note.validate()?;
 
+    let mut tx = db.begin().await.map_err(crate::Error::DbError)?;
+
+    let format = note.format.unwrap_or_default();
     let record = sqlx::query!(
-        "INSERT INTO notes (text) VALUES ($1) RETURNING id",
+        "INSERT INTO notes (text, owner_id, format) VALUES ($1, $2, $3) RETURNING id, created_at, updated_at, version",
         note.text,
+        owner_id,
+        format as NoteFormat,
     )
-    .fetch_one(executor)
+    .fetch_one(&mut *tx)
     .await
     .map_err(crate::Error::DbError)?;
 
+    let tags = tags::replace_for_note(
+        record.id,
+        owner_id,
+        note.tags.as_deref().unwrap_or_default(),
+        &mut tx,
+    )
+    .await?;
+
+    tx.commit().await.map_err(crate::Error::DbError)?;
+
     Ok(Note {
         id: record.id,
         text: note.text,
+        owner_id,
+        created_at: record.created_at,
+        updated_at: record.updated_at,
+        version: record.version,
+        format,
+        tags,
     })
 }
 
+/// Updates the text of a note that the user owns or that is shared with them for writing, setting its `updated_at` to the current time and incrementing its version.
+///
+/// The note's tags are replaced in the same transaction if the changeset contains tags. If the note is only shared with the user for reading, [`crate::Error::Forbidden`] is returned. If `expected_versions` is passed, the note is only updated if its current version is one of them and [`crate::Error::PreconditionFailed`] is returned otherwise.
 pub async fn update(
     id: Uuid,
     note: NoteChangeset,
-    executor: impl sqlx::Executor<'_, Database = Postgres>,
+    user_id: Uuid,
+    expected_versions: Option<&[i32]>,
+    db: impl sqlx::Acquire<'_, Database = Postgres>,
 ) -> Result<Note, crate::Error> {
     note.validate()?;
 
-    match sqlx::query!(
-        "UPDATE notes SET text = $1 WHERE id = $2 RETURNING id",
-        note.text,
-        id
+    let patch = NotePatch {
+        text: Some(note.text),
+        tags: note.tags,
+        format: note.format,
+    };
+    self::patch(id, patch, user_id, expected_versions, db).await
+}
+
+/// A partial update of a note as described by a JSON Merge Patch (see [RFC 7396](https://www.rfc-editor.org/rfc/rfc7396)).
+///
+/// Fields that are missing are left unchanged. Since a note can't be without text, `text` must not be `null`, while a `null` for `tags` removes all tags from the note.
+#[derive(Deserialize, Validate, Clone, Default, Debug, utoipa::ToSchema)]
+pub struct NotePatch {
+    /// The new text of the note.
+    #[serde(default, deserialize_with = "deserialize_present")]
+    #[validate(length(min = 1))]
+    pub text: Option<String>,
+    /// The names of the tags the note should have, replacing its current tags.
+    #[serde(default, deserialize_with = "deserialize_null_as_empty")]
+    #[validate(length(max = 20), custom(function = "validate_tag_names"))]
+    pub tags: Option<Vec<String>>,
+    /// How the text of the note is formatted.
+    #[serde(default, deserialize_with = "deserialize_present")]
+    pub format: Option<NoteFormat>,
+}
+
+/// Deserializes a field that may be missing but must not be `null`.
+fn deserialize_present<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    T::deserialize(deserializer).map(Some)
+}
+
+/// Deserializes a list that may be missing and is empty if it is `null`.
+fn deserialize_null_as_empty<'de, D, T>(deserializer: D) -> Result<Option<Vec<T>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<Vec<T>>::deserialize(deserializer).map(|list| Some(list.unwrap_or_default()))
+}
+
+#[derive(sqlx::FromRow)]
+struct PatchedRecord {
+    owner_id: Uuid,
+    created_at: DateTime<Utc>,
+    writable: bool,
+    text: Option<String>,
+    updated_at: Option<DateTime<Utc>>,
+    version: Option<i32>,
+    format: Option<NoteFormat>,
+    tags: Vec<String>,
+}
+
+/// Applies a partial update to a note that the user owns or that is shared with them for writing, setting its `updated_at` to the current time and incrementing its version.
+///
+/// Only the columns of the fields present in the patch are written. The note's tags are replaced in the same transaction if the patch contains tags – they are always the tags of the note's owner, regardless of who updates it. If the note is only shared with the user for reading, [`crate::Error::Forbidden`] is returned. If `expected_versions` is passed, the note is only updated if its current version is one of them and [`crate::Error::PreconditionFailed`] is returned otherwise.
+pub async fn patch(
+    id: Uuid,
+    patch: NotePatch,
+    user_id: Uuid,
+    expected_versions: Option<&[i32]>,
+    db: impl sqlx::Acquire<'_, Database = Postgres>,
+) -> Result<Note, crate::Error> {
+    patch.validate()?;
+
+    let mut tx = db.begin().await.map_err(crate::Error::DbError)?;
+
+    // The target row is locked so that concurrent updates expecting the same version can't both
+    // succeed.
+    let mut query = QueryBuilder::<Postgres>::new("WITH access AS (SELECT note_access(");
+    query
+        .push_bind(id)
+        .push(", ")
+        .push_bind(user_id)
+        .push(") AS permission), ")
+        .push("target AS (SELECT id, owner_id, created_at, version, access.permission = 'write' AS writable FROM notes, access WHERE id = ")
+        .push_bind(id)
+        .push(" AND access.permission IS NOT NULL AND deleted_at IS NULL FOR UPDATE OF notes), updated AS (UPDATE notes SET updated_at = now(), version = notes.version + 1");
+    if let Some(text) = &patch.text {
+        query.push(", text = ").push_bind(text);
+    }
+    if let Some(format) = patch.format {
+        query.push(", format = ").push_bind(format);
+    }
+    // The cached HTML rendering is outdated once the text or format changes.
+    if patch.text.is_some() || patch.format.is_some() {
+        query.push(", html = NULL");
+    }
+    query
+        .push(" FROM target WHERE notes.id = target.id AND target.writable AND (")
+        .push_bind(expected_versions)
+        .push("::int4[] IS NULL OR target.version = ANY(")
+        .push_bind(expected_versions)
+        .push(")) RETURNING notes.text, notes.updated_at, notes.version, notes.format) ")
+        .push("SELECT target.owner_id, target.created_at, target.writable, updated.text, updated.updated_at, updated.version, updated.format, note_tag_names(target.id) AS tags ")
+        .push("FROM target LEFT JOIN updated ON true");
+
+    let record: PatchedRecord = query
+        .build_query_as()
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(crate::Error::DbError)?
+        .ok_or(crate::Error::NoRecordFound)?;
+
+    if !record.writable {
+        return Err(crate::Error::Forbidden);
+    }
+    let (Some(text), Some(updated_at), Some(version), Some(format)) = (
+        record.text,
+        record.updated_at,
+        record.version,
+        record.format,
+    ) else {
+        return Err(crate::Error::PreconditionFailed);
+    };
+
+    let tags = match patch.tags {
+        Some(names) => tags::replace_for_note(id, record.owner_id, &names, &mut tx).await?,
+        None => record.tags,
+    };
+
+    tx.commit().await.map_err(crate::Error::DbError)?;
+
+    Ok(Note {
+        id,
+        text,
+        owner_id: record.owner_id,
+        created_at: record.created_at,
+        updated_at,
+        version,
+        format,
+        tags,
+    })
+}
+
+/// Moves a note of the owner to the trash.
+///
+/// Trashed notes are excluded from all other functions in this module except for [`load_trash`] and [`restore`] until they are purged (see [`purge_trashed`]). If `expected_versions` is passed, the note is only trashed if its current version is one of them and [`crate::Error::PreconditionFailed`] is returned otherwise.
+pub async fn delete(
+    id: Uuid,
+    owner_id: Uuid,
+    expected_versions: Option<&[i32]>,
+    executor: impl sqlx::Executor<'_, Database = Postgres>,
+) -> Result<(), crate::Error> {
+    let record = sqlx::query!(
+        r#"
+        WITH target AS (
+            SELECT id, version FROM notes
+            WHERE id = $1 AND owner_id = $2 AND deleted_at IS NULL
+            FOR UPDATE
+        ),
+        trashed AS (
+            UPDATE notes SET deleted_at = now()
+            FROM target
+            WHERE notes.id = target.id AND ($3::int4[] IS NULL OR target.version = ANY($3))
+            RETURNING notes.id
+        )
+        SELECT trashed.id AS "trashed_id?"
+        FROM target LEFT JOIN trashed ON true
+        "#,
+        id,
+        owner_id,
+        expected_versions as Option<&[i32]>
     )
     .fetch_optional(executor)
     .await
     .map_err(crate::Error::DbError)?
-    {
-        Some(record) => Ok(Note {
-            id: record.id,
-            text: note.text,
-        }),
-        None => Err(crate::Error::NoRecordFound),
+    .ok_or(crate::Error::NoRecordFound)?;
+
+    match record.trashed_id {
+        Some(_) => Ok(()),
+        None => Err(crate::Error::PreconditionFailed),
     }
 }
 
-pub async fn delete(
+/// A note in the trash.
+#[derive(Serialize, Debug, utoipa::ToSchema, Deserialize)]
+pub struct TrashedNote {
+    #[serde(flatten)]
+    pub note: Note,
+    /// The time the note was moved to the trash.
+    pub deleted_at: DateTime<Utc>,
+}
+
+/// Loads all notes of the owner that are in the trash, most recently trashed first.
+pub async fn load_trash(
+    owner_id: Uuid,
+    executor: impl sqlx::Executor<'_, Database = Postgres>,
+) -> Result<Vec<TrashedNote>, crate::Error> {
+    let records = sqlx::query!(
+        r#"
+        SELECT id, text, owner_id, created_at, updated_at, version, format AS "format: NoteFormat", note_tag_names(id) AS "tags!", deleted_at AS "deleted_at!"
+        FROM notes
+        WHERE owner_id = $1 AND deleted_at IS NOT NULL
+        ORDER BY deleted_at DESC, id DESC
+        "#,
+        owner_id
+    )
+    .fetch_all(executor)
+    .await?;
+
+    Ok(records
+        .into_iter()
+        .map(|record| TrashedNote {
+            note: Note {
+                id: record.id,
+                text: record.text,
+                owner_id: record.owner_id,
+                created_at: record.created_at,
+                updated_at: record.updated_at,
+                version: record.version,
+                format: record.format,
+                tags: record.tags,
+            },
+            deleted_at: record.deleted_at,
+        })
+        .collect())
+}
+
+/// Restores a note of the owner from the trash.
+///
+/// If the note is not in the trash, [`crate::Error::NoRecordFound`] is returned.
+pub async fn restore(
     id: Uuid,
+    owner_id: Uuid,
     executor: impl sqlx::Executor<'_, Database = Postgres>,
-) -> Result<(), crate::Error> {
-    match sqlx::query!("DELETE FROM notes WHERE id = $1 RETURNING id", id)
-        .fetch_optional(executor)
-        .await
-        .map_err(crate::Error::DbError)?
+) -> Result<Note, crate::Error> {
+    match sqlx::query_as!(
+        Note,
+        r#"
+        UPDATE notes SET deleted_at = NULL
+        WHERE id = $1 AND owner_id = $2 AND deleted_at IS NOT NULL
+        RETURNING id, text, owner_id, created_at, updated_at, version, format AS "format: NoteFormat", note_tag_names(id) AS "tags!"
+        "#,
+        id,
+        owner_id
+    )
+    .fetch_optional(executor)
+    .await
+    .map_err(crate::Error::DbError)?
     {
-        Some(_) => Ok(()),
+        Some(note) => Ok(note),
         None => Err(crate::Error::NoRecordFound),
     }
 }
+
+/// The notes removed by [`purge_trashed`].
+#[derive(Debug)]
+pub struct PurgedNotes {
+    /// The number of deleted notes.
+    pub count: u64,
+    /// The keys of the blobs of the deleted notes' attachments (see [`super::attachments`]), which are not referenced anymore.
+    pub blob_keys: Vec<String>,
+}
+
+/// Permanently deletes all notes that were moved to the trash before the passed time along with their attachments.
+///
+/// Only the attachments' records are deleted – the caller is responsible for deleting the blobs with the returned keys.
+pub async fn purge_trashed(
+    before: DateTime<Utc>,
+    executor: impl sqlx::Executor<'_, Database = Postgres>,
+) -> Result<PurgedNotes, crate::Error> {
+    // All parts of the statement see the same snapshot, so the attachments removed by the
+    // cascading delete can still be selected.
+    let record = sqlx::query!(
+        r#"
+        WITH purged AS (DELETE FROM notes WHERE deleted_at < $1 RETURNING id)
+        SELECT
+            (SELECT count(*) FROM purged) AS "count!",
+            ARRAY(
+                SELECT attachments.blob_key FROM attachments JOIN purged ON purged.id = attachments.note_id
+            ) AS "blob_keys!"
+        "#,
+        before
+    )
+    .fetch_one(executor)
+    .await
+    .map_err(crate::Error::DbError)?;
+
+    Ok(PurgedNotes {
+        count: record.count as u64,
+        blob_keys: record.blob_keys,
+    })
+}
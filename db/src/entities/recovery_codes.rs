@@ -0,0 +1,79 @@
+use sqlx::Postgres;
+use uuid::Uuid;
+
+/// Replaces all recovery codes of a user with the codes with the passed hashes.
+///
+/// Codes are stored as hashes only so that they can't be used if the database leaks. Codes that have not been used yet are invalidated as well.
+pub async fn replace(
+    user_id: Uuid,
+    code_hashes: &[String],
+    db: impl sqlx::Acquire<'_, Database = Postgres>,
+) -> Result<(), crate::Error> {
+    let mut tx = db.begin().await.map_err(crate::Error::DbError)?;
+
+    sqlx::query!("DELETE FROM recovery_codes WHERE user_id = $1", user_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(crate::Error::DbError)?;
+    sqlx::query!(
+        "INSERT INTO recovery_codes (user_id, code_hash) SELECT $1, unnest($2::varchar[])",
+        user_id,
+        code_hashes
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(crate::Error::DbError)?;
+
+    tx.commit().await.map_err(crate::Error::DbError)?;
+
+    Ok(())
+}
+
+/// Marks the recovery code with the passed hash as used, returning whether it was valid.
+///
+/// Every code can only be used once – marking a code that has been used before fails, also when two requests try to use the same code concurrently.
+pub async fn consume(
+    user_id: Uuid,
+    code_hash: &str,
+    executor: impl sqlx::Executor<'_, Database = Postgres>,
+) -> Result<bool, crate::Error> {
+    let result = sqlx::query!(
+        "UPDATE recovery_codes SET used_at = now() WHERE user_id = $1 AND code_hash = $2 AND used_at IS NULL",
+        user_id,
+        code_hash
+    )
+    .execute(executor)
+    .await
+    .map_err(crate::Error::DbError)?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Counts the recovery codes of a user that have not been used yet.
+pub async fn count_unused(
+    user_id: Uuid,
+    executor: impl sqlx::Executor<'_, Database = Postgres>,
+) -> Result<i64, crate::Error> {
+    let count = sqlx::query_scalar!(
+        r#"SELECT count(*) AS "count!" FROM recovery_codes WHERE user_id = $1 AND used_at IS NULL"#,
+        user_id
+    )
+    .fetch_one(executor)
+    .await
+    .map_err(crate::Error::DbError)?;
+
+    Ok(count)
+}
+
+/// Deletes all recovery codes of a user.
+pub async fn delete_all(
+    user_id: Uuid,
+    executor: impl sqlx::Executor<'_, Database = Postgres>,
+) -> Result<(), crate::Error> {
+    sqlx::query!("DELETE FROM recovery_codes WHERE user_id = $1", user_id)
+        .execute(executor)
+        .await
+        .map_err(crate::Error::DbError)?;
+
+    Ok(())
+}
@@ -0,0 +1,127 @@
+use chrono::{DateTime, Utc};
+use sqlx::Postgres;
+use uuid::Uuid;
+
+/// A refresh token record.
+///
+/// Only the SHA-256 hash of the token is stored. Every token belongs to a family that starts with a login – each rotation adds a new token to the family while marking the previous one as rotated.
+#[derive(Debug, Clone)]
+pub struct RefreshToken {
+    /// The id of the record.
+    pub id: Uuid,
+    /// The id of the user the token was issued to.
+    pub user_id: Uuid,
+    /// The id shared by all tokens descending from the same login.
+    pub family_id: Uuid,
+    /// The hex encoded SHA-256 hash of the token.
+    pub token_hash: String,
+    /// The time after which the token cannot be used anymore.
+    pub expires_at: DateTime<Utc>,
+    /// The time the token was exchanged for a new one, if it was.
+    pub rotated_at: Option<DateTime<Utc>>,
+    /// The time the token's family was revoked, if it was.
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+/// Stores a new refresh token.
+pub async fn create(
+    user_id: Uuid,
+    family_id: Uuid,
+    token_hash: &str,
+    expires_at: DateTime<Utc>,
+    executor: impl sqlx::Executor<'_, Database = Postgres>,
+) -> Result<RefreshToken, crate::Error> {
+    let record = sqlx::query!(
+        "INSERT INTO refresh_tokens (user_id, family_id, token_hash, expires_at) VALUES ($1, $2, $3, $4) RETURNING id",
+        user_id,
+        family_id,
+        token_hash,
+        expires_at,
+    )
+    .fetch_one(executor)
+    .await
+    .map_err(crate::Error::DbError)?;
+
+    Ok(RefreshToken {
+        id: record.id,
+        user_id,
+        family_id,
+        token_hash: token_hash.to_string(),
+        expires_at,
+        rotated_at: None,
+        revoked_at: None,
+    })
+}
+
+/// Loads a refresh token by its hash.
+///
+/// Rotated and revoked tokens are returned as well so that callers can detect reuse.
+pub async fn load_by_hash(
+    token_hash: &str,
+    executor: impl sqlx::Executor<'_, Database = Postgres>,
+) -> Result<RefreshToken, crate::Error> {
+    match sqlx::query_as!(
+        RefreshToken,
+        "SELECT id, user_id, family_id, token_hash, expires_at, rotated_at, revoked_at FROM refresh_tokens WHERE token_hash = $1",
+        token_hash
+    )
+    .fetch_optional(executor)
+    .await
+    .map_err(crate::Error::DbError)?
+    {
+        Some(token) => Ok(token),
+        None => Err(crate::Error::NoRecordFound),
+    }
+}
+
+/// Marks a refresh token as rotated.
+///
+/// This only succeeds for tokens that have neither been rotated nor revoked before so that two concurrent requests cannot both rotate the same token. [`crate::Error::NoRecordFound`] is returned otherwise.
+pub async fn rotate(
+    id: Uuid,
+    executor: impl sqlx::Executor<'_, Database = Postgres>,
+) -> Result<(), crate::Error> {
+    match sqlx::query!(
+        "UPDATE refresh_tokens SET rotated_at = now() WHERE id = $1 AND rotated_at IS NULL AND revoked_at IS NULL RETURNING id",
+        id
+    )
+    .fetch_optional(executor)
+    .await
+    .map_err(crate::Error::DbError)?
+    {
+        Some(_) => Ok(()),
+        None => Err(crate::Error::NoRecordFound),
+    }
+}
+
+/// Revokes all tokens of a family that haven't been revoked yet.
+pub async fn revoke_family(
+    family_id: Uuid,
+    executor: impl sqlx::Executor<'_, Database = Postgres>,
+) -> Result<(), crate::Error> {
+    sqlx::query!(
+        "UPDATE refresh_tokens SET revoked_at = now() WHERE family_id = $1 AND revoked_at IS NULL",
+        family_id
+    )
+    .execute(executor)
+    .await
+    .map_err(crate::Error::DbError)?;
+
+    Ok(())
+}
+
+/// Revokes all tokens of all families of the passed user.
+pub async fn revoke_all_for_user(
+    user_id: Uuid,
+    executor: impl sqlx::Executor<'_, Database = Postgres>,
+) -> Result<(), crate::Error> {
+    sqlx::query!(
+        "UPDATE refresh_tokens SET revoked_at = now() WHERE user_id = $1 AND revoked_at IS NULL",
+        user_id
+    )
+    .execute(executor)
+    .await
+    .map_err(crate::Error::DbError)?;
+
+    Ok(())
+}
@@ -0,0 +1,48 @@
+use chrono::{DateTime, Utc};
+use sqlx::Postgres;
+use uuid::Uuid;
+
+/// Adds the token with the passed `jti` claim to the revocation list.
+///
+/// The entry only needs to be kept until the token would have expired anyway, after which it is removed by [`delete_expired`]. Revoking a token more than once is a no-op.
+pub async fn revoke(
+    jti: Uuid,
+    expires_at: DateTime<Utc>,
+    executor: impl sqlx::Executor<'_, Database = Postgres>,
+) -> Result<(), crate::Error> {
+    sqlx::query!(
+        "INSERT INTO revoked_tokens (jti, expires_at) VALUES ($1, $2) ON CONFLICT (jti) DO NOTHING",
+        jti,
+        expires_at,
+    )
+    .execute(executor)
+    .await
+    .map_err(crate::Error::DbError)?;
+
+    Ok(())
+}
+
+/// Checks whether the token with the passed `jti` claim has been revoked.
+pub async fn is_revoked(
+    jti: Uuid,
+    executor: impl sqlx::Executor<'_, Database = Postgres>,
+) -> Result<bool, crate::Error> {
+    let record = sqlx::query!("SELECT jti FROM revoked_tokens WHERE jti = $1", jti)
+        .fetch_optional(executor)
+        .await
+        .map_err(crate::Error::DbError)?;
+
+    Ok(record.is_some())
+}
+
+/// Removes all entries for tokens that have expired, returning the number of removed entries.
+pub async fn delete_expired(
+    executor: impl sqlx::Executor<'_, Database = Postgres>,
+) -> Result<u64, crate::Error> {
+    let result = sqlx::query!("DELETE FROM revoked_tokens WHERE expires_at <= now()")
+        .execute(executor)
+        .await
+        .map_err(crate::Error::DbError)?;
+
+    Ok(result.rows_affected())
+}
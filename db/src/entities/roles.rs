@@ -0,0 +1,105 @@
+use sqlx::Postgres;
+use uuid::Uuid;
+
+/// The name of the role every user is assigned when signing up.
+pub const DEFAULT_ROLE: &str = "user";
+
+/// The name of the role that is granted all permissions (see `db/seeds.sql`).
+pub const ADMIN_ROLE: &str = "admin";
+
+/// Assigns the role with the passed name to a user.
+///
+/// Assigning a role the user already has is a no-op. If no role exists for the name, [`crate::Error::NoRecordFound`] is returned.
+pub async fn assign(
+    user_id: Uuid,
+    role_name: &str,
+    executor: impl sqlx::Executor<'_, Database = Postgres>,
+) -> Result<(), crate::Error> {
+    match sqlx::query!(
+        r#"
+        WITH role AS (SELECT id FROM roles WHERE name = $2),
+        assigned AS (
+            INSERT INTO user_roles (user_id, role_id)
+            SELECT $1, id FROM role
+            ON CONFLICT DO NOTHING
+        )
+        SELECT id FROM role
+        "#,
+        user_id,
+        role_name
+    )
+    .fetch_optional(executor)
+    .await
+    .map_err(crate::Error::DbError)?
+    {
+        Some(_) => Ok(()),
+        None => Err(crate::Error::NoRecordFound),
+    }
+}
+
+/// Removes the role with the passed name from a user.
+pub async fn unassign(
+    user_id: Uuid,
+    role_name: &str,
+    executor: impl sqlx::Executor<'_, Database = Postgres>,
+) -> Result<(), crate::Error> {
+    sqlx::query!(
+        "DELETE FROM user_roles USING roles WHERE user_roles.role_id = roles.id AND user_roles.user_id = $1 AND roles.name = $2",
+        user_id,
+        role_name
+    )
+    .execute(executor)
+    .await
+    .map_err(crate::Error::DbError)?;
+
+    Ok(())
+}
+
+/// Checks whether any of the roles assigned to a user grants the permission with the passed name.
+pub async fn has_permission(
+    user_id: Uuid,
+    permission: &str,
+    executor: impl sqlx::Executor<'_, Database = Postgres>,
+) -> Result<bool, crate::Error> {
+    let record = sqlx::query!(
+        r#"
+        SELECT EXISTS (
+            SELECT 1
+            FROM user_roles
+            JOIN role_permissions ON role_permissions.role_id = user_roles.role_id
+            JOIN permissions ON permissions.id = role_permissions.permission_id
+            WHERE user_roles.user_id = $1 AND permissions.name = $2
+        ) AS "granted!"
+        "#,
+        user_id,
+        permission
+    )
+    .fetch_one(executor)
+    .await
+    .map_err(crate::Error::DbError)?;
+
+    Ok(record.granted)
+}
+
+/// Loads the names of all permissions granted to a user by any of their roles, sorted by name.
+pub async fn permissions_of(
+    user_id: Uuid,
+    executor: impl sqlx::Executor<'_, Database = Postgres>,
+) -> Result<Vec<String>, crate::Error> {
+    let permissions = sqlx::query_scalar!(
+        r#"
+        SELECT DISTINCT permissions.name
+        FROM user_roles
+        JOIN role_permissions ON role_permissions.role_id = user_roles.role_id
+        JOIN permissions ON permissions.id = role_permissions.permission_id
+        WHERE user_roles.user_id = $1
+        ORDER BY permissions.name
+        "#,
+        user_id
+    )
+    .fetch_all(executor)
+    .await
+    .map_err(crate::Error::DbError)?;
+
+    Ok(permissions)
+}
@@ -0,0 +1,109 @@
+use serde::{Deserialize, Serialize};
+use sqlx::{PgConnection, Postgres};
+use uuid::Uuid;
+
+/// The maximum number of characters of a tag name.
+pub const MAX_NAME_LENGTH: usize = 32;
+
+/// A tag along with how many notes it is attached to.
+#[derive(Serialize, Debug, utoipa::ToSchema, Deserialize)]
+pub struct TagUsage {
+    /// The name of the tag.
+    pub name: String,
+    /// The number of notes the tag is attached to, not counting notes in the trash.
+    pub note_count: i64,
+}
+
+/// Loads all tags of the owner that are attached to at least one note, sorted by name.
+pub async fn load_all(
+    owner_id: Uuid,
+    executor: impl sqlx::Executor<'_, Database = Postgres>,
+) -> Result<Vec<TagUsage>, crate::Error> {
+    let tags = sqlx::query_as!(
+        TagUsage,
+        r#"
+        SELECT tags.name, count(*) AS "note_count!"
+        FROM tags
+        JOIN note_tags ON note_tags.tag_id = tags.id
+        JOIN notes ON notes.id = note_tags.note_id
+        WHERE tags.owner_id = $1 AND notes.deleted_at IS NULL
+        GROUP BY tags.id
+        ORDER BY lower(tags.name)
+        "#,
+        owner_id
+    )
+    .fetch_all(executor)
+    .await?;
+
+    Ok(tags)
+}
+
+/// Replaces the tags of a note of the owner with the tags of the passed names, returning the names of the note's tags sorted alphabetically.
+///
+/// Tags that the owner doesn't have yet are created. Names are compared case-insensitively, so the name of an existing tag keeps its spelling. This runs several statements and is meant to be called in the same transaction that creates or updates the note.
+pub(crate) async fn replace_for_note(
+    note_id: Uuid,
+    owner_id: Uuid,
+    names: &[String],
+    connection: &mut PgConnection,
+) -> Result<Vec<String>, crate::Error> {
+    let names = normalize(names);
+
+    // Updating the conflicting row instead of doing nothing makes the statement return the ids of
+    // existing tags as well.
+    let tags = sqlx::query!(
+        r#"
+        INSERT INTO tags (owner_id, name)
+        SELECT $1, name FROM unnest($2::text[]) AS name
+        ON CONFLICT (owner_id, lower(name)) DO UPDATE SET name = tags.name
+        RETURNING id, name
+        "#,
+        owner_id,
+        &names
+    )
+    .fetch_all(&mut *connection)
+    .await
+    .map_err(crate::Error::DbError)?;
+    let tag_ids: Vec<Uuid> = tags.iter().map(|tag| tag.id).collect();
+
+    sqlx::query!(
+        "DELETE FROM note_tags WHERE note_id = $1 AND tag_id <> ALL($2)",
+        note_id,
+        &tag_ids
+    )
+    .execute(&mut *connection)
+    .await
+    .map_err(crate::Error::DbError)?;
+
+    sqlx::query!(
+        r#"
+        INSERT INTO note_tags (note_id, tag_id)
+        SELECT $1, tag_id FROM unnest($2::uuid[]) AS tag_id
+        ON CONFLICT DO NOTHING
+        "#,
+        note_id,
+        &tag_ids
+    )
+    .execute(&mut *connection)
+    .await
+    .map_err(crate::Error::DbError)?;
+
+    let mut names: Vec<String> = tags.into_iter().map(|tag| tag.name).collect();
+    names.sort_by_key(|name| name.to_lowercase());
+    Ok(names)
+}
+
+/// Trims the passed tag names and removes empty names as well as names that only differ in case from a previous one.
+pub(crate) fn normalize(names: &[String]) -> Vec<String> {
+    let mut normalized: Vec<String> = vec![];
+    for name in names.iter().map(|name| name.trim()) {
+        if !name.is_empty()
+            && !normalized
+                .iter()
+                .any(|other| other.to_lowercase() == name.to_lowercase())
+        {
+            normalized.push(name.to_string());
+        }
+    }
+    normalized
+}
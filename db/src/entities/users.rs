@@ -1,6 +1,8 @@
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use sqlx::Postgres;
 use uuid::Uuid;
+use validator::{ValidationError, ValidationErrors};
 
 /// A user record.
 #[derive(Serialize, Debug, Clone, Deserialize)]
@@ -11,45 +13,59 @@ pub struct User {
     pub name: String,
     pub pass: String,
     pub token: String,
+    /// The generation of the user's tokens – tokens issued for earlier generations have been revoked.
+    pub token_generation: i32,
 }
 
-/// Loads a user based on the passed token.
+/// Loads a user based on the passed id.
 ///
-/// If no user exists for the token, [`Option::None`] is returned, otherwise `Option::Some(User)` is returned.
-pub async fn load_with_token(
-    token: &str,
+/// If no user exists for the id, [`Option::None`] is returned, otherwise `Option::Some(User)` is returned.
+pub async fn load(
+    id: Uuid,
     executor: impl sqlx::Executor<'_, Database = Postgres>,
 ) -> Result<Option<User>, anyhow::Error> {
     Ok(sqlx::query_as!(
         User,
-        "SELECT id, name, pass, token FROM users WHERE token = $1",
-        token
+        "SELECT id, name, pass, token, token_generation FROM users WHERE id = $1",
+        id
     )
     .fetch_optional(executor)
     .await?)
 }
 
+/// Loads a user based on the passed name.
+///
+/// Names are compared case-insensitively. If no user exists for the name, [`Option::None`] is returned, otherwise `Option::Some(User)` is returned.
 pub async fn find_user_by_name(
     name: &str,
     executor: impl sqlx::Executor<'_, Database = Postgres>,
 ) -> Result<Option<User>, anyhow::Error> {
     Ok(sqlx::query_as!(
         User,
-        "SELECT id, name, pass, token FROM users WHERE name = $1",
+        "SELECT id, name, pass, token, token_generation FROM users WHERE lower(name) = lower($1)",
         name
     )
     .fetch_optional(executor)
     .await?)
 }
 
+/// Creates a user with the passed name and password hash.
+///
+/// Names are unique regardless of case – if the name is already taken, [`crate::Error::Conflict`] is returned.
 pub async fn insert_user(
     name: &str,
     hashed_pass: &str, // Password is already hashed
     executor: impl sqlx::Executor<'_, Database = Postgres>,
-) -> Result<User, anyhow::Error> {
-    // Validate inputs (optional but recommended)
-    if name.is_empty() || hashed_pass.is_empty() {
-        return Err(anyhow::anyhow!("Name and password must not be empty"));
+) -> Result<User, crate::Error> {
+    let mut errors = ValidationErrors::new();
+    if name.is_empty() {
+        errors.add("name", ValidationError::new("length"));
+    }
+    if hashed_pass.is_empty() {
+        errors.add("pass", ValidationError::new("length"));
+    }
+    if !errors.is_empty() {
+        return Err(crate::Error::ValidationError(errors));
     }
     let token = "random_text";
     // Insert the user and return the newly created record
@@ -58,14 +74,263 @@ pub async fn insert_user(
         r#"
         INSERT INTO users (name, pass, token)
         VALUES ($1, $2, $3)
-        RETURNING id, name, pass, token
+        RETURNING id, name, pass, token, token_generation
         "#,
         name,
         hashed_pass,
         &token
     )
     .fetch_one(executor)
-    .await?;
+    .await
+    .map_err(crate::Error::conflict_on("name"))?;
+
+    Ok(user)
+}
+
+/// Replaces the password hash of a user, e.g. to upgrade it to a stronger algorithm.
+pub async fn update_password_hash(
+    id: Uuid,
+    hashed_pass: &str,
+    executor: impl sqlx::Executor<'_, Database = Postgres>,
+) -> Result<(), crate::Error> {
+    sqlx::query!("UPDATE users SET pass = $2 WHERE id = $1", id, hashed_pass)
+        .execute(executor)
+        .await
+        .map_err(crate::Error::DbError)?;
+
+    Ok(())
+}
+
+/// Sets the email address of a user, which then has to be verified again.
+///
+/// Any number of users may set the same address as long as nobody has verified it (see [`verify_email`]).
+pub async fn set_email(
+    id: Uuid,
+    email: &str,
+    executor: impl sqlx::Executor<'_, Database = Postgres>,
+) -> Result<(), crate::Error> {
+    sqlx::query!(
+        "UPDATE users SET email = $2, email_verified_at = NULL WHERE id = $1",
+        id,
+        email
+    )
+    .execute(executor)
+    .await
+    .map_err(crate::Error::DbError)?;
+
+    Ok(())
+}
+
+/// Loads a user based on their verified email address.
+///
+/// Addresses are compared case-insensitively. If no user has verified the address, [`Option::None`] is returned.
+pub async fn find_user_by_verified_email(
+    email: &str,
+    executor: impl sqlx::Executor<'_, Database = Postgres>,
+) -> Result<Option<User>, crate::Error> {
+    let user = sqlx::query_as!(
+        User,
+        r#"
+        SELECT id, name, pass, token, token_generation FROM users
+        WHERE lower(email) = lower($1) AND email_verified_at IS NOT NULL
+        "#,
+        email
+    )
+    .fetch_optional(executor)
+    .await
+    .map_err(crate::Error::DbError)?;
 
     Ok(user)
 }
+
+/// Marks the email address of a user as verified.
+///
+/// If the user's address has changed since the passed address was to be verified, [`crate::Error::NoRecordFound`] is returned. Verified addresses are unique regardless of case – if another user has verified the address in the meantime, [`crate::Error::Conflict`] is returned. Otherwise, the address is removed from all other users that set it without verifying it.
+pub async fn verify_email(
+    id: Uuid,
+    email: &str,
+    db: impl sqlx::Acquire<'_, Database = Postgres>,
+) -> Result<(), crate::Error> {
+    let mut tx = db.begin().await.map_err(crate::Error::DbError)?;
+
+    let result = sqlx::query!(
+        r#"
+        UPDATE users SET email_verified_at = coalesce(email_verified_at, now())
+        WHERE id = $1 AND lower(email) = lower($2)
+        "#,
+        id,
+        email
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(crate::Error::conflict_on("email"))?;
+
+    if result.rows_affected() == 0 {
+        return Err(crate::Error::NoRecordFound);
+    }
+
+    sqlx::query!(
+        r#"
+        UPDATE users SET email = NULL
+        WHERE id <> $1 AND lower(email) = lower($2) AND email_verified_at IS NULL
+        "#,
+        id,
+        email
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(crate::Error::DbError)?;
+
+    tx.commit().await.map_err(crate::Error::DbError)?;
+
+    Ok(())
+}
+
+/// The TOTP second factor of a user.
+#[derive(Debug, Clone)]
+pub struct Totp {
+    /// The base32 encoded shared secret.
+    pub secret: String,
+    /// The time the user confirmed enrollment, if they did. The second factor is only required for logging in once it is set.
+    pub enabled_at: Option<DateTime<Utc>>,
+    /// The last time step a code was accepted for so that codes can't be used twice.
+    pub last_used_step: Option<i64>,
+}
+
+/// Loads the TOTP second factor of a user.
+///
+/// If the user has not started enrolling, [`Option::None`] is returned.
+pub async fn load_totp(
+    id: Uuid,
+    executor: impl sqlx::Executor<'_, Database = Postgres>,
+) -> Result<Option<Totp>, crate::Error> {
+    let totp = sqlx::query_as!(
+        Totp,
+        r#"
+        SELECT totp_secret AS "secret!", totp_enabled_at AS enabled_at, totp_last_used_step AS last_used_step
+        FROM users WHERE id = $1 AND totp_secret IS NOT NULL
+        "#,
+        id
+    )
+    .fetch_optional(executor)
+    .await
+    .map_err(crate::Error::DbError)?;
+
+    Ok(totp)
+}
+
+/// Starts enrolling a user in TOTP with the passed base32 encoded secret, replacing the secret of any enrollment that has not been confirmed.
+///
+/// If the user has enabled TOTP already, [`crate::Error::Conflict`] is returned.
+pub async fn start_totp_enrollment(
+    id: Uuid,
+    secret: &str,
+    executor: impl sqlx::Executor<'_, Database = Postgres>,
+) -> Result<(), crate::Error> {
+    let result = sqlx::query!(
+        r#"
+        UPDATE users SET totp_secret = $2, totp_last_used_step = NULL
+        WHERE id = $1 AND totp_enabled_at IS NULL
+        "#,
+        id,
+        secret
+    )
+    .execute(executor)
+    .await
+    .map_err(crate::Error::DbError)?;
+
+    if result.rows_affected() == 0 {
+        return Err(crate::Error::Conflict("totp"));
+    }
+
+    Ok(())
+}
+
+/// Enables TOTP for a user whose enrollment is pending, recording the time step of the code they confirmed it with.
+///
+/// If there's no pending enrollment, [`crate::Error::NoRecordFound`] is returned.
+pub async fn enable_totp(
+    id: Uuid,
+    step: i64,
+    executor: impl sqlx::Executor<'_, Database = Postgres>,
+) -> Result<(), crate::Error> {
+    let result = sqlx::query!(
+        r#"
+        UPDATE users SET totp_enabled_at = now(), totp_last_used_step = $2
+        WHERE id = $1 AND totp_secret IS NOT NULL AND totp_enabled_at IS NULL
+        "#,
+        id,
+        step
+    )
+    .execute(executor)
+    .await
+    .map_err(crate::Error::DbError)?;
+
+    if result.rows_affected() == 0 {
+        return Err(crate::Error::NoRecordFound);
+    }
+
+    Ok(())
+}
+
+/// Records that a code for the passed time step was accepted.
+///
+/// This only succeeds if no code for the same or a later step was accepted before so that two concurrent requests cannot both use the same code. Returns whether the step was recorded.
+pub async fn use_totp_step(
+    id: Uuid,
+    step: i64,
+    executor: impl sqlx::Executor<'_, Database = Postgres>,
+) -> Result<bool, crate::Error> {
+    let result = sqlx::query!(
+        r#"
+        UPDATE users SET totp_last_used_step = $2
+        WHERE id = $1 AND totp_enabled_at IS NOT NULL
+        AND (totp_last_used_step IS NULL OR totp_last_used_step < $2)
+        "#,
+        id,
+        step
+    )
+    .execute(executor)
+    .await
+    .map_err(crate::Error::DbError)?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Disables TOTP for a user, removing the secret along with any pending enrollment.
+///
+/// The user's recovery codes are kept – callers should delete them as well (see [`crate::entities::recovery_codes::delete_all`]).
+pub async fn disable_totp(
+    id: Uuid,
+    executor: impl sqlx::Executor<'_, Database = Postgres>,
+) -> Result<(), crate::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE users SET totp_secret = NULL, totp_enabled_at = NULL, totp_last_used_step = NULL
+        WHERE id = $1
+        "#,
+        id
+    )
+    .execute(executor)
+    .await
+    .map_err(crate::Error::DbError)?;
+
+    Ok(())
+}
+
+/// Revokes all tokens that have been issued to the user so far.
+///
+/// This increments the user's token generation so that tokens issued afterwards, even within the same second, stay valid.
+pub async fn revoke_all_tokens(
+    id: Uuid,
+    executor: impl sqlx::Executor<'_, Database = Postgres>,
+) -> Result<(), anyhow::Error> {
+    sqlx::query!(
+        "UPDATE users SET token_generation = token_generation + 1 WHERE id = $1",
+        id
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
@@ -5,6 +5,7 @@ use forge_api_config::DatabaseConfig;
 use sqlx::{postgres::PgPoolOptions, Postgres, Transaction};
 use thiserror::Error;
 
+pub use sqlx::postgres::PgConnection as DbConnection;
 pub use sqlx::postgres::PgPool as DbPool;
 
 /// Entity definitions and related functions
@@ -51,6 +52,38 @@ pub enum Error {
     #[error("validation failed")]
     /// An invalid changeset was passed to a writing operation such as creating or updating a record.
     ValidationError(#[from] validator::ValidationErrors),
+    /// A writing operation would have violated a unique constraint, e.g. when creating a record
+    /// with a name that's already taken. Carries the name of the field the conflict occurred on.
+    #[error("conflict on {0}")]
+    Conflict(&'static str),
+    /// A writing operation was made conditional on the record being at a particular version but
+    /// the record has been changed since.
+    #[error("precondition failed")]
+    PreconditionFailed,
+    /// A writing operation was attempted on a record the user may see but not change, e.g. a note
+    /// that is only shared with the user for reading.
+    #[error("forbidden")]
+    Forbidden,
+}
+
+impl Error {
+    /// Returns a function that maps unique violations to [`Error::Conflict`] on the passed field
+    /// and all other errors to [`Error::DbError`], e.g.:
+    ///
+    /// ```
+    /// sqlx::query!("INSERT INTO users (name) VALUES ($1)", name)
+    ///     .execute(executor)
+    ///     .await
+    ///     .map_err(crate::Error::conflict_on("name"))?;
+    /// ```
+    pub fn conflict_on(field: &'static str) -> impl FnOnce(sqlx::Error) -> Error {
+        move |e| match &e {
+            sqlx::Error::Database(db_error) if db_error.is_unique_violation() => {
+                Error::Conflict(field)
+            }
+            _ => Error::DbError(e),
+        }
+    }
 }
 
 /// Creates a connection pool to the database specified in the passed [`forge-api-config::DatabaseConfig`]
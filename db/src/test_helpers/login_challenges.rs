@@ -0,0 +1,10 @@
+use sqlx::postgres::PgPool;
+
+/// Lets all login challenges expire right away.
+pub async fn expire_all(db: &PgPool) -> Result<(), anyhow::Error> {
+    sqlx::query!("UPDATE login_challenges SET expires_at = now() - interval '1 second'")
+        .execute(db)
+        .await?;
+
+    Ok(())
+}
@@ -0,0 +1,12 @@
+use sqlx::postgres::PgPool;
+
+/// Lets all lockouts end right away.
+///
+/// The counts of failures are kept so that further failures lead to longer lockouts.
+pub async fn unlock_all(db: &PgPool) -> Result<(), anyhow::Error> {
+    sqlx::query!("UPDATE login_failures SET locked_until = now() - interval '1 second' WHERE locked_until IS NOT NULL")
+        .execute(db)
+        .await?;
+
+    Ok(())
+}
@@ -3,11 +3,26 @@ use forge_api_config::DatabaseConfig;
 use rand::distr::Alphanumeric;
 use rand::{rng, Rng};
 use regex::{Captures, Regex};
+use sqlx::migrate::{Migrate, Migrator};
 use sqlx::postgres::{PgConnectOptions, PgConnection};
 use sqlx::{Connection, Executor};
 use std::str::FromStr;
 use std::sync::Arc;
 
+/// All test functionality related to the [`crate::entities::api_keys::ApiKey`] entity
+pub mod api_keys;
+/// All test functionality related to the tokens sent to users by email
+pub mod email_tokens;
+/// All test functionality related to login challenges
+pub mod login_challenges;
+/// All test functionality related to counting failed logins
+pub mod login_failures;
+/// All test functionality related to the [`crate::entities::note_links::NoteLink`] entity
+pub mod note_links;
+/// All test functionality related to the [`crate::entities::notes::Note`] entity
+pub mod notes;
+/// All test functionality related to roles and permissions
+pub mod roles;
 /// All test functionality related to the [`crate::entities::users::User`] entity
 pub mod users;
 
@@ -17,12 +32,47 @@ pub mod users;
 /// This function is automatically called by the [`forge-api-macros::db_test`] macro. The return connection pool is passed to the test case via the [`forge-api-macros::DbTestContext`].
 #[allow(unused)]
 pub async fn setup_db(config: &DatabaseConfig) -> DbPool {
-    let test_db_config = prepare_db(config).await;
+    let db_name = parse_db_config(&config.url)
+        .get_database()
+        .unwrap()
+        .to_string();
+    let test_db_config = prepare_db(config, &db_name).await;
     connect_pool(test_db_config)
         .await
         .expect("Could not connect to database!")
 }
 
+/// Sets up a dedicated, empty database to be used for testing migrations.
+///
+/// Unlike [`setup_db`], no migrations have been applied to the database. Tests apply them step by step via [`migrate_to`] so they can insert data in the shape of an earlier schema and check how it is migrated. The database has to be dropped via [`teardown_db`] at the end of the test.
+pub async fn setup_empty_db(config: &DatabaseConfig) -> DbPool {
+    let test_db_config = prepare_db(config, "template0").await;
+    connect_pool(test_db_config)
+        .await
+        .expect("Could not connect to database!")
+}
+
+/// Applies all migrations up to and including the passed version that haven't been applied to the database yet.
+pub async fn migrate_to(version: i64, db_pool: &DbPool) {
+    static MIGRATOR: Migrator = sqlx::migrate!("./migrations");
+
+    let mut connection = db_pool.acquire().await.unwrap();
+    connection.ensure_migrations_table().await.unwrap();
+    let applied: Vec<i64> = connection
+        .list_applied_migrations()
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|migration| migration.version)
+        .collect();
+
+    for migration in MIGRATOR.iter() {
+        if migration.version <= version && !applied.contains(&migration.version) {
+            connection.apply(migration).await.unwrap();
+        }
+    }
+}
+
 /// Drops a dedicated database for a test case.
 ///
 /// This function is automatically called by the [`forge-api-macros::db_test`] macro. It ensures test-specific database are cleaned up after each test run so we don't end up with large numbers of unused databases.
@@ -41,7 +91,7 @@ pub async fn teardown_db(db_pool: DbPool) {
     connection.execute(query.as_str()).await.unwrap();
 }
 
-async fn prepare_db(config: &DatabaseConfig) -> DatabaseConfig {
+async fn prepare_db(config: &DatabaseConfig, template: &str) -> DatabaseConfig {
     let db_config = parse_db_config(&config.url);
     let db_name = db_config.get_database().unwrap();
 
@@ -50,7 +100,7 @@ async fn prepare_db(config: &DatabaseConfig) -> DatabaseConfig {
 
     let test_db_name = build_test_db_name(db_name);
 
-    let query = format!("CREATE DATABASE {} TEMPLATE {}", test_db_name, db_name);
+    let query = format!("CREATE DATABASE {} TEMPLATE {}", test_db_name, template);
     connection.execute(query.as_str()).await.unwrap();
 
     let regex = Regex::new(r"(.+)\/(.+$)").unwrap();
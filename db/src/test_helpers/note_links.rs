@@ -0,0 +1,16 @@
+use sqlx::postgres::PgPool;
+use uuid::Uuid;
+
+/// Lets a link expire right away.
+///
+/// Links can only be created with an expiry in the future, so this is the only way to get an expired link without waiting.
+pub async fn expire(id: Uuid, db: &PgPool) -> Result<(), anyhow::Error> {
+    sqlx::query!(
+        "UPDATE note_links SET expires_at = now() - interval '1 second' WHERE id = $1",
+        id
+    )
+    .execute(db)
+    .await?;
+
+    Ok(())
+}
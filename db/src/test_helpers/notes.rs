@@ -0,0 +1,27 @@
+use crate::entities::notes::{self, Note, NoteChangeset};
+use fake::{Fake, Faker};
+use sqlx::postgres::PgPool;
+use uuid::Uuid;
+
+/// Creates a note with the passed text for the passed user, leaving all other fields at their defaults.
+///
+/// Notes with particular tags or formats can be created via [`crate::entities::notes::create`] with a changeset that fills the remaining fields with fake data, e.g.:
+///
+/// ```
+/// let changeset = NoteChangeset {
+///     text: String::from("text"),
+///     tags: Some(vec![String::from("tag")]),
+///     ..Faker.fake()
+/// };
+/// ```
+///
+/// Panics if the note can't be created.
+pub async fn create(text: &str, owner_id: Uuid, db: &PgPool) -> Note {
+    let changeset = NoteChangeset {
+        text: String::from(text),
+        ..Faker.fake()
+    };
+    notes::create(changeset, owner_id, db)
+        .await
+        .expect("Could not create note!")
+}
@@ -0,0 +1,26 @@
+use sqlx::postgres::PgPool;
+use uuid::Uuid;
+
+/// Creates a role that grants exactly the passed permissions and returns its name.
+///
+/// The role gets a random name so that it doesn't clash with the roles created by migrations or other calls.
+pub async fn create(permissions: &[&str], db: &PgPool) -> Result<String, anyhow::Error> {
+    let name = format!("role-{}", Uuid::new_v4());
+    let permissions: Vec<String> = permissions.iter().map(|p| p.to_string()).collect();
+
+    sqlx::query!(
+        r#"
+        WITH role AS (INSERT INTO roles (name) VALUES ($1) RETURNING id)
+        INSERT INTO role_permissions (role_id, permission_id)
+        SELECT role.id, permissions.id
+        FROM role CROSS JOIN permissions
+        WHERE permissions.name = ANY($2)
+        "#,
+        name,
+        &permissions
+    )
+    .execute(db)
+    .await?;
+
+    Ok(name)
+}
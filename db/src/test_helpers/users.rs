@@ -1,5 +1,6 @@
+use crate::entities::roles;
 use crate::entities::users::User;
-use fake::{faker::name::en::*, Dummy};
+use fake::{faker::name::en::*, Dummy, Fake, Faker};
 use sqlx::postgres::PgPool;
 
 /// A changeset representing the data that is intended to be used to either create a new user or update an existing user.
@@ -19,26 +20,41 @@ pub struct UserChangeset {
     /// The user's auth token, fake data will be a 100 characters long number
     #[dummy(faker = "100..101")]
     pub token: String,
+    /// The user's password hash, fake data will be a 100 characters long number
     #[dummy(faker = "100..101")]
     pub pass: String,
 }
 
 /// Creates a user in the database with the data in the passed [`UserChangeset`].
 ///
-/// If the data in the changeset isn't valid, a [`crate::Error::ValidationError`] will be returned, otherwise the created user is returned.
+/// Like users that sign up, the created user is assigned the [`crate::entities::roles::DEFAULT_ROLE`]. If the data in the changeset isn't valid, a [`crate::Error::ValidationError`] will be returned, otherwise the created user is returned.
 pub async fn create(user: UserChangeset, db: &PgPool) -> Result<User, anyhow::Error> {
     let record = sqlx::query!(
-        "INSERT INTO users (name, token) VALUES ($1, $2) RETURNING id",
+        "INSERT INTO users (name, pass, token) VALUES ($1, $2, $3) RETURNING id",
         user.name,
+        user.pass,
         user.token,
     )
     .fetch_one(db)
     .await?;
 
+    roles::assign(record.id, roles::DEFAULT_ROLE, db).await?;
+
     Ok(User {
         id: record.id,
         name: user.name,
         pass: user.pass,
         token: user.token,
+        token_generation: 0,
     })
 }
+
+/// Creates a user with fake data (see [`create`]).
+///
+/// Panics if the user can't be created.
+pub async fn create_fake(db: &PgPool) -> User {
+    let user_changeset: UserChangeset = Faker.fake();
+    create(user_changeset, db)
+        .await
+        .expect("Could not create user!")
+}
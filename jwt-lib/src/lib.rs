@@ -1,55 +1,151 @@
+use anyhow::{Context, anyhow};
 use chrono::{Duration, Utc};
+use forge_api_config::{AuthConfig, JwtAlgorithm};
 use forge_api_db::entities::users::User;
-use jsonwebtoken::{DecodingKey, EncodingKey, Header, Validation, decode, encode};
+use jsonwebtoken::{Algorithm, DecodingKey, EncodingKey, Header, Validation, decode, encode};
 use serde::{Deserialize, Serialize};
+use uuid::Uuid;
 
-#[derive(Deserialize, Serialize)]
-pub struct AuthUser {
-    name: String,
-}
+pub use jsonwebtoken::errors::{Error, ErrorKind};
 
-#[derive(Serialize, Deserialize)]
-struct Claims {
-    name: String,
-    exp: i64,
+/// The claims carried by the access tokens handed out by `/login`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Claims {
+    /// The id of the user the token was issued to.
+    pub sub: Uuid,
+    /// The name of the user the token was issued to.
+    pub name: String,
+    /// The unique id of the token, used for revoking it.
+    pub jti: Uuid,
+    /// The time the token was issued at as a Unix timestamp.
+    pub iat: i64,
+    /// The token generation of the user the token was issued for. Tokens of earlier generations have been revoked.
+    #[serde(default)]
+    pub generation: i32,
+    /// The expiry of the token as a Unix timestamp.
+    pub exp: i64,
+    /// The issuer of the token, if one is configured.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub iss: Option<String>,
+    /// The audience of the token, if one is configured.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub aud: Option<String>,
 }
 
-pub async fn get_jwt(user: User) -> Result<String, String> {
-    // // 1. Lookup user by name
-    // let user = find_user_by_name(name, &app_state.db_pool)
-    //     .await
-    //     .ok_or_else(|| "Invalid credentials".to_string())?;
-
-    // // 2. Verify password
-    // let password_ok =
-    //     verify(pass, &user.hashed_pass).map_err(|_| "Invalid credentials".to_string())?;
-
-    // if !password_ok {
-    //     return Err("Invalid credentials".to_string());
-    // }
-    let token = encode(
-        &Header::default(),
-        &Claims {
-            name: user.name,
-            exp: (Utc::now() + Duration::minutes(2)).timestamp(),
-        },
-        &EncodingKey::from_secret("dummy_secret_key".as_bytes()),
-    )
-    .map_err(|e| e.to_string());
-
-    return token;
+/// Issues and verifies tokens according to a [`forge_api_config::AuthConfig`].
+#[derive(Clone)]
+pub struct Jwt {
+    algorithm: Algorithm,
+    encoding_key: EncodingKey,
+    decoding_key: DecodingKey,
+    validation: Validation,
+    issuer: Option<String>,
+    audience: Option<String>,
+    ttl: Duration,
 }
 
-pub fn decode_jwt(token: &str) -> Result<User, String> {
-    let token_data = decode::<User>(
-        token,
-        &DecodingKey::from_secret("dummy_secret_key".as_bytes()),
-        &Validation::default(),
-    );
+impl Jwt {
+    /// Builds the keys and validation rules described by the passed configuration.
+    ///
+    /// This reads the key files for asymmetric algorithms and fails if they are missing or can't be parsed.
+    pub fn from_config(config: &AuthConfig) -> Result<Self, anyhow::Error> {
+        let algorithm = match config.algorithm {
+            JwtAlgorithm::HS256 => Algorithm::HS256,
+            JwtAlgorithm::HS384 => Algorithm::HS384,
+            JwtAlgorithm::HS512 => Algorithm::HS512,
+            JwtAlgorithm::RS256 => Algorithm::RS256,
+            JwtAlgorithm::RS384 => Algorithm::RS384,
+            JwtAlgorithm::RS512 => Algorithm::RS512,
+            JwtAlgorithm::ES256 => Algorithm::ES256,
+            JwtAlgorithm::ES384 => Algorithm::ES384,
+            JwtAlgorithm::EdDSA => Algorithm::EdDSA,
+        };
+
+        let (encoding_key, decoding_key) = if config.algorithm.is_symmetric() {
+            let secret = config
+                .secret
+                .as_deref()
+                .ok_or_else(|| anyhow!("No JWT secret configured!"))?;
+            (
+                EncodingKey::from_secret(secret.as_bytes()),
+                DecodingKey::from_secret(secret.as_bytes()),
+            )
+        } else {
+            let private_key = read_key(config.private_key_path.as_ref(), "private")?;
+            let public_key = read_key(config.public_key_path.as_ref(), "public")?;
+            match config.algorithm {
+                JwtAlgorithm::ES256 | JwtAlgorithm::ES384 => (
+                    EncodingKey::from_ec_pem(&private_key)?,
+                    DecodingKey::from_ec_pem(&public_key)?,
+                ),
+                JwtAlgorithm::EdDSA => (
+                    EncodingKey::from_ed_pem(&private_key)?,
+                    DecodingKey::from_ed_pem(&public_key)?,
+                ),
+                _ => (
+                    EncodingKey::from_rsa_pem(&private_key)?,
+                    DecodingKey::from_rsa_pem(&public_key)?,
+                ),
+            }
+        };
+
+        let mut validation = Validation::new(algorithm);
+        if let Some(issuer) = &config.issuer {
+            validation.set_issuer(&[issuer]);
+            validation.required_spec_claims.insert(String::from("iss"));
+        }
+        match &config.audience {
+            Some(audience) => {
+                validation.set_audience(&[audience]);
+                validation.required_spec_claims.insert(String::from("aud"));
+            }
+            None => validation.validate_aud = false,
+        }
+        let ttl = i64::try_from(config.access_token_ttl_secs)
+            .ok()
+            .and_then(Duration::try_seconds)
+            .ok_or_else(|| anyhow!("Access token TTL is out of range!"))?;
 
-    match token_data {
-        Ok(token_data) => Ok(token_data.claims),
+        Ok(Self {
+            algorithm,
+            encoding_key,
+            decoding_key,
+            validation,
+            issuer: config.issuer.clone(),
+            audience: config.audience.clone(),
+            ttl,
+        })
+    }
+
+    /// Issues a signed access token for the passed user.
+    pub fn get_jwt(&self, user: &User) -> Result<String, Error> {
+        let now = Utc::now();
+        self.encode_claims(&Claims {
+            sub: user.id,
+            name: user.name.clone(),
+            jti: Uuid::new_v4(),
+            iat: now.timestamp(),
+            generation: user.token_generation,
+            exp: (now + self.ttl).timestamp(),
+            iss: self.issuer.clone(),
+            aud: self.audience.clone(),
+        })
+    }
 
-        Err(e) => Err(e.to_string()),
+    /// Signs the passed claims as-is.
+    pub fn encode_claims(&self, claims: &Claims) -> Result<String, Error> {
+        encode(&Header::new(self.algorithm), claims, &self.encoding_key)
     }
+
+    /// Verifies the signature, expiry, issuer and audience of the passed token and returns its claims.
+    pub fn decode_jwt(&self, token: &str) -> Result<Claims, Error> {
+        let token_data = decode::<Claims>(token, &self.decoding_key, &self.validation)?;
+
+        Ok(token_data.claims)
+    }
+}
+
+fn read_key(path: Option<&std::path::PathBuf>, kind: &str) -> Result<Vec<u8>, anyhow::Error> {
+    let path = path.ok_or_else(|| anyhow!("No {} key configured!", kind))?;
+    std::fs::read(path).with_context(|| format!("Could not read {} key {:?}!", kind, path))
 }
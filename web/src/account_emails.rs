@@ -0,0 +1,284 @@
+use crate::error::Error;
+use crate::mailer::Email;
+use crate::state::{AppState, SharedAppState};
+use crate::tokens;
+use axum::{
+    extract::{Json, State},
+    http::StatusCode,
+};
+use chrono::{Duration, Utc};
+use forge_api_db::entities::email_tokens::{self, EmailTokenPurpose};
+use forge_api_db::entities::{api_keys, refresh_tokens, users};
+use forge_api_db::transaction;
+use serde::Deserialize;
+#[cfg(feature = "test-helpers")]
+use serde::Serialize;
+use tracing::error;
+use uuid::Uuid;
+use validator::{Validate, ValidationError, ValidationErrors};
+
+/// The payload of a request to `/password/forgot`.
+#[derive(Deserialize, Validate, utoipa::ToSchema)]
+#[cfg_attr(feature = "test-helpers", derive(Serialize))]
+pub struct ForgotPasswordRequest {
+    /// The verified email address of the user
+    #[validate(email)]
+    pub email: String,
+}
+
+/// The payload of a request to `/password/reset`.
+#[derive(Deserialize, Validate, utoipa::ToSchema)]
+#[cfg_attr(feature = "test-helpers", derive(Serialize))]
+pub struct ResetPasswordRequest {
+    /// The token from the password reset email
+    #[validate(length(min = 1))]
+    pub token: String,
+    /// The new password, 8 to 128 characters including at least one letter and one digit
+    #[validate(
+        length(min = 8, max = 128),
+        custom(function = "crate::auth::validate_password")
+    )]
+    pub password: String,
+}
+
+/// The payload of a request to `/email/verify`.
+#[derive(Deserialize, Validate, utoipa::ToSchema)]
+#[cfg_attr(feature = "test-helpers", derive(Serialize))]
+pub struct VerifyEmailRequest {
+    /// The token from the verification email
+    #[validate(length(min = 1))]
+    pub token: String,
+}
+
+/// Sends a link for resetting the password to the passed email address if it belongs to a user who has verified it.
+///
+/// The response is the same whether or not the address belongs to a user and the email is sent in the background so that neither the response nor its timing reveal which addresses are registered. Earlier links stay valid until they expire or one of them is used.
+#[utoipa::path(
+    post,
+    path = "/password/forgot",
+    request_body(content = ForgotPasswordRequest, content_type = "application/json"),
+    responses(
+        (status = ACCEPTED, description = "A link has been sent if the address belongs to a user"),
+        (status = UNPROCESSABLE_ENTITY, description = "Invalid payload")
+    )
+)]
+#[axum::debug_handler]
+pub async fn forgot_password(
+    State(app_state): State<SharedAppState>,
+    Json(payload): Json<ForgotPasswordRequest>,
+) -> Result<StatusCode, Error> {
+    payload.validate()?;
+
+    tokio::spawn(async move {
+        if let Err(e) = send_password_reset_email(&app_state, &payload.email).await {
+            error!(err.msg = %e, err.details = ?e, "Sending password reset email failed");
+        }
+    });
+
+    Ok(StatusCode::ACCEPTED)
+}
+
+/// Sets a new password for the user a password reset link was sent to.
+///
+/// Reset tokens can only be used once and using one invalidates all other reset tokens of the user. As whoever knew the old password might have been using the account, all of the user's sessions are logged out and all of their API keys are revoked.
+#[utoipa::path(
+    post,
+    path = "/password/reset",
+    request_body(content = ResetPasswordRequest, content_type = "application/json"),
+    responses(
+        (status = NO_CONTENT, description = "The password has been changed"),
+        (status = UNPROCESSABLE_ENTITY, description = "Invalid payload or unknown, used or expired token")
+    )
+)]
+#[axum::debug_handler]
+pub async fn reset_password(
+    State(app_state): State<SharedAppState>,
+    Json(payload): Json<ResetPasswordRequest>,
+) -> Result<StatusCode, Error> {
+    payload.validate()?;
+
+    let hashed_pass = app_state.password_hashing.hash(payload.password).await?;
+
+    let mut tx = transaction(&app_state.db_pool).await?;
+    let token_hash = tokens::hash(&payload.token);
+    let token = email_tokens::consume(EmailTokenPurpose::PasswordReset, &token_hash, &mut *tx)
+        .await
+        .map_err(token_error)?;
+    users::update_password_hash(token.user_id, &hashed_pass, &mut *tx).await?;
+    email_tokens::invalidate_all(token.user_id, EmailTokenPurpose::PasswordReset, &mut *tx).await?;
+    users::revoke_all_tokens(token.user_id, &mut *tx).await?;
+    refresh_tokens::revoke_all_for_user(token.user_id, &mut *tx).await?;
+    api_keys::revoke_all_for_user(token.user_id, &mut *tx).await?;
+    tx.commit().await.map_err(forge_api_db::Error::DbError)?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Marks the email address a verification link was sent to as verified.
+///
+/// Verification tokens can only be used once. If the user's address has changed since the link was sent, the token is rejected. If another user has verified the address in the meantime, verifying it fails with a conflict.
+#[utoipa::path(
+    post,
+    path = "/email/verify",
+    request_body(content = VerifyEmailRequest, content_type = "application/json"),
+    responses(
+        (status = NO_CONTENT, description = "The email address has been verified"),
+        (status = CONFLICT, description = "Email address already verified by another user"),
+        (status = UNPROCESSABLE_ENTITY, description = "Invalid payload or unknown, used or expired token")
+    )
+)]
+#[axum::debug_handler]
+pub async fn verify_email(
+    State(app_state): State<SharedAppState>,
+    Json(payload): Json<VerifyEmailRequest>,
+) -> Result<StatusCode, Error> {
+    payload.validate()?;
+
+    let mut tx = transaction(&app_state.db_pool).await?;
+    let token_hash = tokens::hash(&payload.token);
+    let token = email_tokens::consume(EmailTokenPurpose::EmailVerification, &token_hash, &mut *tx)
+        .await
+        .map_err(token_error)?;
+    users::verify_email(token.user_id, &token.email, &mut *tx)
+        .await
+        .map_err(token_error)?;
+    tx.commit().await.map_err(forge_api_db::Error::DbError)?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Sends a link for verifying the passed email address of a user in the background.
+///
+/// Failures are only logged as the user can still use their account without a verified address.
+pub fn send_verification_email(app_state: SharedAppState, user_id: Uuid, email: String) {
+    tokio::spawn(async move {
+        let ttl_secs = app_state.config.account_emails.email_verification_ttl_secs;
+        let result = send_token_email(
+            &app_state,
+            user_id,
+            &email,
+            EmailTokenPurpose::EmailVerification,
+            ttl_secs,
+        )
+        .await;
+        if let Err(e) = result {
+            error!(err.msg = %e, err.details = ?e, "Sending verification email failed");
+        }
+    });
+}
+
+/// Lets the owner of the passed email address know in the background that someone tried to register another account with it.
+///
+/// This is sent instead of a verification link so that registering doesn't reveal whether an address is taken while the owner still learns about it, e.g. if they forgot they already have an account.
+pub fn send_email_taken_notice(app_state: SharedAppState, email: String) {
+    tokio::spawn(async move {
+        let result = app_state
+            .mailer
+            .send(Email {
+                to: email,
+                subject: String::from("Your email address is already registered"),
+                body: String::from(
+                    "Someone just tried to register a new account with this email address, which already belongs to an account. If that was you, you can log in to your existing account or reset its password instead.\n\nIf it wasn't you, you can ignore this email. No account has been linked to this address.\n",
+                ),
+            })
+            .await;
+        if let Err(e) = result {
+            error!(err.msg = %e, err.details = ?e, "Sending email taken notice failed");
+        }
+    });
+}
+
+async fn send_password_reset_email(app_state: &AppState, email: &str) -> Result<(), Error> {
+    let Some(user) = users::find_user_by_verified_email(email, &app_state.db_pool).await? else {
+        return Ok(());
+    };
+    let ttl_secs = app_state.config.account_emails.password_reset_ttl_secs;
+
+    send_token_email(
+        app_state,
+        user.id,
+        email,
+        EmailTokenPurpose::PasswordReset,
+        ttl_secs,
+    )
+    .await
+}
+
+/// Stores a new token for the passed purpose and mails a link containing it to the passed address.
+async fn send_token_email(
+    app_state: &AppState,
+    user_id: Uuid,
+    email: &str,
+    purpose: EmailTokenPurpose,
+    ttl_secs: u64,
+) -> Result<(), Error> {
+    let token = tokens::generate();
+    let expires_at = Utc::now() + Duration::seconds(ttl_secs.min(i32::MAX as u64) as i64);
+    email_tokens::create(
+        user_id,
+        purpose,
+        &tokens::hash(&token),
+        email,
+        expires_at,
+        &app_state.db_pool,
+    )
+    .await?;
+
+    let base_url = app_state
+        .config
+        .account_emails
+        .link_base_url
+        .trim_end_matches('/');
+    let valid_for = format_duration(ttl_secs);
+    let (subject, body) = match purpose {
+        EmailTokenPurpose::PasswordReset => (
+            "Reset your password",
+            format!(
+                "Someone asked to reset the password of your account. If that was you, follow this link to choose a new password:\n\n{base_url}/password/reset?token={token}\n\nThe link is valid for {valid_for}. If you didn't ask to reset your password, you can ignore this email.\n"
+            ),
+        ),
+        EmailTokenPurpose::EmailVerification => (
+            "Verify your email address",
+            format!(
+                "Please follow this link to confirm that this is your email address:\n\n{base_url}/email/verify?token={token}\n\nThe link is valid for {valid_for}.\n"
+            ),
+        ),
+    };
+    app_state
+        .mailer
+        .send(Email {
+            to: String::from(email),
+            subject: String::from(subject),
+            body,
+        })
+        .await?;
+
+    Ok(())
+}
+
+/// Formats a number of seconds for humans, e.g. "2 days" or "1 hour".
+fn format_duration(secs: u64) -> String {
+    let (count, unit) = match secs {
+        s if s >= 86400 && s % 86400 == 0 => (s / 86400, "day"),
+        s if s >= 3600 && s % 3600 == 0 => (s / 3600, "hour"),
+        s if s >= 60 && s % 60 == 0 => (s / 60, "minute"),
+        s => (s, "second"),
+    };
+    let plural = if count == 1 { "" } else { "s" };
+    format!("{count} {unit}{plural}")
+}
+
+/// Maps a token that can't be found, e.g. because it has been used or has expired, to a validation error on `token`.
+fn token_error(e: forge_api_db::Error) -> Error {
+    match e {
+        forge_api_db::Error::NoRecordFound => {
+            let mut errors = ValidationErrors::new();
+            errors.add(
+                "token",
+                ValidationError::new("invalid").with_message("is invalid or has expired".into()),
+            );
+            Error::Validation(errors)
+        }
+        e => e.into(),
+    }
+}
@@ -1,109 +1,501 @@
-use crate::state::SharedAppState;
+use crate::account_emails;
+use crate::error::Error;
+use crate::login_throttling::LoginAttempt;
+use crate::middlewares::auth::CurrentUser;
+use crate::passwords::Verification;
+use crate::state::{AppState, SharedAppState};
+use crate::tokens;
+use crate::two_factor;
 
 use axum::{
-    extract::{Json, State},
+    extract::{ConnectInfo, Extension, Json, State},
     http::StatusCode,
-    response::IntoResponse,
+    response::{IntoResponse, Response},
 };
-use serde_json::{json, Value};
-use tracing::info;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+use std::sync::LazyLock;
+use tracing::warn;
+use uuid::Uuid;
+use validator::{Validate, ValidationError};
 
-use bcrypt::{hash, verify, DEFAULT_COST};
-use forge_api_db::entities::users::{find_user_by_name, insert_user};
+use forge_api_db::entities::users::{self, find_user_by_name, insert_user};
+use forge_api_db::entities::{login_challenges, refresh_tokens, revoked_tokens, roles};
+use forge_api_db::transaction;
+use jwt_lib::Claims;
+use regex::Regex;
 
+static NAME_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^[A-Za-z0-9_.-]+$").unwrap());
+
+/// The payload of a request to `/login`.
+#[derive(Deserialize, Validate, utoipa::ToSchema)]
+#[cfg_attr(feature = "test-helpers", derive(Serialize))]
+pub struct LoginRequest {
+    /// The name of the user
+    #[validate(length(min = 1))]
+    pub name: String,
+    /// The user's password
+    #[validate(length(min = 1))]
+    pub password: String,
+}
+
+/// The payload of a request to `/login/2fa`.
+#[derive(Deserialize, Validate, utoipa::ToSchema)]
+#[cfg_attr(feature = "test-helpers", derive(Serialize))]
+pub struct TwoFactorLoginRequest {
+    /// The challenge token handed out by `/login`
+    pub challenge_token: String,
+    /// A code from the user's authenticator app or one of their recovery codes
+    #[validate(length(min = 1))]
+    pub code: String,
+}
+
+/// The payload of a request to `/register`.
+#[derive(Deserialize, Validate, utoipa::ToSchema)]
+#[cfg_attr(feature = "test-helpers", derive(Serialize))]
+pub struct RegisterRequest {
+    /// The name of the user, 3 to 32 letters, digits, `_`, `.` or `-`
+    #[validate(
+        length(min = 3, max = 32),
+        regex(path = *NAME_REGEX, code = "charset", message = "must only contain letters, digits, `_`, `.` or `-`")
+    )]
+    pub name: String,
+    /// The user's password, 8 to 128 characters including at least one letter and one digit
+    #[validate(length(min = 8, max = 128), custom(function = "validate_password"))]
+    pub password: String,
+    /// The user's email address, which is needed for resetting the password once it has been verified
+    #[validate(email, length(max = 254))]
+    pub email: Option<String>,
+}
+
+/// The tokens handed out by `/login` and `/token/refresh`.
+#[derive(Serialize, Deserialize, utoipa::ToSchema)]
+pub struct TokenResponse {
+    /// The short-lived access token to send as `Authorization: Bearer <token>`
+    pub token: String,
+    /// The single-use refresh token to exchange for new tokens via `/token/refresh`
+    pub refresh_token: String,
+}
+
+/// The challenge handed out by `/login` for users who have enabled two-factor authentication.
+#[derive(Serialize, Deserialize, utoipa::ToSchema)]
+pub struct TwoFactorChallenge {
+    /// The single-use token to send to `/login/2fa` along with a code
+    pub challenge_token: String,
+    /// The number of seconds the challenge token is valid for
+    pub expires_in: u64,
+}
+
+/// The response of `/login`: either tokens or, if the user has enabled two-factor authentication, a challenge.
+pub enum LoginResponse {
+    /// The user is logged in.
+    Tokens(TokenResponse),
+    /// The user still has to present their second factor via `/login/2fa`.
+    TwoFactorRequired(TwoFactorChallenge),
+}
+
+impl IntoResponse for LoginResponse {
+    fn into_response(self) -> Response {
+        match self {
+            LoginResponse::Tokens(tokens) => (StatusCode::OK, Json(tokens)).into_response(),
+            LoginResponse::TwoFactorRequired(challenge) => {
+                (StatusCode::ACCEPTED, Json(challenge)).into_response()
+            }
+        }
+    }
+}
+
+/// The user created by `/register`.
+#[derive(Serialize, Deserialize, utoipa::ToSchema)]
+pub struct UserResponse {
+    /// The id of the user
+    pub id: Uuid,
+    /// The name of the user
+    pub name: String,
+}
+
+pub(crate) fn validate_password(password: &str) -> Result<(), ValidationError> {
+    let has_letter = password.chars().any(char::is_alphabetic);
+    let has_digit = password.chars().any(|c| c.is_ascii_digit());
+    if has_letter && has_digit {
+        Ok(())
+    } else {
+        Err(ValidationError::new("password_policy")
+            .with_message("must contain at least one letter and one digit".into()))
+    }
+}
+
+/// Authenticates a user by name and password and hands out an access and a refresh token.
+///
+/// If the user has enabled two-factor authentication, a challenge token is handed out instead that has to be exchanged for tokens along with a code via `/login/2fa`.
+///
+/// If the user's password hash was created with a legacy algorithm or outdated parameters, it is replaced with a fresh one now that the password is known.
+///
+/// Accounts and client IP addresses that failed to log in too often are locked out for a while (see [`crate::login_throttling`]). Unknown names take as long to be rejected as wrong passwords so that responses don't reveal which names exist.
+#[utoipa::path(
+    post,
+    path = "/login",
+    request_body(content = LoginRequest, content_type = "application/json"),
+    responses(
+        (status = OK, body = TokenResponse),
+        (status = ACCEPTED, body = TwoFactorChallenge, description = "The user has to present their second factor via `/login/2fa`"),
+        (status = UNAUTHORIZED, description = "Invalid credentials"),
+        (status = UNPROCESSABLE_ENTITY, description = "Invalid payload"),
+        (status = TOO_MANY_REQUESTS, description = "Too many failed logins for the account or from the client's IP address", headers(
+            ("Retry-After" = u64, description = "The number of seconds after which logins are possible again")
+        ))
+    )
+)]
 #[axum::debug_handler]
 pub async fn login_handler(
     State(app_state): State<SharedAppState>,
-    Json(payload): Json<Value>,
-) -> impl IntoResponse {
-    let name = payload.get("name").and_then(|v| v.as_str());
-    let pass = payload.get("password").and_then(|v| v.as_str());
-
-    if name.is_none() || pass.is_none() {
-        return (
-            StatusCode::BAD_REQUEST,
-            Json(json!({"success": false, "message": "Missing name or password"})),
-        );
-    }
+    ConnectInfo(client_addr): ConnectInfo<SocketAddr>,
+    Json(payload): Json<LoginRequest>,
+) -> Result<LoginResponse, Error> {
+    payload.validate()?;
 
-    // Extract the actual &str from Option<&str>
-    let name = name.unwrap(); // Safe because we checked above
+    let attempt = LoginAttempt::new(&payload.name, client_addr.ip());
+    attempt.ensure_not_locked(&app_state).await?;
 
-    // This requires your function to return Result<impl IntoResponse, (StatusCode, Json<Value>)>
-    let user = match find_user_by_name(name, &app_state.db_pool).await {
-        Err(_) => {
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(json!({"success": false, "message": "Database error"})),
-            )
+    let user = find_user_by_name(&payload.name, &app_state.db_pool).await?;
+    let verification = app_state
+        .password_hashing
+        .verify(
+            payload.password.clone(),
+            user.as_ref().map(|user| user.pass.clone()),
+        )
+        .await?;
+    let user = match (user, verification) {
+        (Some(user), Verification::Valid | Verification::ValidNeedsRehash) => user,
+        _ => {
+            attempt.record_failure(&app_state).await?;
+            return Err(Error::Unauthorized);
         }
-        Ok(None) => {
-            return (
-                StatusCode::UNAUTHORIZED,
-                Json(json!({"success": false, "message": "Invalid credentials"})),
-            )
+    };
+
+    if verification == Verification::ValidNeedsRehash {
+        rehash_password(&app_state, user.id, payload.password).await;
+    }
+
+    if two_factor::is_enabled(&app_state, user.id).await? {
+        // The account's failed logins are only forgotten once the second factor has been presented
+        // as well so that knowing the password doesn't allow guessing codes without limit.
+        let challenge = new_login_challenge(&app_state, user.id).await?;
+        return Ok(LoginResponse::TwoFactorRequired(challenge));
+    }
+
+    attempt.record_success(&app_state).await?;
+    let tokens = issue_tokens(&app_state, &user).await?;
+
+    Ok(LoginResponse::Tokens(tokens))
+}
+
+/// Completes logging in a user who has enabled two-factor authentication and hands out an access and a refresh token.
+///
+/// The challenge token handed out by `/login` has to be sent along with a code from the user's authenticator app or one of their recovery codes. Challenge tokens can be used until they expire or a valid code is presented. Wrong codes count as failed logins (see [`crate::login_throttling`]).
+#[utoipa::path(
+    post,
+    path = "/login/2fa",
+    request_body(content = TwoFactorLoginRequest, content_type = "application/json"),
+    responses(
+        (status = OK, body = TokenResponse),
+        (status = UNAUTHORIZED, description = "Unknown or expired challenge token or invalid code"),
+        (status = UNPROCESSABLE_ENTITY, description = "Invalid payload"),
+        (status = TOO_MANY_REQUESTS, description = "Too many failed logins for the account or from the client's IP address", headers(
+            ("Retry-After" = u64, description = "The number of seconds after which logins are possible again")
+        ))
+    )
+)]
+#[axum::debug_handler]
+pub async fn login_two_factor_handler(
+    State(app_state): State<SharedAppState>,
+    ConnectInfo(client_addr): ConnectInfo<SocketAddr>,
+    Json(payload): Json<TwoFactorLoginRequest>,
+) -> Result<Json<TokenResponse>, Error> {
+    payload.validate()?;
+
+    let token_hash = tokens::hash(&payload.challenge_token);
+    let user_id = match login_challenges::load_user_id(&token_hash, &app_state.db_pool).await {
+        Ok(user_id) => user_id,
+        Err(forge_api_db::Error::NoRecordFound) => return Err(Error::Unauthorized),
+        Err(e) => return Err(e.into()),
+    };
+    let user = users::load(user_id, &app_state.db_pool)
+        .await?
+        .ok_or(Error::Unauthorized)?;
+
+    let attempt = LoginAttempt::new(&user.name, client_addr.ip());
+    attempt.ensure_not_locked(&app_state).await?;
+    if !two_factor::verify(&app_state, user.id, &payload.code).await? {
+        attempt.record_failure(&app_state).await?;
+        return Err(Error::Unauthorized);
+    }
+
+    match login_challenges::complete(&token_hash, &app_state.db_pool).await {
+        Ok(()) => {}
+        Err(forge_api_db::Error::NoRecordFound) => return Err(Error::Unauthorized),
+        Err(e) => return Err(e.into()),
+    }
+    attempt.record_success(&app_state).await?;
+    let tokens = issue_tokens(&app_state, &user).await?;
+
+    Ok(Json(tokens))
+}
+
+/// Issues an access token and a refresh token that starts a new family for a user who just logged in.
+async fn issue_tokens(app_state: &AppState, user: &users::User) -> Result<TokenResponse, Error> {
+    let token = app_state.jwt.get_jwt(user).map_err(anyhow::Error::from)?;
+    let (refresh_token, token_hash, expires_at) = new_refresh_token(app_state);
+    refresh_tokens::create(
+        user.id,
+        Uuid::new_v4(),
+        &token_hash,
+        expires_at,
+        &app_state.db_pool,
+    )
+    .await?;
+
+    Ok(TokenResponse {
+        token,
+        refresh_token,
+    })
+}
+
+/// Stores a new challenge for a user who still has to present their second factor, returning its token in plain text.
+async fn new_login_challenge(
+    app_state: &AppState,
+    user_id: Uuid,
+) -> Result<TwoFactorChallenge, Error> {
+    let ttl_secs = app_state.config.two_factor.challenge_ttl_secs;
+    let challenge_token = tokens::generate();
+    let expires_at = Utc::now() + Duration::seconds(ttl_secs.min(i32::MAX as u64) as i64);
+    login_challenges::create(
+        user_id,
+        &tokens::hash(&challenge_token),
+        expires_at,
+        &app_state.db_pool,
+    )
+    .await?;
+
+    Ok(TwoFactorChallenge {
+        challenge_token,
+        expires_in: ttl_secs,
+    })
+}
+
+/// Replaces the password hash of a user with one created with the current algorithm and parameters.
+///
+/// Failures are only logged as the old hash keeps working.
+async fn rehash_password(app_state: &AppState, user_id: Uuid, password: String) {
+    let result = match app_state.password_hashing.hash(password).await {
+        Ok(hash) => users::update_password_hash(user_id, &hash, &app_state.db_pool)
+            .await
+            .map_err(anyhow::Error::from),
+        Err(e) => Err(e),
+    };
+    if let Err(e) = result {
+        warn!(err.msg = %e, err.details = ?e, user_id = %user_id, "Upgrading password hash failed");
+    }
+}
+
+/// The payload of a request to `/token/refresh`.
+#[derive(Deserialize, utoipa::ToSchema)]
+pub struct RefreshRequest {
+    /// The refresh token handed out by `/login` or a previous refresh.
+    pub refresh_token: String,
+}
+
+/// Exchanges a refresh token for a new access token and a new refresh token.
+///
+/// Every refresh token can only be used once. Presenting a token that has already been exchanged indicates it was leaked, so the whole family of tokens descending from the same login is revoked.
+#[utoipa::path(
+    post,
+    path = "/token/refresh",
+    request_body(content = RefreshRequest, content_type = "application/json"),
+    responses(
+        (status = OK, body = TokenResponse),
+        (status = UNAUTHORIZED, description = "Unknown, expired, revoked or reused refresh token")
+    )
+)]
+#[axum::debug_handler]
+pub async fn refresh_handler(
+    State(app_state): State<SharedAppState>,
+    Json(payload): Json<RefreshRequest>,
+) -> Result<Json<TokenResponse>, Error> {
+    match rotate_refresh_token(&app_state, &payload.refresh_token).await? {
+        Some(tokens) => Ok(Json(tokens)),
+        None => Err(Error::Unauthorized),
+    }
+}
+
+/// The optional payload of a request to `/logout`.
+#[derive(Deserialize)]
+pub struct LogoutRequest {
+    /// The refresh token to revoke along with the access token.
+    pub refresh_token: Option<String>,
+}
+
+/// Revokes the access token the request was authenticated with.
+///
+/// If a refresh token is passed as well, its whole family is revoked so that it can't be used to get a new access token either. Requests authenticated with an API key are rejected as there's no session to log out of – keys are revoked via `DELETE /account/api-keys/{id}` instead.
+#[axum::debug_handler]
+pub async fn logout_handler(
+    State(app_state): State<SharedAppState>,
+    current_user: CurrentUser,
+    claims: Option<Extension<Claims>>,
+    payload: Option<Json<LogoutRequest>>,
+) -> Result<StatusCode, Error> {
+    // Extracting `CurrentUser` above inserts the claims of the access token the request was
+    // authenticated with. Requests authenticated with an API key carry no claims.
+    let Some(Extension(claims)) = claims else {
+        return Err(Error::Forbidden);
+    };
+    let expires_at = DateTime::from_timestamp(claims.exp, 0).unwrap_or_else(Utc::now);
+    let mut tx = transaction(&app_state.db_pool).await?;
+
+    revoked_tokens::revoke(claims.jti, expires_at, &mut *tx).await?;
+
+    if let Some(refresh_token) = payload.and_then(|Json(payload)| payload.refresh_token) {
+        match refresh_tokens::load_by_hash(&tokens::hash(&refresh_token), &mut *tx).await {
+            Ok(stored) if stored.user_id == current_user.id => {
+                refresh_tokens::revoke_family(stored.family_id, &mut *tx).await?;
+            }
+            Ok(_) | Err(forge_api_db::Error::NoRecordFound) => {}
+            Err(e) => return Err(e.into()),
         }
-        Ok(Some(user)) => user,
+    }
+
+    tx.commit().await.map_err(forge_api_db::Error::DbError)?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Revokes all access and refresh tokens that have been issued to the current user, logging out all of their sessions.
+#[axum::debug_handler]
+pub async fn logout_all_handler(
+    State(app_state): State<SharedAppState>,
+    current_user: CurrentUser,
+) -> Result<StatusCode, Error> {
+    current_user.require_session()?;
+    let mut tx = transaction(&app_state.db_pool).await?;
+
+    users::revoke_all_tokens(current_user.id, &mut *tx).await?;
+    refresh_tokens::revoke_all_for_user(current_user.id, &mut *tx).await?;
+
+    tx.commit().await.map_err(forge_api_db::Error::DbError)?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Generates a new refresh token, returning it in plain text along with its hash and expiry for storing it.
+fn new_refresh_token(app_state: &AppState) -> (String, String, DateTime<Utc>) {
+    let token = tokens::generate();
+    let token_hash = tokens::hash(&token);
+    let ttl_secs = app_state.config.auth.refresh_token_ttl_secs;
+    let expires_at = Utc::now() + Duration::seconds(ttl_secs.min(i32::MAX as u64) as i64);
+
+    (token, token_hash, expires_at)
+}
+
+/// Rotates the passed refresh token, returning a new access and refresh token or `None` if the token can't be used.
+async fn rotate_refresh_token(
+    app_state: &AppState,
+    token: &str,
+) -> Result<Option<TokenResponse>, anyhow::Error> {
+    let mut tx = transaction(&app_state.db_pool).await?;
+
+    let stored = match refresh_tokens::load_by_hash(&tokens::hash(token), &mut *tx).await {
+        Ok(stored) => stored,
+        Err(forge_api_db::Error::NoRecordFound) => return Ok(None),
+        Err(e) => return Err(e.into()),
     };
-    // Validate password
-    let pass = pass.unwrap(); // Safe because we checked above
-
-    let password_ok = verify(&pass, &user.pass).unwrap_or(false);
-    info!("password_ok {}", password_ok);
-
-    println!("Stored hash: '{}'", &user.pass);
-    if !password_ok {
-        return (
-            StatusCode::UNAUTHORIZED,
-            Json(json!({"success": false, "message": "Invalid credentials"})),
-        );
+
+    if stored.revoked_at.is_some() || stored.expires_at <= Utc::now() {
+        return Ok(None);
     }
 
-    match jwt_lib::get_jwt(user).await {
-        Ok(token) => (
-            StatusCode::OK,
-            Json(json!({"success": true, "data": { "token": token}})),
-        ),
-        Err(e) => (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(json!({"success": false, "message": e})),
-        ),
+    match refresh_tokens::rotate(stored.id, &mut *tx).await {
+        Ok(()) => {}
+        Err(forge_api_db::Error::NoRecordFound) => {
+            warn!(family_id = %stored.family_id, "Refresh token reuse detected, revoking token family");
+            refresh_tokens::revoke_family(stored.family_id, &mut *tx).await?;
+            tx.commit().await?;
+            return Ok(None);
+        }
+        Err(e) => return Err(e.into()),
     }
+
+    let user = match users::load(stored.user_id, &mut *tx).await? {
+        Some(user) => user,
+        None => return Ok(None),
+    };
+
+    let (refresh_token, token_hash, expires_at) = new_refresh_token(app_state);
+    refresh_tokens::create(user.id, stored.family_id, &token_hash, expires_at, &mut *tx).await?;
+    let token = app_state.jwt.get_jwt(&user)?;
+
+    tx.commit().await?;
+
+    Ok(Some(TokenResponse {
+        token,
+        refresh_token,
+    }))
 }
 
+/// Creates a new user account.
+///
+/// New users are assigned the default role, which grants them access to their own notes. If an email address is passed, a link for verifying it is sent to it (see [`crate::account_emails`]). If another user has already verified the address, the account is created without it and a notice is sent to the address instead, so that the response doesn't reveal which addresses are registered.
+#[utoipa::path(
+    post,
+    path = "/register",
+    request_body(content = RegisterRequest, content_type = "application/json"),
+    responses(
+        (status = CREATED, body = UserResponse),
+        (status = CONFLICT, description = "Name already taken"),
+        (status = UNPROCESSABLE_ENTITY, description = "Invalid payload")
+    )
+)]
 #[axum::debug_handler]
 pub async fn registeration_handler(
     State(app_state): State<SharedAppState>,
-    Json(payload): Json<Value>,
-) -> impl IntoResponse {
-    let name = payload.get("name").and_then(|v| v.as_str());
-    let pass = payload.get("password").and_then(|v| v.as_str());
-
-    if name.is_none() || pass.is_none() {
-        return (
-            StatusCode::BAD_REQUEST,
-            Json(json!({"success": false, "message": "Missing name or password"})),
-        );
-    }
-    // shadow variable
-    let pass = pass.unwrap();
-    let hashed_pass = hash(&pass, DEFAULT_COST);
-    let hashed_pass = hashed_pass.unwrap();
-    // Extract the actual &str from Option<&str>
-    let name = name.unwrap(); // Safe because we checked above
-
-    match insert_user(name, &hashed_pass, &app_state.db_pool).await {
-        Err(err) => {
-            eprintln!("Database insert error: {}", err);
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(json!({"success": false, "message": "Database error"})),
-            )
+    Json(payload): Json<RegisterRequest>,
+) -> Result<(StatusCode, Json<UserResponse>), Error> {
+    payload.validate()?;
+
+    let hashed_pass = app_state
+        .password_hashing
+        .hash(payload.password.clone())
+        .await?;
+
+    let mut tx = transaction(&app_state.db_pool).await?;
+    let user = insert_user(&payload.name, &hashed_pass, &mut *tx).await?;
+    roles::assign(user.id, roles::DEFAULT_ROLE, &mut *tx).await?;
+    let email_taken = match &payload.email {
+        Some(email) => {
+            let owner = users::find_user_by_verified_email(email, &mut *tx).await?;
+            if owner.is_none() {
+                users::set_email(user.id, email, &mut *tx).await?;
+            }
+            owner.is_some()
         }
+        None => false,
+    };
+    tx.commit().await.map_err(forge_api_db::Error::DbError)?;
 
-        Ok(user) => (
-            StatusCode::OK,
-            Json(json!({"success": true, "data": { "user": user.name}})),
-        ),
+    match payload.email {
+        Some(email) if email_taken => {
+            account_emails::send_email_taken_notice(app_state.clone(), email)
+        }
+        Some(email) => account_emails::send_verification_email(app_state.clone(), user.id, email),
+        None => {}
     }
+
+    Ok((
+        StatusCode::CREATED,
+        Json(UserResponse {
+            id: user.id,
+            name: user.name,
+        }),
+    ))
 }
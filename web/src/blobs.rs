@@ -0,0 +1,88 @@
+use async_trait::async_trait;
+use std::io;
+use std::path::PathBuf;
+use std::pin::Pin;
+use tokio::fs;
+use tokio::io::AsyncRead;
+
+/// A stream of the contents of a blob.
+pub type BlobReader = Pin<Box<dyn AsyncRead + Send>>;
+
+/// Storage for the contents of files, e.g. attachments, addressed by opaque keys.
+///
+/// Implementations stream contents in and out rather than holding them in memory so that large files don't have to fit into memory. Keys consist of ASCII letters, digits, `-` and `_` only.
+#[async_trait]
+pub trait BlobStore: Send + Sync {
+    /// Stores the contents read from `data` under `key`, replacing any blob stored under it before, and returns the number of bytes stored.
+    async fn put(&self, key: &str, data: &mut (dyn AsyncRead + Send + Unpin)) -> io::Result<u64>;
+
+    /// Opens the blob stored under `key` for reading.
+    ///
+    /// If there's no such blob, an error of kind [`io::ErrorKind::NotFound`] is returned.
+    async fn get(&self, key: &str) -> io::Result<BlobReader>;
+
+    /// Deletes the blob stored under `key`. Deleting a blob that doesn't exist is not an error.
+    async fn delete(&self, key: &str) -> io::Result<()>;
+}
+
+/// A [`BlobStore`] that keeps every blob in a file in a directory on the local filesystem (see [`forge_api_config::AttachmentsConfig::storage_root`]).
+pub struct LocalBlobStore {
+    root: PathBuf,
+}
+
+impl LocalBlobStore {
+    /// Creates a store that keeps its blobs in the passed directory, which is created when the first blob is stored.
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    fn path(&self, key: &str) -> io::Result<PathBuf> {
+        let valid = !key.is_empty()
+            && key
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        if valid {
+            Ok(self.root.join(key))
+        } else {
+            Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("invalid blob key: {:?}", key),
+            ))
+        }
+    }
+}
+
+#[async_trait]
+impl BlobStore for LocalBlobStore {
+    async fn put(&self, key: &str, data: &mut (dyn AsyncRead + Send + Unpin)) -> io::Result<u64> {
+        let path = self.path(key)?;
+        fs::create_dir_all(&self.root).await?;
+
+        // Write to a temporary file first so that a failed upload never leaves a partial blob behind.
+        let partial_path = path.with_extension("partial");
+        let result = async {
+            let mut file = fs::File::create(&partial_path).await?;
+            let size = tokio::io::copy(data, &mut file).await?;
+            file.sync_all().await?;
+            fs::rename(&partial_path, &path).await?;
+            Ok(size)
+        }
+        .await;
+        if result.is_err() {
+            let _ = fs::remove_file(&partial_path).await;
+        }
+        result
+    }
+
+    async fn get(&self, key: &str) -> io::Result<BlobReader> {
+        let file = fs::File::open(self.path(key)?).await?;
+        Ok(Box::pin(file))
+    }
+
+    async fn delete(&self, key: &str) -> io::Result<()> {
+        match fs::remove_file(self.path(key)?).await {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+            result => result,
+        }
+    }
+}
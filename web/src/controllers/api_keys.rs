@@ -0,0 +1,116 @@
+use crate::middlewares::auth::CurrentUser;
+use crate::tokens;
+use crate::{error::Error, state::SharedAppState};
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use forge_api_db::entities::api_keys::{self, ApiKey, ApiKeyChangeset};
+use forge_api_db::entities::roles;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use validator::{ValidationError, ValidationErrors};
+
+/// A newly created key along with the key itself.
+#[derive(Serialize, Deserialize, Debug, utoipa::ToSchema)]
+pub struct CreatedApiKey {
+    /// The key to send as `Authorization: Bearer <key>`. It is only ever returned here as only its hash is stored.
+    pub key: String,
+    #[serde(flatten)]
+    pub api_key: ApiKey,
+}
+
+/// Lists the current user's API keys that haven't been revoked, including when they were last used.
+#[utoipa::path(
+    get,
+    path = "/account/api-keys",
+    responses(
+        (status = OK, body = Vec<ApiKey>),
+        (status = UNAUTHORIZED, description = "Not authenticated"),
+        (status = FORBIDDEN, description = "Authenticated with an API key")
+    )
+)]
+#[axum::debug_handler]
+pub async fn read_all(
+    State(app_state): State<SharedAppState>,
+    user: CurrentUser,
+) -> Result<Json<Vec<ApiKey>>, Error> {
+    user.require_session()?;
+
+    let keys = api_keys::load_all(user.id, &app_state.db_pool).await?;
+
+    Ok(Json(keys))
+}
+
+/// Creates an API key for machine clients, e.g. scripts or CI jobs, that can be used instead of an access token.
+///
+/// The key can only be used for the permissions in its scopes, which must be granted to the current user. Should the user lose a permission later on, the key can't be used for it anymore either.
+#[utoipa::path(
+    post,
+    path = "/account/api-keys",
+    request_body = ApiKeyChangeset,
+    responses(
+        (status = CREATED, body = CreatedApiKey),
+        (status = UNAUTHORIZED, description = "Not authenticated"),
+        (status = FORBIDDEN, description = "Authenticated with an API key"),
+        (status = CONFLICT, description = "Name already taken"),
+        (status = UNPROCESSABLE_ENTITY, description = "Invalid payload, e.g. a scope the user lacks or an expiry in the past")
+    )
+)]
+#[axum::debug_handler]
+pub async fn create(
+    State(app_state): State<SharedAppState>,
+    user: CurrentUser,
+    Json(api_key): Json<ApiKeyChangeset>,
+) -> Result<(StatusCode, Json<CreatedApiKey>), Error> {
+    user.require_session()?;
+
+    let granted = roles::permissions_of(user.id, &app_state.db_pool).await?;
+    if let Some(scope) = api_key.scopes.iter().find(|scope| !granted.contains(scope)) {
+        let mut errors = ValidationErrors::new();
+        errors.add(
+            "scopes",
+            ValidationError::new("scope").with_message(
+                format!("{:?} is not a permission granted to the user", scope).into(),
+            ),
+        );
+        return Err(Error::Validation(errors));
+    }
+
+    let (key, prefix) = tokens::generate_api_key();
+    let api_key = api_keys::create(
+        user.id,
+        &prefix,
+        &tokens::hash(&key),
+        api_key,
+        &app_state.db_pool,
+    )
+    .await?;
+
+    Ok((StatusCode::CREATED, Json(CreatedApiKey { key, api_key })))
+}
+
+/// Revokes an API key of the current user so it can't be used anymore.
+#[utoipa::path(
+    delete,
+    path = "/account/api-keys/{id}",
+    responses(
+        (status = NO_CONTENT),
+        (status = UNAUTHORIZED, description = "Not authenticated"),
+        (status = FORBIDDEN, description = "Authenticated with an API key"),
+        (status = NOT_FOUND, description = "No such key")
+    )
+)]
+#[axum::debug_handler]
+pub async fn delete(
+    State(app_state): State<SharedAppState>,
+    user: CurrentUser,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode, Error> {
+    user.require_session()?;
+
+    api_keys::revoke(id, user.id, &app_state.db_pool).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
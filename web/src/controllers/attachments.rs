@@ -0,0 +1,250 @@
+use crate::permissions::{ReadNotes, RequirePermission, UpdateNotes};
+use crate::{error::Error, state::SharedAppState};
+use axum::{
+    body::Body,
+    extract::{Multipart, Path, State},
+    http::{header, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use forge_api_db::entities::attachments::{self, Attachment, NewAttachment};
+use forge_api_db::entities::note_shares::{self, SharePermission};
+use futures_util::TryStreamExt;
+use std::io;
+use tokio::io::AsyncReadExt;
+use tokio_util::io::{ReaderStream, StreamReader};
+use uuid::Uuid;
+use validator::{ValidationError, ValidationErrors};
+
+/// The name of the multipart field that carries the uploaded file.
+const FILE_FIELD: &str = "file";
+
+/// Lists the files attached to a note.
+#[utoipa::path(
+    get,
+    path = "/notes/{id}/attachments",
+    responses(
+        (status = OK, body = Vec<Attachment>),
+        (status = NOT_FOUND, description = "No such note")
+    )
+)]
+#[axum::debug_handler]
+pub async fn read_all(
+    State(app_state): State<SharedAppState>,
+    RequirePermission { user, .. }: RequirePermission<ReadNotes>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<Vec<Attachment>>, Error> {
+    // Distinguish notes without attachments from notes that don't exist.
+    note_shares::load_access(id, user.id, &app_state.db_pool).await?;
+    let attachments = attachments::load_all(id, user.id, &app_state.db_pool).await?;
+
+    Ok(Json(attachments))
+}
+
+/// Attaches a file to a note.
+///
+/// The file is uploaded as the `file` field of a `multipart/form-data` body and streamed to the blob store. Its size and MIME type must be within the limits configured in [`forge_api_config::AttachmentsConfig`].
+#[utoipa::path(
+    post,
+    path = "/notes/{id}/attachments",
+    request_body(content_type = "multipart/form-data", description = "The file to attach as the `file` field"),
+    responses(
+        (status = CREATED, body = Attachment),
+        (status = FORBIDDEN, description = "The note is only shared for reading"),
+        (status = NOT_FOUND, description = "No such note"),
+        (status = PAYLOAD_TOO_LARGE, description = "The file is larger than allowed"),
+        (status = UNSUPPORTED_MEDIA_TYPE, description = "Files of the type are not allowed"),
+        (status = UNPROCESSABLE_ENTITY, description = "The file or its name is missing")
+    )
+)]
+#[axum::debug_handler]
+pub async fn create(
+    State(app_state): State<SharedAppState>,
+    RequirePermission { user, .. }: RequirePermission<UpdateNotes>,
+    Path(id): Path<Uuid>,
+    mut multipart: Multipart,
+) -> Result<(StatusCode, Json<Attachment>), Error> {
+    // Check access up front so that no blob is stored for uploads that are rejected anyway.
+    if note_shares::load_access(id, user.id, &app_state.db_pool).await? != SharePermission::Write {
+        return Err(Error::Forbidden);
+    }
+
+    let config = &app_state.config.attachments;
+    while let Some(field) = multipart.next_field().await? {
+        if field.name() != Some(FILE_FIELD) {
+            continue;
+        }
+
+        let content_type = field
+            .content_type()
+            .and_then(|value| value.split(';').next())
+            .map(|essence| essence.trim().to_ascii_lowercase())
+            .unwrap_or_default();
+        if !config.allowed_content_types.contains(&content_type) {
+            return Err(Error::UnsupportedMediaType);
+        }
+        let file_name = field
+            .file_name()
+            .map(base_name)
+            .ok_or_else(|| missing(FILE_FIELD))?;
+
+        let blob_key = Uuid::new_v4().simple().to_string();
+        // Read at most one byte more than allowed to detect oversized files without reading them completely.
+        let mut data = Box::pin(
+            StreamReader::new(field.map_err(io::Error::other)).take(config.max_size_bytes + 1),
+        );
+        let size = app_state
+            .blob_store
+            .put(&blob_key, &mut data)
+            .await
+            .map_err(anyhow::Error::from)?;
+        if size > config.max_size_bytes {
+            delete_blob(&app_state, &blob_key).await;
+            return Err(Error::PayloadTooLarge);
+        }
+
+        let attachment = NewAttachment {
+            blob_key: blob_key.clone(),
+            file_name,
+            content_type,
+            size: size as i64,
+        };
+        return match attachments::create(id, user.id, attachment, &app_state.db_pool).await {
+            Ok(attachment) => Ok((StatusCode::CREATED, Json(attachment))),
+            Err(e) => {
+                delete_blob(&app_state, &blob_key).await;
+                Err(e.into())
+            }
+        };
+    }
+
+    Err(missing(FILE_FIELD))
+}
+
+/// Strips any directories that clients may send along with a file name.
+fn base_name(file_name: &str) -> String {
+    file_name
+        .rsplit(['/', '\\'])
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_string()
+}
+
+fn missing(field: &'static str) -> Error {
+    let mut errors = ValidationErrors::new();
+    errors.add(
+        field,
+        ValidationError::new("required").with_message("is required".into()),
+    );
+    Error::Validation(errors)
+}
+
+/// Deletes a blob that is not referenced anymore, logging rather than returning failures.
+async fn delete_blob(app_state: &SharedAppState, key: &str) {
+    if let Err(e) = app_state.blob_store.delete(key).await {
+        tracing::error!(err.msg = %e, err.details = ?e, blob_key = key, "Deleting blob failed");
+    }
+}
+
+/// Downloads a file attached to a note.
+///
+/// The file is streamed from the blob store with the MIME type it was uploaded with. It is always sent as a download rather than being displayed inline.
+#[utoipa::path(
+    get,
+    path = "/notes/{id}/attachments/{attachment_id}",
+    responses(
+        (status = OK, content_type = "application/octet-stream", body = Vec<u8>, description = "The contents of the file"),
+        (status = NOT_FOUND, description = "No such note or attachment")
+    )
+)]
+#[axum::debug_handler]
+pub async fn read_one(
+    State(app_state): State<SharedAppState>,
+    RequirePermission { user, .. }: RequirePermission<ReadNotes>,
+    Path((id, attachment_id)): Path<(Uuid, Uuid)>,
+) -> Result<Response, Error> {
+    let attachment = attachments::load(attachment_id, id, user.id, &app_state.db_pool).await?;
+    let data = app_state
+        .blob_store
+        .get(&attachment.blob_key)
+        .await
+        .map_err(anyhow::Error::from)?;
+
+    let content_type = HeaderValue::from_str(&attachment.content_type)
+        .unwrap_or(HeaderValue::from_static("application/octet-stream"));
+    Ok((
+        [
+            (header::CONTENT_TYPE, content_type),
+            (header::CONTENT_LENGTH, HeaderValue::from(attachment.size)),
+            (
+                header::CONTENT_DISPOSITION,
+                content_disposition(&attachment.file_name),
+            ),
+            (
+                header::X_CONTENT_TYPE_OPTIONS,
+                HeaderValue::from_static("nosniff"),
+            ),
+        ],
+        Body::from_stream(ReaderStream::new(data)),
+    )
+        .into_response())
+}
+
+/// Builds a `Content-Disposition` header that makes clients download a file under its name.
+///
+/// The name is passed both as a plain ASCII approximation and percent-encoded as UTF-8 (see [RFC 6266](https://www.rfc-editor.org/rfc/rfc6266)).
+fn content_disposition(file_name: &str) -> HeaderValue {
+    let ascii_name: String = file_name
+        .chars()
+        .map(|c| {
+            if (c.is_ascii_graphic() && c != '"' && c != '\\') || c == ' ' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    let encoded_name: String = file_name
+        .bytes()
+        .map(|b| {
+            if b.is_ascii_alphanumeric() || b"-._~".contains(&b) {
+                char::from(b).to_string()
+            } else {
+                format!("%{:02X}", b)
+            }
+        })
+        .collect();
+
+    HeaderValue::from_str(&format!(
+        "attachment; filename=\"{}\"; filename*=UTF-8''{}",
+        ascii_name, encoded_name
+    ))
+    .unwrap_or(HeaderValue::from_static("attachment"))
+}
+
+/// Deletes a file attached to a note.
+#[utoipa::path(
+    delete,
+    path = "/notes/{id}/attachments/{attachment_id}",
+    responses(
+        (status = NO_CONTENT),
+        (status = FORBIDDEN, description = "The note is only shared for reading"),
+        (status = NOT_FOUND, description = "No such note or attachment")
+    )
+)]
+#[axum::debug_handler]
+pub async fn delete(
+    State(app_state): State<SharedAppState>,
+    RequirePermission { user, .. }: RequirePermission<UpdateNotes>,
+    Path((id, attachment_id)): Path<(Uuid, Uuid)>,
+) -> Result<StatusCode, Error> {
+    if note_shares::load_access(id, user.id, &app_state.db_pool).await? != SharePermission::Write {
+        return Err(Error::Forbidden);
+    }
+
+    let blob_key = attachments::delete(attachment_id, id, user.id, &app_state.db_pool).await?;
+    delete_blob(&app_state, &blob_key).await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -1,2 +1,14 @@
+/// All endpoints for managing personal API keys
+pub mod api_keys;
+/// All endpoints for the files attached to notes
+pub mod attachments;
+/// All endpoints for public links to notes
+pub mod note_links;
+/// All endpoints for the revision history of notes
+pub mod note_revisions;
+/// All endpoints for sharing notes with other users
+pub mod note_shares;
 /// All endpoints for managing notes
 pub mod notes;
+/// All endpoints for the tags of notes
+pub mod tags;
@@ -0,0 +1,130 @@
+use crate::controllers::notes::prefers_html;
+use crate::permissions::{ReadNotes, RequirePermission, UpdateNotes};
+use crate::render;
+use crate::tokens;
+use crate::{error::Error, state::SharedAppState};
+use axum::{
+    extract::{Path, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{Html, IntoResponse, Response},
+    Json,
+};
+use forge_api_db::entities::note_links::{self, LinkedNote, NoteLink, NoteLinkChangeset};
+use forge_api_db::entities::notes;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// A newly created link along with its token.
+#[derive(Serialize, Deserialize, Debug, utoipa::ToSchema)]
+pub struct CreatedNoteLink {
+    /// The token to pass to `GET /s/{token}`. It is only ever returned here as only its hash is stored.
+    pub token: String,
+    #[serde(flatten)]
+    pub link: NoteLink,
+}
+
+/// Lists the links to a note. Only the note's owner can see its links.
+#[utoipa::path(
+    get,
+    path = "/notes/{id}/links",
+    responses(
+        (status = OK, body = Vec<NoteLink>),
+        (status = NOT_FOUND, description = "No such note")
+    )
+)]
+#[axum::debug_handler]
+pub async fn read_all(
+    State(app_state): State<SharedAppState>,
+    RequirePermission { user, .. }: RequirePermission<ReadNotes>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<Vec<NoteLink>>, Error> {
+    // Distinguish notes without links from notes that don't exist or that the user doesn't own.
+    let note = notes::load(id, user.id, &app_state.db_pool).await?;
+    if note.owner_id != user.id {
+        return Err(forge_api_db::Error::NoRecordFound.into());
+    }
+    let links = note_links::load_all(id, user.id, &app_state.db_pool).await?;
+
+    Ok(Json(links))
+}
+
+/// Creates a link that gives anyone who knows it read access to a note, optionally expiring at some time or after a number of views.
+#[utoipa::path(
+    post,
+    path = "/notes/{id}/links",
+    request_body = NoteLinkChangeset,
+    responses(
+        (status = CREATED, body = CreatedNoteLink),
+        (status = NOT_FOUND, description = "No such note"),
+        (status = UNPROCESSABLE_ENTITY, description = "The expiry is in the past or the view limit is not positive")
+    )
+)]
+#[axum::debug_handler]
+pub async fn create(
+    State(app_state): State<SharedAppState>,
+    RequirePermission { user, .. }: RequirePermission<UpdateNotes>,
+    Path(id): Path<Uuid>,
+    Json(link): Json<NoteLinkChangeset>,
+) -> Result<(StatusCode, Json<CreatedNoteLink>), Error> {
+    let token = tokens::generate();
+    let link =
+        note_links::create(id, user.id, &tokens::hash(&token), link, &app_state.db_pool).await?;
+
+    Ok((StatusCode::CREATED, Json(CreatedNoteLink { token, link })))
+}
+
+/// Revokes a link to a note so it can't be used anymore.
+#[utoipa::path(
+    delete,
+    path = "/notes/{id}/links/{link_id}",
+    responses(
+        (status = NO_CONTENT),
+        (status = NOT_FOUND, description = "No such link")
+    )
+)]
+#[axum::debug_handler]
+pub async fn delete(
+    State(app_state): State<SharedAppState>,
+    RequirePermission { user, .. }: RequirePermission<UpdateNotes>,
+    Path((id, link_id)): Path<(Uuid, Uuid)>,
+) -> Result<StatusCode, Error> {
+    note_links::revoke(link_id, id, user.id, &app_state.db_pool).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Loads the note a link points to. This doesn't require authentication – knowing the link's token is enough.
+///
+/// Every request counts as a view of the link. Like `GET /notes/{id}`, the note is rendered to HTML if the `Accept` header prefers `text/html`.
+#[utoipa::path(
+    get,
+    path = "/s/{token}",
+    responses(
+        (status = OK, body = LinkedNote),
+        (status = OK, content_type = "text/html", body = String, description = "The rendered text of the note"),
+        (status = NOT_FOUND, description = "No such link, or it has expired or been used up")
+    )
+)]
+#[axum::debug_handler]
+pub async fn view(
+    State(app_state): State<SharedAppState>,
+    Path(token): Path<String>,
+    headers: HeaderMap,
+) -> Result<Response, Error> {
+    let vary = (header::VARY, HeaderValue::from_static("accept"));
+    let (note, html) = note_links::view(&tokens::hash(&token), &app_state.db_pool).await?;
+
+    if !prefers_html(&headers) {
+        return Ok(([vary], Json(note)).into_response());
+    }
+
+    let html = match html {
+        Some(html) => html,
+        None => {
+            let html = render::to_html(&note.text, note.format);
+            notes::cache_html(note.id, note.version, &html, &app_state.db_pool).await?;
+            html
+        }
+    };
+    Ok(([vary], Html(html)).into_response())
+}
@@ -0,0 +1,156 @@
+use crate::etag::{etag, IfMatch};
+use crate::permissions::{ReadNotes, RequirePermission, UpdateNotes};
+use crate::{error::Error, state::SharedAppState};
+use axum::{
+    extract::{Path, State},
+    http::{header, HeaderValue},
+    Json,
+};
+use forge_api_db::entities::note_revisions::{self, NoteRevision};
+use forge_api_db::entities::note_shares;
+use forge_api_db::entities::notes::{self, Note, NoteChangeset};
+use forge_api_db::transaction;
+use serde::{Deserialize, Serialize};
+use similar::{ChangeTag, TextDiff};
+use uuid::Uuid;
+
+/// Lists the previous versions of a note, most recent first.
+#[utoipa::path(
+    get,
+    path = "/notes/{id}/revisions",
+    responses(
+        (status = OK, body = Vec<NoteRevision>),
+        (status = NOT_FOUND, description = "No such note")
+    )
+)]
+#[axum::debug_handler]
+pub async fn read_all(
+    State(app_state): State<SharedAppState>,
+    RequirePermission { user, .. }: RequirePermission<ReadNotes>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<Vec<NoteRevision>>, Error> {
+    // Distinguish notes without revisions from notes that don't exist.
+    note_shares::load_access(id, user.id, &app_state.db_pool).await?;
+    let revisions = note_revisions::load_all(id, user.id, &app_state.db_pool).await?;
+
+    Ok(Json(revisions))
+}
+
+/// Whether a line is unchanged, added or removed.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, utoipa::ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum LineChange {
+    /// The line is the same in both versions.
+    Equal,
+    /// The line only exists in the current version.
+    Insert,
+    /// The line only exists in the revision.
+    Delete,
+}
+
+/// A line of a [`RevisionDiff`].
+#[derive(Serialize, Deserialize, Debug, utoipa::ToSchema)]
+pub struct DiffLine {
+    /// How the line changed
+    pub change: LineChange,
+    /// The text of the line without the trailing line break
+    pub text: String,
+}
+
+/// The line diff between a revision of a note and its current version.
+#[derive(Serialize, Deserialize, Debug, utoipa::ToSchema)]
+pub struct RevisionDiff {
+    /// The version of the revision
+    pub from_version: i32,
+    /// The current version of the note
+    pub to_version: i32,
+    /// The lines of both versions in order
+    pub lines: Vec<DiffLine>,
+}
+
+/// Returns the line diff between a revision of a note and the note's current version, i.e. what reverting to the revision would undo.
+#[utoipa::path(
+    get,
+    path = "/notes/{id}/revisions/{rev}/diff",
+    responses(
+        (status = OK, body = RevisionDiff),
+        (status = NOT_FOUND, description = "No such note or revision")
+    )
+)]
+#[axum::debug_handler]
+pub async fn diff(
+    State(app_state): State<SharedAppState>,
+    RequirePermission { user, .. }: RequirePermission<ReadNotes>,
+    Path((id, rev)): Path<(Uuid, i32)>,
+) -> Result<Json<RevisionDiff>, Error> {
+    let note = notes::load(id, user.id, &app_state.db_pool).await?;
+    let revision = note_revisions::load(id, rev, user.id, &app_state.db_pool).await?;
+
+    // Without a final line break, the last line would never equal the same line followed by others.
+    let old_text = with_final_newline(&revision.text);
+    let new_text = with_final_newline(&note.text);
+    let lines = TextDiff::from_lines(&old_text, &new_text)
+        .iter_all_changes()
+        .map(|change| DiffLine {
+            change: match change.tag() {
+                ChangeTag::Equal => LineChange::Equal,
+                ChangeTag::Insert => LineChange::Insert,
+                ChangeTag::Delete => LineChange::Delete,
+            },
+            text: change
+                .as_str()
+                .unwrap_or_default()
+                .trim_end_matches(['\r', '\n'])
+                .to_string(),
+        })
+        .collect();
+
+    Ok(Json(RevisionDiff {
+        from_version: revision.version,
+        to_version: note.version,
+        lines,
+    }))
+}
+
+fn with_final_newline(text: &str) -> String {
+    if text.ends_with('\n') {
+        text.to_string()
+    } else {
+        format!("{}\n", text)
+    }
+}
+
+/// Reverts a note to the text of one of its revisions.
+///
+/// Reverting is an update like any other – it creates a new version of the note and keeps the current one as a revision. If an `If-Match` header is sent, the note is only reverted if the header matches its current `ETag`.
+#[utoipa::path(
+    post,
+    path = "/notes/{id}/revisions/{rev}/revert",
+    params(("If-Match" = Option<String>, Header, description = "The `ETag` the note is expected to have")),
+    responses(
+        (status = OK, body = Note, headers(("ETag" = String, description = "The new version of the note"))),
+        (status = FORBIDDEN, description = "The note is only shared for reading"),
+        (status = NOT_FOUND, description = "No such note or revision"),
+        (status = PRECONDITION_FAILED, description = "The note has been changed since")
+    )
+)]
+#[axum::debug_handler]
+pub async fn revert(
+    State(app_state): State<SharedAppState>,
+    RequirePermission { user, .. }: RequirePermission<UpdateNotes>,
+    Path((id, rev)): Path<(Uuid, i32)>,
+    if_match: IfMatch,
+) -> Result<([(header::HeaderName, HeaderValue); 1], Json<Note>), Error> {
+    let mut tx = transaction(&app_state.db_pool).await?;
+    let revision = note_revisions::load(id, rev, user.id, &mut *tx).await?;
+    note_revisions::record(id, user.id, &mut *tx).await?;
+    let changeset = NoteChangeset {
+        text: revision.text,
+        tags: None,
+        format: None,
+    };
+    let note = notes::update(id, changeset, user.id, if_match.versions(), &mut *tx).await?;
+    tx.commit().await.map_err(forge_api_db::Error::DbError)?;
+
+    Ok(([(header::ETAG, etag(note.version))], Json(note)))
+}
@@ -0,0 +1,78 @@
+use crate::permissions::{ReadNotes, RequirePermission, UpdateNotes};
+use crate::{error::Error, state::SharedAppState};
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use forge_api_db::entities::note_shares::{self, NoteShare, NoteShareChangeset};
+use forge_api_db::entities::notes;
+use uuid::Uuid;
+
+/// Lists the users a note is shared with. Only the note's owner can see its shares.
+#[utoipa::path(
+    get,
+    path = "/notes/{id}/shares",
+    responses(
+        (status = OK, body = Vec<NoteShare>),
+        (status = NOT_FOUND, description = "No such note")
+    )
+)]
+#[axum::debug_handler]
+pub async fn read_all(
+    State(app_state): State<SharedAppState>,
+    RequirePermission { user, .. }: RequirePermission<ReadNotes>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<Vec<NoteShare>>, Error> {
+    // Distinguish notes without shares from notes that don't exist or that the user doesn't own.
+    let note = notes::load(id, user.id, &app_state.db_pool).await?;
+    if note.owner_id != user.id {
+        return Err(forge_api_db::Error::NoRecordFound.into());
+    }
+    let shares = note_shares::load_all(id, user.id, &app_state.db_pool).await?;
+
+    Ok(Json(shares))
+}
+
+/// Shares a note with another user, identified by name, or changes what they may do with it.
+#[utoipa::path(
+    post,
+    path = "/notes/{id}/shares",
+    request_body = NoteShareChangeset,
+    responses(
+        (status = OK, body = NoteShare),
+        (status = NOT_FOUND, description = "No such note"),
+        (status = UNPROCESSABLE_ENTITY, description = "No such user or the user is the owner of the note")
+    )
+)]
+#[axum::debug_handler]
+pub async fn create(
+    State(app_state): State<SharedAppState>,
+    RequirePermission { user, .. }: RequirePermission<UpdateNotes>,
+    Path(id): Path<Uuid>,
+    Json(share): Json<NoteShareChangeset>,
+) -> Result<Json<NoteShare>, Error> {
+    let share = note_shares::grant(id, user.id, share, &app_state.db_pool).await?;
+
+    Ok(Json(share))
+}
+
+/// Stops sharing a note with a user.
+#[utoipa::path(
+    delete,
+    path = "/notes/{id}/shares/{user_id}",
+    responses(
+        (status = NO_CONTENT),
+        (status = NOT_FOUND, description = "The note is not shared with the user")
+    )
+)]
+#[axum::debug_handler]
+pub async fn delete(
+    State(app_state): State<SharedAppState>,
+    RequirePermission { user, .. }: RequirePermission<UpdateNotes>,
+    Path((id, user_id)): Path<(Uuid, Uuid)>,
+) -> Result<StatusCode, Error> {
+    note_shares::revoke(id, user_id, user.id, &app_state.db_pool).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -1,67 +1,608 @@
+use crate::etag::{etag, IfMatch};
+use crate::merge_patch::MergePatch;
+use crate::middlewares::auth::CurrentUser;
+use crate::permissions::{
+    CreateNotes, DeleteNotes, Permission, ReadNotes, RequirePermission, UpdateNotes,
+};
+use crate::render;
 use crate::{error::Error, state::SharedAppState};
-use axum::{extract::Path, extract::State, http::StatusCode, Json};
+use axum::{
+    extract::Path,
+    extract::Query,
+    extract::State,
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{Html, IntoResponse, Response},
+    Json,
+};
+use axum_extra::extract::Query as MultiQuery;
+use chrono::DateTime;
 use forge_api_db::entities;
-use forge_api_db::entities::notes::Note;
-use tracing::info;
+use forge_api_db::entities::notes::{
+    Cursor, Note, NoteChangeset, NoteFilter, NotePatch, SearchResult, SortOrder, TagMode,
+    TrashedNote,
+};
+use forge_api_db::entities::roles;
+use forge_api_db::{transaction, DbConnection};
+use serde::{Deserialize, Serialize};
 use uuid::Uuid;
+use validator::{Validate, ValidationError, ValidationErrors};
 
 #[utoipa::path(
-    post, 
+    post,
     path = "/notes",
     request_body(content = Note, content_type = "application/json"),
-    responses((status = OK, body = Note))
+    responses((status = CREATED, body = Note, headers(("ETag" = String, description = "The version of the note"))))
 )]
 #[axum::debug_handler]
 pub async fn create(
     State(app_state): State<SharedAppState>,
+    RequirePermission { user, .. }: RequirePermission<CreateNotes>,
     Json(note): Json<entities::notes::NoteChangeset>,
-) -> Result<(StatusCode, Json<entities::notes::Note>), Error> {
-    info!("respondingggggggggggggggggggggg");
-    let note = entities::notes::create(note, &app_state.db_pool).await?;
-    info!("responding with {:?}", note);
-    Ok((StatusCode::CREATED, Json(note)))
+) -> Result<
+    (
+        StatusCode,
+        [(header::HeaderName, HeaderValue); 1],
+        Json<Note>,
+    ),
+    Error,
+> {
+    let note = entities::notes::create(note, user.id, &app_state.db_pool).await?;
+    Ok((
+        StatusCode::CREATED,
+        [(header::ETAG, etag(note.version))],
+        Json(note),
+    ))
 }
 
-#[utoipa::path(get, path = "/notes", responses((status = OK, body = Note)))]
+/// The query parameters of `GET /notes`.
+#[derive(Deserialize, Validate, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct NotesQuery {
+    /// The maximum number of notes to return, 1 to 100, defaults to 20
+    #[validate(range(min = 1, max = 100))]
+    pub limit: Option<i64>,
+    /// The `next_cursor` of the previous page to continue after
+    pub cursor: Option<String>,
+    /// Whether to return the oldest (`asc`) or newest (`desc`) notes first, defaults to `desc`
+    #[param(inline)]
+    pub order: Option<SortOrder>,
+    /// Only return notes containing this text, compared case-insensitively
+    pub text: Option<String>,
+    /// Only return notes with this tag, compared case-insensitively. May be passed multiple times
+    #[serde(default)]
+    pub tag: Vec<String>,
+    /// Whether notes must have `all` of the passed tags or `any` of them, defaults to `all`
+    #[param(inline)]
+    pub tag_mode: Option<TagMode>,
+}
+
+/// A page of notes.
+#[derive(Serialize, Deserialize, utoipa::ToSchema)]
+pub struct NotesPage {
+    /// The notes on this page
+    pub items: Vec<Note>,
+    /// The cursor to pass for loading the next page, missing on the last page
+    pub next_cursor: Option<String>,
+}
+
+const DEFAULT_PAGE_SIZE: i64 = 20;
+
+#[utoipa::path(
+    get,
+    path = "/notes",
+    params(NotesQuery),
+    responses(
+        (status = OK, body = NotesPage),
+        (status = UNPROCESSABLE_ENTITY, description = "Invalid query parameters")
+    )
+)]
 #[axum::debug_handler]
 pub async fn read_all(
     State(app_state): State<SharedAppState>,
-) -> Result<Json<Vec<entities::notes::Note>>, Error> {
-    // /* Example:
-    let notes = entities::notes::load_all(&app_state.db_pool).await?;
+    RequirePermission { user, .. }: RequirePermission<ReadNotes>,
+    MultiQuery(query): MultiQuery<NotesQuery>,
+) -> Result<Json<NotesPage>, Error> {
+    query.validate()?;
+    let after = query.cursor.as_deref().map(decode_cursor).transpose()?;
 
-    info!("responding with {:?}", notes);
+    let (items, next) = entities::notes::load_page(
+        user.id,
+        NoteFilter {
+            text: query.text.as_deref(),
+            tags: &query.tag,
+            tag_mode: query.tag_mode.unwrap_or_default(),
+        },
+        query.order.unwrap_or_default(),
+        after,
+        query.limit.unwrap_or(DEFAULT_PAGE_SIZE),
+        &app_state.db_pool,
+    )
+    .await?;
 
-    Ok(Json(notes))
+    Ok(Json(NotesPage {
+        items,
+        next_cursor: next.map(encode_cursor),
+    }))
+}
+
+/// The query parameters of `GET /notes/search`.
+#[derive(Deserialize, Validate, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct SearchQuery {
+    /// The words to search for. Words in double quotes must occur as a phrase, words ending in `*` match all words they are a prefix of
+    #[validate(length(min = 1, max = 256))]
+    pub q: String,
+    /// The maximum number of results to return, 1 to 100, defaults to 20
+    #[validate(range(min = 1, max = 100))]
+    pub limit: Option<i64>,
 }
 
-#[utoipa::path(get, path = "/notes/{id}", responses((status = OK, body = Note)))]
+/// Searches the notes of the current user, returning the best matches first.
+#[utoipa::path(
+    get,
+    path = "/notes/search",
+    params(SearchQuery),
+    responses(
+        (status = OK, body = Vec<SearchResult>),
+        (status = UNPROCESSABLE_ENTITY, description = "Invalid query parameters")
+    )
+)]
+#[axum::debug_handler]
+pub async fn search(
+    State(app_state): State<SharedAppState>,
+    RequirePermission { user, .. }: RequirePermission<ReadNotes>,
+    Query(query): Query<SearchQuery>,
+) -> Result<Json<Vec<SearchResult>>, Error> {
+    query.validate()?;
+
+    let results = entities::notes::search(
+        user.id,
+        &query.q,
+        query.limit.unwrap_or(DEFAULT_PAGE_SIZE),
+        &app_state.db_pool,
+    )
+    .await?;
+
+    Ok(Json(results))
+}
+
+/// Encodes a cursor as an opaque string that clients pass back as-is.
+fn encode_cursor(cursor: Cursor) -> String {
+    hex::encode(format!(
+        "{}.{}",
+        cursor.created_at.timestamp_micros(),
+        cursor.id
+    ))
+}
+
+fn decode_cursor(value: &str) -> Result<Cursor, ValidationErrors> {
+    let invalid = || {
+        let mut errors = ValidationErrors::new();
+        errors.add(
+            "cursor",
+            ValidationError::new("cursor").with_message("is invalid".into()),
+        );
+        errors
+    };
+
+    let decoded = hex::decode(value)
+        .ok()
+        .and_then(|bytes| String::from_utf8(bytes).ok())
+        .ok_or_else(invalid)?;
+    let (created_at, id) = decoded.split_once('.').ok_or_else(invalid)?;
+    let created_at = created_at
+        .parse()
+        .ok()
+        .and_then(DateTime::from_timestamp_micros)
+        .ok_or_else(invalid)?;
+    let id = id.parse().map_err(|_| invalid())?;
+
+    Ok(Cursor { created_at, id })
+}
+
+/// Loads a note.
+///
+/// Responds with the note as JSON unless the `Accept` header prefers `text/html`, in which case the note's text is rendered to sanitized HTML according to its format (see [`crate::render::to_html`]). The rendering is cached until the note's text or format changes.
+#[utoipa::path(
+    get,
+    path = "/notes/{id}",
+    responses(
+        (status = OK, body = Note, headers(("ETag" = String, description = "The version of the note"))),
+        (status = OK, content_type = "text/html", body = String, description = "The rendered text of the note"),
+        (status = NOT_FOUND, description = "No such note")
+    )
+)]
 #[axum::debug_handler]
 pub async fn read_one(
     State(app_state): State<SharedAppState>,
+    RequirePermission { user, .. }: RequirePermission<ReadNotes>,
     Path(id): Path<Uuid>,
-) -> Result<Json<entities::notes::Note>, Error> {
-    let note = entities::notes::load(id, &app_state.db_pool).await?;
-    Ok(Json(note))
+    headers: HeaderMap,
+) -> Result<Response, Error> {
+    let vary = (header::VARY, HeaderValue::from_static("accept"));
+
+    if !prefers_html(&headers) {
+        let note = entities::notes::load(id, user.id, &app_state.db_pool).await?;
+        return Ok(([(header::ETAG, etag(note.version)), vary], Json(note)).into_response());
+    }
+
+    let (note, html) = entities::notes::load_with_html(id, user.id, &app_state.db_pool).await?;
+    let html = match html {
+        Some(html) => html,
+        None => {
+            let html = render::to_html(&note.text, note.format);
+            entities::notes::cache_html(note.id, note.version, &html, &app_state.db_pool).await?;
+            html
+        }
+    };
+    Ok(([vary], Html(html)).into_response())
+}
+
+/// Whether the `Accept` header ranks `text/html` higher than JSON.
+///
+/// Each of the two gets the quality of the most specific media range matching it, e.g. `text/*` counts for `text/html` unless `text/html` is listed itself (see RFC 9110, section 12.5.1). JSON wins ties as well as requests without an `Accept` header.
+pub(crate) fn prefers_html(headers: &HeaderMap) -> bool {
+    if !headers.contains_key(header::ACCEPT) {
+        return false;
+    }
+
+    let ranges: Vec<(String, f32)> = headers
+        .get_all(header::ACCEPT)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(|range| {
+            let mut parts = range.split(';').map(str::trim);
+            let media_range = parts.next().unwrap_or_default().to_ascii_lowercase();
+            let quality = parts
+                .find_map(|param| {
+                    param
+                        .strip_prefix("q=")
+                        .or_else(|| param.strip_prefix("Q="))
+                })
+                .and_then(|q| q.parse().ok())
+                .unwrap_or(1.0);
+            (media_range, quality)
+        })
+        .collect();
+
+    quality_of("text/html", &ranges) > quality_of("application/json", &ranges)
 }
 
-#[utoipa::path(put, path = "/notes/{id}", responses((status = OK, body = Note)))]
+/// Returns the quality the passed media ranges assign to a media type, which is 0 if none matches.
+fn quality_of(media_type: &str, ranges: &[(String, f32)]) -> f32 {
+    let main_type = media_type.split('/').next().unwrap_or_default();
+    let mut best: Option<(u8, f32)> = None;
+    for (range, quality) in ranges {
+        let specificity = match range.split_once('/') {
+            _ if range == media_type => 2,
+            Some((range_type, "*")) if range_type == main_type => 1,
+            Some(("*", "*")) => 0,
+            _ => continue,
+        };
+        if best.is_none_or(|(best_specificity, best_quality)| {
+            specificity > best_specificity
+                || (specificity == best_specificity && *quality > best_quality)
+        }) {
+            best = Some((specificity, *quality));
+        }
+    }
+
+    best.map_or(0.0, |(_, quality)| quality)
+}
+
+/// Updates a note.
+///
+/// The previous version of the note is kept as a revision (see [`crate::controllers::note_revisions`]). If an `If-Match` header is sent, the note is only updated if the header matches its current `ETag`.
+#[utoipa::path(
+    put,
+    path = "/notes/{id}",
+    params(("If-Match" = Option<String>, Header, description = "The `ETag` the note is expected to have")),
+    responses(
+        (status = OK, body = Note, headers(("ETag" = String, description = "The new version of the note"))),
+        (status = NOT_FOUND, description = "No such note"),
+        (status = PRECONDITION_FAILED, description = "The note has been changed since")
+    )
+)]
 #[axum::debug_handler]
 pub async fn update(
     State(app_state): State<SharedAppState>,
+    RequirePermission { user, .. }: RequirePermission<UpdateNotes>,
     Path(id): Path<Uuid>,
+    if_match: IfMatch,
     Json(note): Json<entities::notes::NoteChangeset>,
-) -> Result<Json<entities::notes::Note>, Error> {
-    let note = entities::notes::update(id, note, &app_state.db_pool).await?;
-    Ok(Json(note))
+) -> Result<([(header::HeaderName, HeaderValue); 1], Json<Note>), Error> {
+    let mut tx = transaction(&app_state.db_pool).await?;
+    entities::note_revisions::record(id, user.id, &mut *tx).await?;
+    let note = entities::notes::update(id, note, user.id, if_match.versions(), &mut *tx).await?;
+    tx.commit().await.map_err(forge_api_db::Error::DbError)?;
+
+    Ok(([(header::ETAG, etag(note.version))], Json(note)))
 }
 
-#[utoipa::path(delete, path = "/notes/{id}", responses((status = OK, body = Note)))]
+/// Partially updates a note.
+///
+/// The request body is a JSON Merge Patch (see [RFC 7396](https://www.rfc-editor.org/rfc/rfc7396)) – only the fields it contains are changed. The previous version of the note is kept as a revision and an `If-Match` header is handled like for `PUT /notes/{id}`.
+#[utoipa::path(
+    patch,
+    path = "/notes/{id}",
+    params(("If-Match" = Option<String>, Header, description = "The `ETag` the note is expected to have")),
+    request_body(content = NotePatch, content_type = "application/merge-patch+json"),
+    responses(
+        (status = OK, body = Note, headers(("ETag" = String, description = "The new version of the note"))),
+        (status = NOT_FOUND, description = "No such note"),
+        (status = PRECONDITION_FAILED, description = "The note has been changed since"),
+        (status = UNSUPPORTED_MEDIA_TYPE, description = "The body is not sent as `application/merge-patch+json`"),
+        (status = UNPROCESSABLE_ENTITY, description = "Invalid patch")
+    )
+)]
+#[axum::debug_handler]
+pub async fn patch(
+    State(app_state): State<SharedAppState>,
+    RequirePermission { user, .. }: RequirePermission<UpdateNotes>,
+    Path(id): Path<Uuid>,
+    if_match: IfMatch,
+    MergePatch(patch): MergePatch<NotePatch>,
+) -> Result<([(header::HeaderName, HeaderValue); 1], Json<Note>), Error> {
+    let mut tx = transaction(&app_state.db_pool).await?;
+    entities::note_revisions::record(id, user.id, &mut *tx).await?;
+    let note = entities::notes::patch(id, patch, user.id, if_match.versions(), &mut *tx).await?;
+    tx.commit().await.map_err(forge_api_db::Error::DbError)?;
+
+    Ok(([(header::ETAG, etag(note.version))], Json(note)))
+}
+
+/// Moves a note to the trash.
+///
+/// Trashed notes can be restored via `POST /notes/{id}/restore` until they are purged after the configured retention period. If an `If-Match` header is sent, the note is only trashed if the header matches its current `ETag`.
+#[utoipa::path(
+    delete,
+    path = "/notes/{id}",
+    params(("If-Match" = Option<String>, Header, description = "The `ETag` the note is expected to have")),
+    responses(
+        (status = NO_CONTENT),
+        (status = NOT_FOUND, description = "No such note"),
+        (status = PRECONDITION_FAILED, description = "The note has been changed since")
+    )
+)]
 #[axum::debug_handler]
 pub async fn delete(
     State(app_state): State<SharedAppState>,
+    RequirePermission { user, .. }: RequirePermission<DeleteNotes>,
     Path(id): Path<Uuid>,
+    if_match: IfMatch,
 ) -> Result<StatusCode, Error> {
-    entities::notes::delete(id, &app_state.db_pool).await?;
+    entities::notes::delete(id, user.id, if_match.versions(), &app_state.db_pool).await?;
     Ok(StatusCode::NO_CONTENT)
 }
+
+/// Lists the notes of the current user that are in the trash, most recently trashed first.
+#[utoipa::path(get, path = "/notes/trash", responses((status = OK, body = Vec<TrashedNote>)))]
+#[axum::debug_handler]
+pub async fn read_trash(
+    State(app_state): State<SharedAppState>,
+    RequirePermission { user, .. }: RequirePermission<ReadNotes>,
+) -> Result<Json<Vec<TrashedNote>>, Error> {
+    let notes = entities::notes::load_trash(user.id, &app_state.db_pool).await?;
+    Ok(Json(notes))
+}
+
+/// Restores a note from the trash.
+#[utoipa::path(
+    post,
+    path = "/notes/{id}/restore",
+    responses(
+        (status = OK, body = Note, headers(("ETag" = String, description = "The version of the note"))),
+        (status = NOT_FOUND, description = "No such note in the trash")
+    )
+)]
+#[axum::debug_handler]
+pub async fn restore(
+    State(app_state): State<SharedAppState>,
+    RequirePermission { user, .. }: RequirePermission<DeleteNotes>,
+    Path(id): Path<Uuid>,
+) -> Result<([(header::HeaderName, HeaderValue); 1], Json<Note>), Error> {
+    let note = entities::notes::restore(id, user.id, &app_state.db_pool).await?;
+    Ok(([(header::ETAG, etag(note.version))], Json(note)))
+}
+
+/// Whether the operations of a batch are executed all-or-nothing or independently of each other.
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq, utoipa::ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum BatchMode {
+    /// All operations are executed in one transaction that is rolled back if any of them fails.
+    #[default]
+    AllOrNothing,
+    /// Every operation is executed in its own transaction, regardless of whether others fail.
+    BestEffort,
+}
+
+/// An operation of a batch, equivalent to the respective single-note endpoint.
+#[derive(Deserialize, utoipa::ToSchema)]
+#[serde(tag = "op", rename_all = "lowercase")]
+pub enum BatchOperation {
+    /// Creates a note like `POST /notes`.
+    Create {
+        /// The note to create
+        note: NoteChangeset,
+    },
+    /// Updates a note like `PUT /notes/{id}`.
+    Update {
+        /// The id of the note to update
+        id: Uuid,
+        /// The version the note is expected to have, like the `If-Match` header
+        version: Option<i32>,
+        /// The new contents of the note
+        note: NoteChangeset,
+    },
+    /// Moves a note to the trash like `DELETE /notes/{id}`.
+    Delete {
+        /// The id of the note to delete
+        id: Uuid,
+        /// The version the note is expected to have, like the `If-Match` header
+        version: Option<i32>,
+    },
+}
+
+/// The request body of `POST /notes/batch`.
+#[derive(Deserialize, utoipa::ToSchema)]
+pub struct BatchRequest {
+    /// How the operations are executed, defaults to `all_or_nothing`
+    #[serde(default)]
+    pub mode: BatchMode,
+    /// The operations to execute in order, 1 to 100
+    pub operations: Vec<BatchOperation>,
+}
+
+/// The outcome of an operation of a batch.
+#[derive(Serialize, Deserialize, Debug, utoipa::ToSchema)]
+pub struct BatchResult {
+    /// The status code the respective single-note endpoint would have responded with. Operations that were rolled back or not executed because another operation of an `all_or_nothing` batch failed have status 424
+    pub status: u16,
+    /// The created or updated note, missing for deletions and failed operations
+    pub note: Option<Note>,
+    /// The description of the error for failed operations if there is one
+    pub error: Option<String>,
+}
+
+impl BatchResult {
+    fn success(status: StatusCode, note: Option<Note>) -> Self {
+        Self {
+            status: status.as_u16(),
+            note,
+            error: None,
+        }
+    }
+
+    async fn failure(error: Error) -> Self {
+        let response = error.into_response();
+        let status = response.status().as_u16();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap_or_default();
+        Self {
+            status,
+            note: None,
+            error: (!body.is_empty()).then(|| String::from_utf8_lossy(&body).into_owned()),
+        }
+    }
+
+    fn failed_dependency() -> Self {
+        Self::success(StatusCode::FAILED_DEPENDENCY, None)
+    }
+}
+
+const MAX_BATCH_SIZE: usize = 100;
+
+/// Creates, updates and deletes multiple notes at once.
+///
+/// Responds with the result of every operation in the order of the operations. In `all_or_nothing` mode, the batch stops at the first failing operation and all operations are rolled back. In `best_effort` mode, the operations that succeed are kept regardless of the ones that fail. Every operation requires the permission of the respective single-note endpoint.
+#[utoipa::path(
+    post,
+    path = "/notes/batch",
+    request_body(content = BatchRequest, content_type = "application/json"),
+    responses(
+        (status = OK, body = Vec<BatchResult>),
+        (status = UNPROCESSABLE_ENTITY, description = "Invalid request body")
+    )
+)]
+#[axum::debug_handler]
+pub async fn batch(
+    State(app_state): State<SharedAppState>,
+    user: CurrentUser,
+    Json(batch): Json<BatchRequest>,
+) -> Result<Json<Vec<BatchResult>>, Error> {
+    if !(1..=MAX_BATCH_SIZE).contains(&batch.operations.len()) {
+        let mut errors = ValidationErrors::new();
+        errors.add(
+            "operations",
+            ValidationError::new("length")
+                .with_message(format!("must contain 1 to {} operations", MAX_BATCH_SIZE).into()),
+        );
+        return Err(Error::Validation(errors));
+    }
+
+    let mut results = Vec::with_capacity(batch.operations.len());
+    match batch.mode {
+        BatchMode::AllOrNothing => {
+            let count = batch.operations.len();
+            let mut tx = transaction(&app_state.db_pool).await?;
+            for operation in batch.operations {
+                match execute(operation, &user, &mut tx).await {
+                    Ok(result) => results.push(result),
+                    Err(e) => {
+                        // Dropping the transaction rolls back the operations executed so far.
+                        drop(tx);
+                        let failed = results.len();
+                        results = (0..count)
+                            .map(|_| BatchResult::failed_dependency())
+                            .collect();
+                        results[failed] = BatchResult::failure(e).await;
+                        return Ok(Json(results));
+                    }
+                }
+            }
+            tx.commit().await.map_err(forge_api_db::Error::DbError)?;
+        }
+        BatchMode::BestEffort => {
+            for operation in batch.operations {
+                let mut tx = transaction(&app_state.db_pool).await?;
+                let result = match execute(operation, &user, &mut tx).await {
+                    Ok(result) => {
+                        tx.commit().await.map_err(forge_api_db::Error::DbError)?;
+                        result
+                    }
+                    Err(e) => BatchResult::failure(e).await,
+                };
+                results.push(result);
+            }
+        }
+    }
+
+    Ok(Json(results))
+}
+
+/// Executes an operation of a batch on behalf of the user, checking the permission it requires first.
+async fn execute(
+    operation: BatchOperation,
+    user: &CurrentUser,
+    connection: &mut DbConnection,
+) -> Result<BatchResult, Error> {
+    let user_id = user.id;
+    let permission = match operation {
+        BatchOperation::Create { .. } => CreateNotes::NAME,
+        BatchOperation::Update { .. } => UpdateNotes::NAME,
+        BatchOperation::Delete { .. } => DeleteNotes::NAME,
+    };
+    if !user.is_in_scope(permission)
+        || !roles::has_permission(user_id, permission, &mut *connection).await?
+    {
+        return Err(Error::Forbidden);
+    }
+
+    match operation {
+        BatchOperation::Create { note } => {
+            let note = entities::notes::create(note, user_id, &mut *connection).await?;
+            Ok(BatchResult::success(StatusCode::CREATED, Some(note)))
+        }
+        BatchOperation::Update { id, version, note } => {
+            let expected_versions = version.map(|version| vec![version]);
+            entities::note_revisions::record(id, user_id, &mut *connection).await?;
+            let note = entities::notes::update(
+                id,
+                note,
+                user_id,
+                expected_versions.as_deref(),
+                &mut *connection,
+            )
+            .await?;
+            Ok(BatchResult::success(StatusCode::OK, Some(note)))
+        }
+        BatchOperation::Delete { id, version } => {
+            let expected_versions = version.map(|version| vec![version]);
+            entities::notes::delete(id, user_id, expected_versions.as_deref(), &mut *connection)
+                .await?;
+            Ok(BatchResult::success(StatusCode::NO_CONTENT, None))
+        }
+    }
+}
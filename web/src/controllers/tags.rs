@@ -0,0 +1,15 @@
+use crate::permissions::{ReadNotes, RequirePermission};
+use crate::{error::Error, state::SharedAppState};
+use axum::{extract::State, Json};
+use forge_api_db::entities::tags::{self, TagUsage};
+
+/// Lists the tags of the current user's notes along with how many notes have them, sorted by name.
+#[utoipa::path(get, path = "/tags", responses((status = OK, body = Vec<TagUsage>)))]
+#[axum::debug_handler]
+pub async fn read_all(
+    State(app_state): State<SharedAppState>,
+    RequirePermission { user, .. }: RequirePermission<ReadNotes>,
+) -> Result<Json<Vec<TagUsage>>, Error> {
+    let tags = tags::load_all(user.id, &app_state.db_pool).await?;
+    Ok(Json(tags))
+}
@@ -1,4 +1,7 @@
-use axum::{http::StatusCode, response::IntoResponse};
+use axum::{
+    http::{header, StatusCode},
+    response::IntoResponse,
+};
 use std::fmt::{Debug, Display};
 
 /// Error type that encapsultes anything that can go wrong
@@ -9,6 +12,30 @@ pub enum Error {
     /// Errors that can occur as a result of a data layer operation.
     #[error("Database error")]
     Database(#[from] forge_api_db::Error),
+    /// The request payload failed validation. Handled as an Unprocessable Entity error.
+    #[error("Validation error")]
+    Validation(#[from] validator::ValidationErrors),
+    /// The request could not be authenticated, e.g. because of invalid credentials.
+    #[error("Unauthorized")]
+    Unauthorized,
+    /// The authenticated user lacks a permission required for the request.
+    #[error("Forbidden")]
+    Forbidden,
+    /// The client sent too many requests and must wait for the passed number of seconds before retrying. Handled as a Too Many Requests error with a `Retry-After` header.
+    #[error("Too many requests")]
+    TooManyRequests {
+        /// The number of seconds after which the request may be retried
+        retry_after_secs: u64,
+    },
+    /// The request payload exceeds a size limit.
+    #[error("Payload too large")]
+    PayloadTooLarge,
+    /// The request payload is of a media type that is not accepted.
+    #[error("Unsupported media type")]
+    UnsupportedMediaType,
+    /// A `multipart/form-data` request body could not be read.
+    #[error("Multipart error")]
+    Multipart(#[from] axum::extract::multipart::MultipartError),
     /// Any other error. Handled as an Internal Server Error.
     #[error("Error: {0}")]
     Other(#[from] anyhow::Error),
@@ -23,7 +50,27 @@ impl IntoResponse for Error {
             Error::Database(forge_api_db::Error::ValidationError(e)) => {
                 validation_error(e).into_response()
             }
+            Error::Database(forge_api_db::Error::Conflict(field)) => {
+                conflict_error(field).into_response()
+            }
+            Error::Database(forge_api_db::Error::PreconditionFailed) => {
+                StatusCode::PRECONDITION_FAILED.into_response()
+            }
+            Error::Database(forge_api_db::Error::Forbidden) => {
+                StatusCode::FORBIDDEN.into_response()
+            }
             Error::Database(forge_api_db::Error::DbError(e)) => internal_error(e).into_response(),
+            Error::Validation(e) => validation_error(e).into_response(),
+            Error::Unauthorized => StatusCode::UNAUTHORIZED.into_response(),
+            Error::Forbidden => StatusCode::FORBIDDEN.into_response(),
+            Error::TooManyRequests { retry_after_secs } => (
+                StatusCode::TOO_MANY_REQUESTS,
+                [(header::RETRY_AFTER, retry_after_secs.to_string())],
+            )
+                .into_response(),
+            Error::PayloadTooLarge => StatusCode::PAYLOAD_TOO_LARGE.into_response(),
+            Error::UnsupportedMediaType => StatusCode::UNSUPPORTED_MEDIA_TYPE.into_response(),
+            Error::Multipart(e) => e.into_response(),
             Error::Other(e) => internal_error(e).into_response(),
         }
     }
@@ -50,3 +97,18 @@ fn validation_error(e: validator::ValidationErrors) -> (StatusCode, String) {
     tracing::info!(err.msg = %e, err.details = ?e, "Validation failed");
     (StatusCode::UNPROCESSABLE_ENTITY, e.to_string())
 }
+
+/// Helper function to create a conflict error response for the passed field while
+/// taking care to log the error itself.
+///
+/// The body has the same format as the body of [`validation_error`] responses so that
+/// clients can handle both the same way.
+fn conflict_error(field: &'static str) -> (StatusCode, String) {
+    tracing::info!(err.field = field, "Conflict");
+    let mut errors = validator::ValidationErrors::new();
+    errors.add(
+        field,
+        validator::ValidationError::new("unique").with_message("is already taken".into()),
+    );
+    (StatusCode::CONFLICT, errors.to_string())
+}
@@ -0,0 +1,53 @@
+use axum::{
+    extract::FromRequestParts,
+    http::{header, request::Parts, HeaderValue},
+};
+use std::convert::Infallible;
+
+/// Returns the `ETag` header value for the passed version of a record, e.g. `"3"`.
+pub fn etag(version: i32) -> HeaderValue {
+    HeaderValue::from_str(&format!("\"{}\"", version)).expect("ETags are valid header values")
+}
+
+/// Extractor for the `If-Match` header of a request.
+///
+/// Holds the versions listed in the header or [`Option::None`] if the header is missing or `*`, i.e. if the request should not be conditional on the version of the record. Weak and malformed entity tags never match (see [RFC 9110](https://www.rfc-editor.org/rfc/rfc9110#name-if-match)) and are thus ignored – a header only containing such tags results in an empty list of versions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IfMatch(pub Option<Vec<i32>>);
+
+impl IfMatch {
+    /// Returns the expected versions as expected by the writing functions in [`forge_api_db::entities`].
+    pub fn versions(&self) -> Option<&[i32]> {
+        self.0.as_deref()
+    }
+}
+
+impl<S: Send + Sync> FromRequestParts<S> for IfMatch {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let values: Vec<&str> = parts
+            .headers
+            .get_all(header::IF_MATCH)
+            .iter()
+            .map(|value| value.to_str().unwrap_or_default())
+            .collect();
+
+        if values.is_empty() || values.iter().any(|value| value.trim() == "*") {
+            return Ok(IfMatch(None));
+        }
+
+        let versions = values
+            .iter()
+            .flat_map(|value| value.split(','))
+            .filter_map(|tag| {
+                tag.trim()
+                    .strip_prefix('"')
+                    .and_then(|tag| tag.strip_suffix('"'))
+                    .and_then(|version| version.parse().ok())
+            })
+            .collect();
+
+        Ok(IfMatch(Some(versions)))
+    }
+}
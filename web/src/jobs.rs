@@ -0,0 +1,131 @@
+use crate::blobs::BlobStore;
+use chrono::{DateTime, Utc};
+use forge_api_db::entities::{
+    email_tokens, login_challenges, login_failures, notes, revoked_tokens,
+};
+use forge_api_db::DbPool;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::interval;
+use tracing::{error, info};
+
+/// How often expired entries are pruned from the token revocation list.
+const PRUNE_REVOKED_TOKENS_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// How often expired login challenges are pruned.
+const PRUNE_LOGIN_CHALLENGES_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// How often expired password reset and email verification tokens are pruned.
+const PRUNE_EMAIL_TOKENS_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// How often entries of accounts and IP addresses without recent failed logins are pruned.
+const PRUNE_LOGIN_FAILURES_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// How often notes that have been in the trash for longer than the retention period are purged.
+const PURGE_TRASHED_NOTES_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Spawns a task that periodically removes entries for expired tokens from the revocation list.
+///
+/// Expired tokens are rejected anyway, so their entries in the revocation list (see [`forge_api_db::entities::revoked_tokens`]) are of no use anymore.
+pub fn spawn_prune_revoked_tokens(db_pool: DbPool) {
+    tokio::spawn(async move {
+        let mut interval = interval(PRUNE_REVOKED_TOKENS_INTERVAL);
+        loop {
+            interval.tick().await;
+            match revoked_tokens::delete_expired(&db_pool).await {
+                Ok(count) => info!(count, "Pruned expired entries from token revocation list"),
+                Err(e) => {
+                    error!(err.msg = %e, err.details = ?e, "Pruning token revocation list failed")
+                }
+            }
+        }
+    });
+}
+
+/// Spawns a task that periodically removes login challenges that have expired without being passed.
+///
+/// Expired challenges are rejected anyway (see [`forge_api_db::entities::login_challenges`]).
+pub fn spawn_prune_login_challenges(db_pool: DbPool) {
+    tokio::spawn(async move {
+        let mut interval = interval(PRUNE_LOGIN_CHALLENGES_INTERVAL);
+        loop {
+            interval.tick().await;
+            match login_challenges::delete_expired(&db_pool).await {
+                Ok(count) => info!(count, "Pruned expired login challenges"),
+                Err(e) => {
+                    error!(err.msg = %e, err.details = ?e, "Pruning login challenges failed")
+                }
+            }
+        }
+    });
+}
+
+/// Spawns a task that periodically removes password reset and email verification tokens that have expired.
+///
+/// Expired tokens are rejected anyway (see [`forge_api_db::entities::email_tokens`]).
+pub fn spawn_prune_email_tokens(db_pool: DbPool) {
+    tokio::spawn(async move {
+        let mut interval = interval(PRUNE_EMAIL_TOKENS_INTERVAL);
+        loop {
+            interval.tick().await;
+            match email_tokens::delete_expired(&db_pool).await {
+                Ok(count) => info!(count, "Pruned expired email tokens"),
+                Err(e) => error!(err.msg = %e, err.details = ?e, "Pruning email tokens failed"),
+            }
+        }
+    });
+}
+
+/// Spawns a task that periodically removes the entries of accounts and IP addresses that haven't failed to log in for the passed number of seconds and aren't locked out.
+///
+/// Their counts of failures would be reset with the next failure anyway (see [`forge_api_config::LoginThrottlingConfig::reset_after_secs`]).
+pub fn spawn_prune_login_failures(db_pool: DbPool, reset_after_secs: u64) {
+    let reset_after = chrono::Duration::seconds(reset_after_secs.min(i32::MAX as u64) as i64);
+    tokio::spawn(async move {
+        let mut interval = interval(PRUNE_LOGIN_FAILURES_INTERVAL);
+        loop {
+            interval.tick().await;
+            match login_failures::delete_stale(Utc::now() - reset_after, &db_pool).await {
+                Ok(count) => info!(count, "Pruned stale failed login entries"),
+                Err(e) => error!(err.msg = %e, err.details = ?e, "Pruning failed logins failed"),
+            }
+        }
+    });
+}
+
+/// Spawns a task that periodically purges notes that have been in the trash for longer than the passed number of days (see [`purge_trashed_notes`]).
+pub fn spawn_purge_trashed_notes(
+    db_pool: DbPool,
+    blob_store: Arc<dyn BlobStore>,
+    retention_days: u32,
+) {
+    let retention = chrono::Duration::days(retention_days.into());
+    tokio::spawn(async move {
+        let mut interval = interval(PURGE_TRASHED_NOTES_INTERVAL);
+        loop {
+            interval.tick().await;
+            match purge_trashed_notes(Utc::now() - retention, &db_pool, blob_store.as_ref()).await {
+                Ok(count) => info!(count, "Purged notes from trash"),
+                Err(e) => error!(err.msg = %e, err.details = ?e, "Purging trashed notes failed"),
+            }
+        }
+    });
+}
+
+/// Permanently deletes all notes that were moved to the trash before the passed time along with their attachments' blobs, returning the number of deleted notes.
+///
+/// Blobs that can't be deleted are logged and left behind rather than failing the purge as their notes are gone already.
+pub async fn purge_trashed_notes(
+    before: DateTime<Utc>,
+    db_pool: &DbPool,
+    blob_store: &dyn BlobStore,
+) -> Result<u64, forge_api_db::Error> {
+    let purged = notes::purge_trashed(before, db_pool).await?;
+    for key in &purged.blob_keys {
+        if let Err(e) = blob_store.delete(key).await {
+            error!(err.msg = %e, err.details = ?e, blob_key = key, "Deleting blob failed");
+        }
+    }
+
+    Ok(purged.count)
+}
@@ -3,21 +3,46 @@
 use anyhow::Context;
 use axum::serve;
 use forge_api_config::{get_env, load_config, Config};
+use std::net::SocketAddr;
 use tokio::net::TcpListener;
 use tracing::info;
 use tracing_panic::panic_hook;
 use tracing_subscriber::{filter::EnvFilter, fmt, layer::SubscriberExt, util::SubscriberInitExt};
 
+/// Password reset and email verification via links sent by email.
+pub mod account_emails;
+/// Storage for the contents of files, e.g. attachments.
+pub mod blobs;
 /// The application's controllers that implement request handlers.
 pub mod controllers;
 /// Contains the application's error type and related conversion implementation.
 pub mod error;
+/// Helpers for the `ETag` and `If-Match` headers used for optimistic concurrency control.
+pub mod etag;
+/// Background jobs that run alongside the server.
+pub mod jobs;
+/// Counting failed logins and locking out accounts and client IP addresses that fail too often.
+pub mod login_throttling;
+/// Sending emails via SMTP or writing them to files or memory instead.
+pub mod mailer;
+/// The extractor for JSON Merge Patch request bodies used for partial updates.
+pub mod merge_patch;
 /// Middlewares that incoming requests are passed through before being passed to [`controllers`].
 pub mod middlewares;
+/// Hashing and verification of passwords.
+pub mod passwords;
+/// Permissions and the extractor that enforces them per handler.
+pub mod permissions;
+/// Rendering of notes to HTML.
+pub mod render;
 /// Contains the application's route definitions.
 pub mod routes;
 /// Contains the application state definition and functionality to initialize it.
 pub mod state;
+/// Helpers for the opaque tokens the application hands out, e.g. refresh tokens.
+pub mod tokens;
+/// Two-factor authentication with time-based one-time passwords and recovery codes.
+pub mod two_factor;
 
 /// Contains the login and sign up flow
 pub mod auth;
@@ -26,15 +51,32 @@ pub mod auth;
 /// This function does all the work to initiatilize and run the application:
 ///
 /// 1. Determine the environment the application is running in (see [`forge_api_config::get_env`])
-/// 2. Load the configuration (see [`forge_api_config::load_config`])
+/// 2. Load the configuration (see [`forge_api_config::load_config`]) and check it's fit for the environment (see [`forge_api_config::AuthConfig::validate`])
 /// 3. Initialize the application state (see [`state::init_app_state`])
-/// 4. Initialize the application's router (see [`routes::init_routes`])
-/// 5. Boot the application and start listening for requests on the configured interface and port
+/// 4. Spawn background jobs (see [`jobs`])
+/// 5. Initialize the application's router (see [`routes::init_routes`])
+/// 6. Boot the application and start listening for requests on the configured interface and port
 pub async fn run() -> anyhow::Result<()> {
     let env = get_env().context("Cannot get environment!")?;
     let config: Config = load_config(&env).context("Cannot load config!")?;
+    config
+        .auth
+        .validate(&env)
+        .context("Invalid auth configuration!")?;
 
     let app_state = state::init_app_state(config.clone()).await;
+    jobs::spawn_prune_revoked_tokens(app_state.db_pool.clone());
+    jobs::spawn_prune_login_challenges(app_state.db_pool.clone());
+    jobs::spawn_prune_email_tokens(app_state.db_pool.clone());
+    jobs::spawn_prune_login_failures(
+        app_state.db_pool.clone(),
+        config.login_throttling.reset_after_secs,
+    );
+    jobs::spawn_purge_trashed_notes(
+        app_state.db_pool.clone(),
+        app_state.blob_store.clone(),
+        config.notes.trash_retention_days,
+    );
     let app = routes::init_routes(app_state);
 
     let addr = config.server.addr();
@@ -43,9 +85,13 @@ pub async fn run() -> anyhow::Result<()> {
 
     // make Ctrl+C cause a graceful shutdown to be able to generate dhat-heap.json
 
-    serve(listener, app.into_make_service())
-        .with_graceful_shutdown(shutdown_signal())
-        .await?;
+    // Logins are throttled per client IP address, so handlers need the address of the peer.
+    serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .with_graceful_shutdown(shutdown_signal())
+    .await?;
 
     Ok(())
 }
@@ -0,0 +1,106 @@
+use crate::error::Error;
+use crate::state::AppState;
+use chrono::{Duration, Utc};
+use forge_api_config::LoginThrottlingConfig;
+use forge_api_db::entities::login_failures::{self, LoginFailureScope};
+use forge_api_db::transaction;
+use std::net::IpAddr;
+
+/// A login attempt for an account from a client IP address.
+///
+/// Failed attempts are counted for both the account and the address so that guessing many passwords for one account as well as guessing passwords for many accounts from one address lead to lockouts (see [`forge_api_config::LoginThrottlingConfig`]). Attempts for names that don't belong to any user are counted the same way so that lockouts don't reveal which names exist.
+pub struct LoginAttempt {
+    account: String,
+    ip: String,
+}
+
+impl LoginAttempt {
+    /// Creates an attempt to log in with the passed user name from the passed address.
+    pub fn new(name: &str, ip: IpAddr) -> Self {
+        Self {
+            // User names are case-insensitive.
+            account: name.to_lowercase(),
+            ip: ip.to_string(),
+        }
+    }
+
+    /// Fails with [`Error::TooManyRequests`] if logins for the account or from the address are currently locked out.
+    pub async fn ensure_not_locked(&self, app_state: &AppState) -> Result<(), Error> {
+        let locked_until =
+            login_failures::locked_until(&self.account, &self.ip, &app_state.db_pool).await?;
+        match locked_until {
+            Some(locked_until) => {
+                let remaining = locked_until - Utc::now();
+                // Round up so that clients retrying after the indicated time aren't locked out still.
+                let retry_after_secs = (remaining.num_milliseconds() + 999) / 1000;
+                Err(Error::TooManyRequests {
+                    retry_after_secs: retry_after_secs.max(1) as u64,
+                })
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Counts the attempt as failed for both the account and the address, locking out either once it has failed too often.
+    pub async fn record_failure(&self, app_state: &AppState) -> Result<(), Error> {
+        let config = &app_state.config.login_throttling;
+        let now = Utc::now();
+        let reset_before = now - secs(config.reset_after_secs);
+
+        let mut tx = transaction(&app_state.db_pool).await?;
+        for (scope, subject, max_failures) in [
+            (
+                LoginFailureScope::Account,
+                &self.account,
+                config.max_failures_per_account,
+            ),
+            (LoginFailureScope::Ip, &self.ip, config.max_failures_per_ip),
+        ] {
+            let failure_count =
+                login_failures::record(scope, subject, reset_before, &mut *tx).await?;
+            if let Some(lockout) = lockout(failure_count, max_failures, config) {
+                login_failures::lock(scope, subject, now + lockout, &mut *tx).await?;
+            }
+        }
+        tx.commit().await.map_err(forge_api_db::Error::DbError)?;
+
+        Ok(())
+    }
+
+    /// Forgets the failed attempts for the account after a successful login.
+    ///
+    /// Failures from the address are kept as a single valid account must not allow an attacker to keep guessing passwords for other accounts.
+    pub async fn record_success(&self, app_state: &AppState) -> Result<(), Error> {
+        login_failures::reset(
+            LoginFailureScope::Account,
+            &self.account,
+            &app_state.db_pool,
+        )
+        .await?;
+
+        Ok(())
+    }
+}
+
+/// Returns how long to lock out after the passed number of failures: not at all below `max_failures`, then for [`LoginThrottlingConfig::base_lockout_secs`], doubling with every further failure up to [`LoginThrottlingConfig::max_lockout_secs`].
+fn lockout(
+    failure_count: i32,
+    max_failures: u32,
+    config: &LoginThrottlingConfig,
+) -> Option<Duration> {
+    let excess = u32::try_from(failure_count)
+        .ok()?
+        .checked_sub(max_failures)?;
+    let factor = 2u64.saturating_pow(excess);
+    let lockout_secs = config
+        .base_lockout_secs
+        .saturating_mul(factor)
+        .min(config.max_lockout_secs);
+
+    Some(secs(lockout_secs))
+}
+
+/// Converts a number of seconds from the configuration, capping it at roughly 68 years so that it can be added to the current time.
+fn secs(secs: u64) -> Duration {
+    Duration::seconds(secs.min(i32::MAX as u64) as i64)
+}
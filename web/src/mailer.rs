@@ -0,0 +1,186 @@
+use anyhow::Context;
+use async_trait::async_trait;
+use chrono::Utc;
+use forge_api_config::{MailTransport, MailerConfig, SmtpSecurity};
+use lettre::message::{header::ContentType, Mailbox};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::fs;
+use uuid::Uuid;
+
+/// A plain text email.
+#[derive(Debug, Clone)]
+pub struct Email {
+    /// The address of the recipient.
+    pub to: String,
+    /// The subject line.
+    pub subject: String,
+    /// The plain text body.
+    pub body: String,
+}
+
+/// Sends emails, e.g. for resetting passwords or verifying email addresses.
+///
+/// The implementation is chosen via [`forge_api_config::MailerConfig::transport`] (see [`from_config`]).
+#[async_trait]
+pub trait Mailer: Send + Sync {
+    /// Sends the passed email.
+    async fn send(&self, email: Email) -> Result<(), anyhow::Error>;
+}
+
+/// Creates the [`Mailer`] configured in the passed [`forge_api_config::MailerConfig`].
+pub fn from_config(config: &MailerConfig) -> Result<Arc<dyn Mailer>, anyhow::Error> {
+    let mailer: Arc<dyn Mailer> = match config.transport {
+        MailTransport::Smtp => Arc::new(SmtpMailer::from_config(config)?),
+        MailTransport::File => Arc::new(FileMailer::new(parse_from(config)?, &config.file_dir)),
+    };
+
+    Ok(mailer)
+}
+
+fn parse_from(config: &MailerConfig) -> Result<Mailbox, anyhow::Error> {
+    config
+        .from
+        .parse()
+        .with_context(|| format!("Invalid sender address: {:?}", config.from))
+}
+
+fn build_message(from: &Mailbox, email: Email) -> Result<Message, anyhow::Error> {
+    let to: Mailbox = email
+        .to
+        .parse()
+        .with_context(|| format!("Invalid recipient address: {:?}", email.to))?;
+    let message = Message::builder()
+        .from(from.clone())
+        .to(to)
+        .subject(email.subject)
+        .header(ContentType::TEXT_PLAIN)
+        .body(email.body)?;
+
+    Ok(message)
+}
+
+/// A [`Mailer`] that sends emails via an SMTP server.
+pub struct SmtpMailer {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+}
+
+impl SmtpMailer {
+    /// Creates a mailer for the SMTP server configured in the passed [`forge_api_config::MailerConfig`].
+    ///
+    /// Connections are only established once emails are sent.
+    pub fn from_config(config: &MailerConfig) -> Result<Self, anyhow::Error> {
+        let builder = match config.smtp_security {
+            SmtpSecurity::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(&config.smtp_host)?,
+            SmtpSecurity::StartTls => {
+                AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&config.smtp_host)?
+            }
+            SmtpSecurity::None => {
+                AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&config.smtp_host)
+            }
+        };
+        let mut builder = builder.port(config.smtp_port);
+        if let (Some(username), Some(password)) = (&config.smtp_username, &config.smtp_password) {
+            builder = builder.credentials(Credentials::new(username.clone(), password.clone()));
+        }
+
+        Ok(Self {
+            transport: builder.build(),
+            from: parse_from(config)?,
+        })
+    }
+}
+
+#[async_trait]
+impl Mailer for SmtpMailer {
+    async fn send(&self, email: Email) -> Result<(), anyhow::Error> {
+        let message = build_message(&self.from, email)?;
+        self.transport
+            .send(message)
+            .await
+            .context("SMTP server rejected email")?;
+
+        Ok(())
+    }
+}
+
+/// A [`Mailer`] that writes every email to an `.eml` file in a directory instead of sending it, e.g. during development (see [`forge_api_config::MailerConfig::file_dir`]).
+pub struct FileMailer {
+    from: Mailbox,
+    dir: PathBuf,
+}
+
+impl FileMailer {
+    /// Creates a mailer that writes emails to the passed directory, which is created when the first email is written.
+    pub fn new(from: Mailbox, dir: impl Into<PathBuf>) -> Self {
+        Self {
+            from,
+            dir: dir.into(),
+        }
+    }
+}
+
+#[async_trait]
+impl Mailer for FileMailer {
+    async fn send(&self, email: Email) -> Result<(), anyhow::Error> {
+        let message = build_message(&self.from, email)?;
+        fs::create_dir_all(&self.dir).await?;
+        // Prefix file names with the time so that listing the directory shows emails in the order they were sent.
+        let file_name = format!(
+            "{}-{}.eml",
+            Utc::now().format("%Y%m%dT%H%M%S%.3f"),
+            Uuid::new_v4()
+        );
+        fs::write(self.dir.join(file_name), message.formatted()).await?;
+
+        Ok(())
+    }
+}
+
+/// A [`Mailer`] that keeps all emails in memory so that tests can inspect them.
+#[derive(Default)]
+pub struct InMemoryMailer {
+    sent: Mutex<Vec<Email>>,
+}
+
+impl InMemoryMailer {
+    /// Creates a mailer that hasn't sent any emails yet.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns all emails sent so far, oldest first.
+    pub fn sent(&self) -> Vec<Email> {
+        self.sent.lock().unwrap().clone()
+    }
+
+    /// Returns the latest email with the passed subject sent to the passed address, waiting up to a few seconds for it as emails are usually sent in the background.
+    pub async fn wait_for(&self, to: &str, subject: &str) -> Option<Email> {
+        for _ in 0..100 {
+            let email = self
+                .sent()
+                .into_iter()
+                .rev()
+                .find(|email| email.to == to && email.subject == subject);
+            if email.is_some() {
+                return email;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+
+        None
+    }
+}
+
+#[async_trait]
+impl Mailer for InMemoryMailer {
+    async fn send(&self, email: Email) -> Result<(), anyhow::Error> {
+        self.sent.lock().unwrap().push(email);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,45 @@
+use axum::{
+    extract::{FromRequest, Request},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::de::DeserializeOwned;
+
+/// The media type of JSON Merge Patch documents, see [RFC 7396](https://www.rfc-editor.org/rfc/rfc7396).
+pub const MERGE_PATCH_CONTENT_TYPE: &str = "application/merge-patch+json";
+
+/// Extractor for a JSON Merge Patch request body.
+///
+/// Works like [`axum::Json`] but only accepts bodies sent as `application/merge-patch+json` and rejects all others with 415. The patch is deserialized into `T` which is expected to have optional fields so that missing members can be told apart from present ones (see [`forge_api_db::entities::notes::NotePatch`]).
+#[derive(Debug, Clone)]
+pub struct MergePatch<T>(pub T);
+
+impl<T, S> FromRequest<S> for MergePatch<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = Response;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let is_merge_patch = req
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.split(';').next())
+            .is_some_and(|media_type| {
+                media_type
+                    .trim()
+                    .eq_ignore_ascii_case(MERGE_PATCH_CONTENT_TYPE)
+            });
+        if !is_merge_patch {
+            return Err(StatusCode::UNSUPPORTED_MEDIA_TYPE.into_response());
+        }
+
+        let Json(patch) = Json::<T>::from_request(req, state)
+            .await
+            .map_err(IntoResponse::into_response)?;
+        Ok(MergePatch(patch))
+    }
+}
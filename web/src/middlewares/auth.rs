@@ -1,25 +1,110 @@
-use crate::state::SharedAppState;
+use crate::error::Error;
+use crate::state::{AppState, SharedAppState};
+use crate::tokens;
 use axum::body::Body;
 use axum::{
-    extract::State,
-    http::{self, Request, StatusCode},
+    extract::{FromRequestParts, State},
+    http::{self, request::Parts, HeaderMap, Request, StatusCode},
     middleware::Next,
     response::Response,
 };
-use forge_api_db::entities::users;
+use forge_api_db::entities::users::{self, User};
+use forge_api_db::entities::{api_keys, revoked_tokens};
+use jwt_lib::{Claims, ErrorKind};
 use tracing::Span;
+use uuid::Uuid;
 
-/// Authenticates an incoming request based on an auth token.
+/// The authenticated user of a request.
 ///
-/// This looks for a token in the `Authorization` header. If no token is present or no user exists with that token (see [`forge_api_db::entities::users::load_with_token`]), a 401 response code is returned and the request is not processed further.
-#[tracing::instrument(skip_all, fields(rejection_reason = tracing::field::Empty))]
+/// The [`auth`] middleware inserts this into the request extensions so that handlers can access it via `Extension<CurrentUser>`. It can also be used as an extractor directly, in which case the request is authenticated on demand, regardless of whether the route is covered by the [`auth`] middleware or not.
+#[derive(Clone, Debug)]
+pub struct CurrentUser {
+    /// The id of the user.
+    pub id: Uuid,
+    /// The name of the user.
+    pub name: String,
+    /// The scopes of the API key the request was authenticated with or `None` if it was authenticated with an access token.
+    pub api_key_scopes: Option<Vec<String>>,
+}
+
+impl CurrentUser {
+    /// Returns whether the credentials the request was authenticated with may be used for the passed permission.
+    ///
+    /// Access tokens may be used for every permission the user's roles grant, API keys only for those in their scopes. This doesn't check the user's roles (see [`crate::permissions::RequirePermission`]).
+    pub fn is_in_scope(&self, permission: &str) -> bool {
+        match &self.api_key_scopes {
+            Some(scopes) => scopes.iter().any(|scope| scope == permission),
+            None => true,
+        }
+    }
+
+    /// Fails with [`Error::Forbidden`] if the request was authenticated with an API key rather than an access token.
+    ///
+    /// Managing the account, e.g. its API keys or second factor, requires logging in so that a leaked key can't be used to take the account over.
+    pub fn require_session(&self) -> Result<(), Error> {
+        if self.api_key_scopes.is_some() {
+            tracing::info!(user_id = %self.id, "API key used for account management");
+            return Err(Error::Forbidden);
+        }
+
+        Ok(())
+    }
+}
+
+impl From<User> for CurrentUser {
+    fn from(user: User) -> Self {
+        Self {
+            id: user.id,
+            name: user.name,
+            api_key_scopes: None,
+        }
+    }
+}
+
+impl FromRequestParts<SharedAppState> for CurrentUser {
+    type Rejection = StatusCode;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        app_state: &SharedAppState,
+    ) -> Result<Self, Self::Rejection> {
+        if let Some(current_user) = parts.extensions.get::<CurrentUser>() {
+            return Ok(current_user.clone());
+        }
+
+        let (current_user, claims) = authenticate(app_state, &parts.headers).await?;
+        parts.extensions.insert(current_user.clone());
+        if let Some(claims) = claims {
+            parts.extensions.insert(claims);
+        }
+
+        Ok(current_user)
+    }
+}
+
+/// Authenticates an incoming request based on a JWT or an API key.
+///
+/// This looks for a `Bearer` token in the `Authorization` header. Tokens starting with [`crate::tokens::API_KEY_PREFIX`] are treated as API keys, which must belong to a key that has neither been revoked nor expired (see [`forge_api_db::entities::api_keys`]). Other tokens are treated as JWTs whose signature and expiry are verified (see [`jwt_lib::Jwt::decode_jwt`]). If no valid token is present, the token has been revoked (see [`forge_api_db::entities::revoked_tokens`]) or the user it was issued to does not exist anymore (see [`forge_api_db::entities::users::load`]), a 401 response code is returned and the request is not processed further. Otherwise, a [`CurrentUser`] and, for JWTs, the token's [`jwt_lib::Claims`] are inserted into the request extensions.
 pub async fn auth(
     State(app_state): State<SharedAppState>,
     mut req: Request<Body>,
     next: Next,
 ) -> Result<Response, StatusCode> {
-    let auth_header = req
-        .headers()
+    let (current_user, claims) = authenticate(&app_state, req.headers()).await?;
+
+    req.extensions_mut().insert(current_user);
+    if let Some(claims) = claims {
+        req.extensions_mut().insert(claims);
+    }
+    Ok(next.run(req).await)
+}
+
+#[tracing::instrument(skip_all, fields(rejection_reason = tracing::field::Empty))]
+async fn authenticate(
+    app_state: &AppState,
+    headers: &HeaderMap,
+) -> Result<(CurrentUser, Option<Claims>), StatusCode> {
+    let auth_header = headers
         .get(http::header::AUTHORIZATION)
         .and_then(|header| header.to_str().ok());
 
@@ -30,20 +115,105 @@ pub async fn auth(
         return Err(StatusCode::UNAUTHORIZED);
     };
 
-    match users::load_with_token(auth_header, &app_state.db_pool).await {
-        Ok(Some(current_user)) => {
-            req.extensions_mut().insert(current_user);
-            Ok(next.run(req).await)
+    let token = if let Some(token) = auth_header.strip_prefix("Bearer ") {
+        token
+    } else {
+        log_rejection_reason("Malformed authorization header");
+        return Err(StatusCode::UNAUTHORIZED);
+    };
+
+    if token.starts_with(tokens::API_KEY_PREFIX) {
+        let current_user = authenticate_api_key(app_state, token).await?;
+        return Ok((current_user, None));
+    }
+
+    let claims = match app_state.jwt.decode_jwt(token) {
+        Ok(claims) => claims,
+        Err(e) => {
+            match e.kind() {
+                ErrorKind::ExpiredSignature => log_rejection_reason("Expired token"),
+                ErrorKind::InvalidSignature => log_rejection_reason("Invalid token signature"),
+                _ => log_rejection_reason("Malformed token"),
+            }
+            return Err(StatusCode::UNAUTHORIZED);
+        }
+    };
+
+    match revoked_tokens::is_revoked(claims.jti, &app_state.db_pool).await {
+        Ok(false) => {}
+        Ok(true) => {
+            log_rejection_reason("Revoked token");
+            return Err(StatusCode::UNAUTHORIZED);
+        }
+        Err(_) => {
+            log_rejection_reason("Database error");
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    }
+
+    match users::load(claims.sub, &app_state.db_pool).await {
+        Ok(Some(user)) => {
+            if claims.generation != user.token_generation {
+                log_rejection_reason("Revoked token");
+                return Err(StatusCode::UNAUTHORIZED);
+            }
+
+            Ok((CurrentUser::from(user), Some(claims)))
+        }
+        Ok(None) => {
+            log_rejection_reason("Unknown user");
+            Err(StatusCode::UNAUTHORIZED)
+        }
+        Err(_) => {
+            log_rejection_reason("Database error");
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+async fn authenticate_api_key(app_state: &AppState, key: &str) -> Result<CurrentUser, StatusCode> {
+    let Some(prefix) = tokens::api_key_prefix(key) else {
+        log_rejection_reason("Malformed API key");
+        return Err(StatusCode::UNAUTHORIZED);
+    };
+
+    let credentials = match api_keys::load_by_prefix(prefix, &app_state.db_pool).await {
+        Ok(Some(credentials)) => credentials,
+        Ok(None) => {
+            log_rejection_reason("Unknown, revoked or expired API key");
+            return Err(StatusCode::UNAUTHORIZED);
+        }
+        Err(_) => {
+            log_rejection_reason("Database error");
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
         }
+    };
+    if !tokens::constant_time_eq(&tokens::hash(key), &credentials.key_hash) {
+        log_rejection_reason("Invalid API key");
+        return Err(StatusCode::UNAUTHORIZED);
+    }
+
+    let user = match users::load(credentials.user_id, &app_state.db_pool).await {
+        Ok(Some(user)) => user,
         Ok(None) => {
-            log_rejection_reason("Unknown user token");
+            log_rejection_reason("Unknown user");
             return Err(StatusCode::UNAUTHORIZED);
         }
         Err(_) => {
             log_rejection_reason("Database error");
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
         }
+    };
+    if let Err(e) = api_keys::touch(credentials.id, &app_state.db_pool).await {
+        // The request is authenticated regardless, only the last-used time is off.
+        tracing::warn!(err.msg = %e, err.details = ?e, api_key_id = %credentials.id, "Recording API key use failed");
     }
+
+    Ok(CurrentUser {
+        id: user.id,
+        name: user.name,
+        api_key_scopes: Some(credentials.scopes),
+    })
 }
 
 fn log_rejection_reason(msg: &str) {
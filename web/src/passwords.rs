@@ -0,0 +1,140 @@
+use anyhow::{anyhow, Context};
+use argon2::password_hash::rand_core::OsRng;
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::{Algorithm, Argon2, Params, Version};
+use forge_api_config::PasswordHashingConfig;
+use std::sync::Arc;
+use tokio::task::spawn_blocking;
+use tracing::warn;
+
+/// The outcome of verifying a password against a stored hash.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verification {
+    /// The password does not match the hash.
+    Invalid,
+    /// The password matches the hash.
+    Valid,
+    /// The password matches the hash, but the hash was created with another algorithm or other parameters than are configured now and should be replaced.
+    ValidNeedsRehash,
+}
+
+/// Hashes and verifies passwords.
+///
+/// New hashes are created with Argon2id using the parameters from [`forge_api_config::PasswordHashingConfig`] and stored as PHC strings, which carry the algorithm and parameters along with the salt. Legacy bcrypt hashes keep verifying but are reported as needing a rehash. Hashing is deliberately slow, so all work is moved off the async executor onto the blocking thread pool.
+#[derive(Clone)]
+pub struct PasswordHashing {
+    argon2: Arc<Argon2<'static>>,
+    /// A hash of a random password that is verified against when there's no user so that unknown names take as long as wrong passwords.
+    dummy_hash: Arc<str>,
+}
+
+impl PasswordHashing {
+    /// Sets up password hashing with the passed parameters, failing if they are out of the range Argon2 supports.
+    pub fn from_config(config: &PasswordHashingConfig) -> Result<Self, anyhow::Error> {
+        let params = Params::new(
+            config.memory_kib,
+            config.iterations,
+            config.parallelism,
+            None,
+        )
+        .map_err(|e| anyhow!("Invalid password hashing parameters: {}", e))?;
+        let argon2 = Argon2::new(Algorithm::Argon2id, Version::V0x13, params);
+        let dummy_hash = hash_with(&argon2, SaltString::generate(&mut OsRng).as_str())?;
+
+        Ok(Self {
+            argon2: Arc::new(argon2),
+            dummy_hash: dummy_hash.into(),
+        })
+    }
+
+    /// Hashes a password, returning the hash as a PHC string.
+    pub async fn hash(&self, password: String) -> Result<String, anyhow::Error> {
+        let argon2 = self.argon2.clone();
+        spawn_blocking(move || hash_with(&argon2, &password))
+            .await
+            .context("Password hashing task failed")?
+    }
+
+    /// Verifies a password against a stored hash.
+    ///
+    /// If no hash is passed, the password is verified against a dummy hash and [`Verification::Invalid`] is returned so that callers can treat unknown users like wrong passwords without revealing the difference through timing. Stored hashes that can't be parsed or use an unknown algorithm are logged and reported as [`Verification::Invalid`] as well, so errors are only returned for internal failures.
+    pub async fn verify(
+        &self,
+        password: String,
+        hash: Option<String>,
+    ) -> Result<Verification, anyhow::Error> {
+        let argon2 = self.argon2.clone();
+        let dummy_hash = self.dummy_hash.clone();
+        spawn_blocking(move || match hash {
+            Some(hash) => verify_with(&argon2, &password, &hash),
+            None => {
+                verify_with(&argon2, &password, &dummy_hash)?;
+                Ok(Verification::Invalid)
+            }
+        })
+        .await
+        .context("Password verification task failed")?
+    }
+}
+
+fn hash_with(argon2: &Argon2, password: &str) -> Result<String, anyhow::Error> {
+    let salt = SaltString::generate(&mut OsRng);
+    let hash = argon2
+        .hash_password(password.as_bytes(), &salt)
+        .map_err(|e| anyhow!("Hashing password failed: {}", e))?;
+
+    Ok(hash.to_string())
+}
+
+fn verify_with(argon2: &Argon2, password: &str, hash: &str) -> Result<Verification, anyhow::Error> {
+    if is_bcrypt(hash) {
+        // bcrypt only fails for hashes it can't parse, which must not be told apart from wrong passwords.
+        return Ok(match bcrypt::verify(password, hash) {
+            Ok(true) => Verification::ValidNeedsRehash,
+            Ok(false) => Verification::Invalid,
+            Err(e) => {
+                warn!(err.msg = %e, "Stored bcrypt hash can't be parsed");
+                Verification::Invalid
+            }
+        });
+    }
+
+    let parsed = match PasswordHash::new(hash) {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            warn!(err.msg = %e, "Stored password hash can't be parsed");
+            return Ok(Verification::Invalid);
+        }
+    };
+    match argon2.verify_password(password.as_bytes(), &parsed) {
+        Ok(()) if is_current(argon2, &parsed) => Ok(Verification::Valid),
+        Ok(()) => Ok(Verification::ValidNeedsRehash),
+        Err(argon2::password_hash::Error::Password) => Ok(Verification::Invalid),
+        Err(e @ argon2::password_hash::Error::Crypto) => {
+            Err(anyhow!("Verifying password failed: {}", e))
+        }
+        // All other errors stem from the hash, e.g. an unknown algorithm or invalid parameters.
+        Err(e) => {
+            warn!(err.msg = %e, "Stored password hash can't be used for verifying");
+            Ok(Verification::Invalid)
+        }
+    }
+}
+
+fn is_bcrypt(hash: &str) -> bool {
+    ["$2a$", "$2b$", "$2x$", "$2y$"]
+        .iter()
+        .any(|prefix| hash.starts_with(prefix))
+}
+
+/// Whether a hash was created with the algorithm, version and parameters that are configured now.
+fn is_current(argon2: &Argon2, hash: &PasswordHash) -> bool {
+    let params = argon2.params();
+    hash.algorithm == Algorithm::Argon2id.ident()
+        && hash.version == Some(Version::V0x13.into())
+        && Params::try_from(hash).is_ok_and(|hash_params| {
+            hash_params.m_cost() == params.m_cost()
+                && hash_params.t_cost() == params.t_cost()
+                && hash_params.p_cost() == params.p_cost()
+        })
+}
@@ -0,0 +1,92 @@
+use crate::error::Error;
+use crate::middlewares::auth::CurrentUser;
+use crate::state::SharedAppState;
+use axum::{
+    extract::FromRequestParts,
+    http::request::Parts,
+    response::{IntoResponse, Response},
+};
+use forge_api_db::entities::roles;
+use std::marker::PhantomData;
+
+/// A permission that can be granted to roles.
+///
+/// Implementors are marker types that are only used as the type parameter of [`RequirePermission`].
+pub trait Permission {
+    /// The name of the permission as stored in the `permissions` table.
+    const NAME: &'static str;
+}
+
+/// Allows reading one's notes.
+pub struct ReadNotes;
+
+impl Permission for ReadNotes {
+    const NAME: &'static str = "notes:read";
+}
+
+/// Allows creating notes.
+pub struct CreateNotes;
+
+impl Permission for CreateNotes {
+    const NAME: &'static str = "notes:create";
+}
+
+/// Allows updating one's notes.
+pub struct UpdateNotes;
+
+impl Permission for UpdateNotes {
+    const NAME: &'static str = "notes:update";
+}
+
+/// Allows deleting one's notes.
+pub struct DeleteNotes;
+
+impl Permission for DeleteNotes {
+    const NAME: &'static str = "notes:delete";
+}
+
+/// Extractor that only lets requests through if they are authenticated (see [`CurrentUser`]) and one of the user's roles grants the permission `P`.
+///
+/// Requests authenticated with an API key additionally need `P` to be among the key's scopes (see [`CurrentUser::is_in_scope`]). Unauthenticated requests are rejected with 401, requests of users lacking the permission with 403. Since the check happens when the handler's arguments are extracted, it does not depend on the route being covered by any middleware.
+///
+/// Example:
+/// ```
+/// pub async fn read_all(
+///     State(app_state): State<SharedAppState>,
+///     RequirePermission { user, .. }: RequirePermission<ReadNotes>,
+/// ) -> Result<Json<Vec<Note>>, Error> {
+///     …
+/// }
+/// ```
+pub struct RequirePermission<P: Permission> {
+    /// The authenticated user holding the permission.
+    pub user: CurrentUser,
+    permission: PhantomData<P>,
+}
+
+impl<P: Permission> FromRequestParts<SharedAppState> for RequirePermission<P> {
+    type Rejection = Response;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        app_state: &SharedAppState,
+    ) -> Result<Self, Self::Rejection> {
+        let user = CurrentUser::from_request_parts(parts, app_state)
+            .await
+            .map_err(IntoResponse::into_response)?;
+
+        let granted = user.is_in_scope(P::NAME)
+            && roles::has_permission(user.id, P::NAME, &app_state.db_pool)
+                .await
+                .map_err(|e| Error::from(e).into_response())?;
+        if !granted {
+            tracing::info!(user_id = %user.id, permission = P::NAME, "Permission denied");
+            return Err(Error::Forbidden.into_response());
+        }
+
+        Ok(Self {
+            user,
+            permission: PhantomData,
+        })
+    }
+}
@@ -0,0 +1,49 @@
+use ammonia::Builder;
+use forge_api_db::entities::notes::NoteFormat;
+use pulldown_cmark::{html, Options, Parser};
+use std::sync::LazyLock;
+
+/// The sanitizer for rendered Markdown: [`ammonia`]'s defaults plus the checkboxes of task lists.
+///
+/// Every `input` is forced to be a disabled checkbox so that notes can't inject other form controls.
+static SANITIZER: LazyLock<Builder<'static>> = LazyLock::new(|| {
+    let mut builder = Builder::default();
+    builder
+        .add_tags(["input"])
+        .add_tag_attribute_values("input", "checked", [""])
+        .set_tag_attribute_value("input", "type", "checkbox")
+        .set_tag_attribute_value("input", "disabled", "");
+    builder
+});
+
+/// Renders the text of a note to an HTML fragment according to its format.
+///
+/// Markdown is rendered as CommonMark with tables, strikethrough and task lists and then sanitized (see [`SANITIZER`]) so that notes can't inject scripts, styles or event handlers into the pages they are displayed on. Plain text is escaped, with blank lines separating paragraphs and other line breaks being kept.
+pub fn to_html(text: &str, format: NoteFormat) -> String {
+    match format {
+        NoteFormat::Markdown => {
+            let options =
+                Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TASKLISTS;
+            let mut rendered = String::new();
+            html::push_html(&mut rendered, Parser::new_ext(text, options));
+            SANITIZER.clean(&rendered).to_string()
+        }
+        NoteFormat::Plain => text
+            .replace("\r\n", "\n")
+            .split("\n\n")
+            .map(str::trim)
+            .filter(|paragraph| !paragraph.is_empty())
+            .map(|paragraph| {
+                let lines: Vec<String> = paragraph.lines().map(escape).collect();
+                format!("<p>{}</p>\n", lines.join("<br>\n"))
+            })
+            .collect(),
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -1,15 +1,17 @@
-use crate::auth::{login_handler, registeration_handler};
-use crate::controllers::notes;
-use crate::middlewares::auth::auth;
+use crate::account_emails;
+use crate::auth;
+use crate::controllers::{
+    api_keys, attachments, note_links, note_revisions, note_shares, notes, tags,
+};
 use crate::state::AppState;
+use crate::two_factor;
 use axum::{
+    extract::DefaultBodyLimit,
     http::StatusCode,
-    middleware,
     response::IntoResponse,
-    routing::{delete, get, post, put},
+    routing::{delete, get, patch, post, put},
     Json, Router,
 };
-// use forge_api_db::entities::notes::Note;
 use std::collections::HashMap;
 use std::sync::Arc;
 use tracing::info;
@@ -24,27 +26,108 @@ pub fn init_routes(app_state: AppState) -> Router {
 
     // OpenApi docs
     let (_, openapi) = OpenApiRouter::<Arc<AppState>>::new()
+        .routes(routes!(notes::read_all, notes::create,))
+        .routes(routes!(notes::search))
+        .routes(routes!(notes::batch))
+        .routes(routes!(notes::read_trash))
+        .routes(routes!(notes::restore))
+        .routes(routes!(note_revisions::read_all))
+        .routes(routes!(note_revisions::diff))
+        .routes(routes!(note_revisions::revert))
+        .routes(routes!(note_shares::read_all, note_shares::create))
+        .routes(routes!(note_shares::delete))
+        .routes(routes!(note_links::read_all, note_links::create))
+        .routes(routes!(note_links::delete))
+        .routes(routes!(note_links::view))
+        .routes(routes!(attachments::read_all, attachments::create))
+        .routes(routes!(attachments::read_one, attachments::delete))
         .routes(routes!(
-            notes::read_all,
-            notes::create,
+            notes::read_one,
             notes::delete,
-            notes::update
+            notes::update,
+            notes::patch
         ))
+        .routes(routes!(tags::read_all))
+        .routes(routes!(auth::login_handler))
+        .routes(routes!(auth::login_two_factor_handler))
+        .routes(routes!(two_factor::status))
+        .routes(routes!(two_factor::enroll))
+        .routes(routes!(two_factor::confirm))
+        .routes(routes!(two_factor::disable))
+        .routes(routes!(two_factor::regenerate_recovery_codes))
+        .routes(routes!(api_keys::read_all, api_keys::create))
+        .routes(routes!(api_keys::delete))
+        .routes(routes!(auth::registeration_handler))
+        .routes(routes!(auth::refresh_handler))
+        .routes(routes!(account_emails::forgot_password))
+        .routes(routes!(account_emails::reset_password))
+        .routes(routes!(account_emails::verify_email))
         .split_for_parts();
 
     // run Hyper sever
+    // Handlers authenticate requests themselves by extracting `CurrentUser` or `RequirePermission`.
     Router::new()
-        .route("/notes/{id}", delete(notes::delete))
-        .route("/notes/{id}", put(notes::update))
-        .route_layer(middleware::from_fn_with_state(
-            shared_app_state.clone(),
-            auth,
-        ))
-        .route("/login", post(login_handler))
-        .route("/register", post(registeration_handler))
+        .route("/logout", post(auth::logout_handler))
+        .route("/logout/all", post(auth::logout_all_handler))
         .route("/notes", get(notes::read_all))
         .route("/notes", post(notes::create))
+        .route("/notes/search", get(notes::search))
+        .route("/notes/batch", post(notes::batch))
+        .route("/notes/trash", get(notes::read_trash))
+        .route("/notes/{id}/restore", post(notes::restore))
+        .route("/notes/{id}/revisions", get(note_revisions::read_all))
+        .route(
+            "/notes/{id}/revisions/{rev}/diff",
+            get(note_revisions::diff),
+        )
+        .route(
+            "/notes/{id}/revisions/{rev}/revert",
+            post(note_revisions::revert),
+        )
+        .route("/notes/{id}/shares", get(note_shares::read_all))
+        .route("/notes/{id}/shares", post(note_shares::create))
+        .route("/notes/{id}/shares/{user_id}", delete(note_shares::delete))
+        .route("/notes/{id}/links", get(note_links::read_all))
+        .route("/notes/{id}/links", post(note_links::create))
+        .route("/notes/{id}/links/{link_id}", delete(note_links::delete))
+        .route("/s/{token}", get(note_links::view))
+        .route("/notes/{id}/attachments", get(attachments::read_all))
+        // Uploads are limited by `AttachmentsConfig::max_size_bytes` while they are streamed instead.
+        .route(
+            "/notes/{id}/attachments",
+            post(attachments::create).layer(DefaultBodyLimit::disable()),
+        )
+        .route(
+            "/notes/{id}/attachments/{attachment_id}",
+            get(attachments::read_one),
+        )
+        .route(
+            "/notes/{id}/attachments/{attachment_id}",
+            delete(attachments::delete),
+        )
         .route("/notes/{id}", get(notes::read_one))
+        .route("/notes/{id}", delete(notes::delete))
+        .route("/notes/{id}", put(notes::update))
+        .route("/notes/{id}", patch(notes::patch))
+        .route("/tags", get(tags::read_all))
+        .route("/login", post(auth::login_handler))
+        .route("/login/2fa", post(auth::login_two_factor_handler))
+        .route("/account/2fa", get(two_factor::status))
+        .route("/account/2fa/totp", post(two_factor::enroll))
+        .route("/account/2fa/totp/confirm", post(two_factor::confirm))
+        .route("/account/2fa/disable", post(two_factor::disable))
+        .route(
+            "/account/2fa/recovery-codes",
+            post(two_factor::regenerate_recovery_codes),
+        )
+        .route("/account/api-keys", get(api_keys::read_all))
+        .route("/account/api-keys", post(api_keys::create))
+        .route("/account/api-keys/{id}", delete(api_keys::delete))
+        .route("/register", post(auth::registeration_handler))
+        .route("/token/refresh", post(auth::refresh_handler))
+        .route("/password/forgot", post(account_emails::forgot_password))
+        .route("/password/reset", post(account_emails::reset_password))
+        .route("/email/verify", post(account_emails::verify_email))
         .merge(SwaggerUi::new("/swagger-ui").url("/api-docs/openapi.json", openapi.clone()))
         .fallback(fallback_handler)
         .with_state(shared_app_state)
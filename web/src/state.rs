@@ -1,11 +1,25 @@
+use crate::blobs::{BlobStore, LocalBlobStore};
+use crate::mailer::{self, Mailer};
+use crate::passwords::PasswordHashing;
 use forge_api_config::Config;
 use forge_api_db::{connect_pool, DbPool};
+use jwt_lib::Jwt;
 use std::sync::Arc;
 
 /// The application's state that is available in [`crate::controllers`] and [`crate::middlewares`].
 pub struct AppState {
     /// The database pool that's used to get a connection to the application's database (see [`forge_api_db::DbPool`]).
     pub db_pool: DbPool,
+    /// Issues and verifies access tokens as configured in [`forge_api_config::AuthConfig`] (see [`jwt_lib::Jwt`]).
+    pub jwt: Jwt,
+    /// Hashes and verifies passwords as configured in [`forge_api_config::PasswordHashingConfig`] (see [`crate::passwords::PasswordHashing`]).
+    pub password_hashing: PasswordHashing,
+    /// The configuration the application was booted with (see [`forge_api_config::Config`]).
+    pub config: Config,
+    /// Where the contents of attachments are stored (see [`crate::blobs::BlobStore`]).
+    pub blob_store: Arc<dyn BlobStore>,
+    /// Sends emails as configured in [`forge_api_config::MailerConfig`] (see [`crate::mailer::Mailer`]).
+    pub mailer: Arc<dyn Mailer>,
 }
 
 /// The application's state as it is shared across the application, e.g. in controllers and middlewares.
@@ -17,9 +31,21 @@ pub type SharedAppState = Arc<AppState>;
 ///
 /// This function creates an [`AppState`] based on the current [`forge_api_config::Config`].
 pub async fn init_app_state(config: Config) -> AppState {
-    let db_pool = connect_pool(config.database)
+    let db_pool = connect_pool(config.database.clone())
         .await
         .expect("Could not connect to database!");
+    let jwt = Jwt::from_config(&config.auth).expect("Could not set up JWT keys!");
+    let password_hashing = PasswordHashing::from_config(&config.password_hashing)
+        .expect("Could not set up password hashing!");
+    let blob_store = Arc::new(LocalBlobStore::new(&config.attachments.storage_root));
+    let mailer = mailer::from_config(&config.mailer).expect("Could not set up mailer!");
 
-    AppState { db_pool }
+    AppState {
+        db_pool,
+        jwt,
+        password_hashing,
+        config,
+        blob_store,
+        mailer,
+    }
 }
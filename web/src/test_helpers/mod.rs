@@ -1,19 +1,29 @@
+use crate::blobs::{BlobStore, LocalBlobStore};
+use crate::mailer::InMemoryMailer;
+use crate::passwords::PasswordHashing;
 use crate::routes::init_routes;
 use crate::state::AppState;
 use axum::{
     body::{Body, Bytes},
+    extract::connect_info::{ConnectInfo, MockConnectInfo},
     http::{Method, Request},
     response::Response,
     Router,
 };
 use forge_api_config::{load_config, Config, Environment};
 use forge_api_db::{
+    entities::users::User,
     test_helpers::{setup_db, teardown_db},
     DbPool,
 };
 use hyper::header::{HeaderMap, HeaderName};
+use jwt_lib::Jwt;
 use std::cell::OnceCell;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
 use tower::ServiceExt;
+use uuid::Uuid;
 
 /// A request that a test sends to the application.
 ///
@@ -34,6 +44,7 @@ pub struct TestRequest {
     method: Method,
     headers: HeaderMap,
     body: Body,
+    client_addr: Option<SocketAddr>,
 }
 
 impl TestRequest {
@@ -44,6 +55,7 @@ impl TestRequest {
             headers: HeaderMap::new(),
             body: Body::empty(),
             method: Method::GET,
+            client_addr: None,
         }
     }
 
@@ -91,6 +103,23 @@ impl TestRequest {
         self
     }
 
+    /// Sets the address the request is sent from. Requests are sent from [`TEST_CLIENT_ADDR`] by default.
+    ///
+    /// Example:
+    /// ```
+    /// let response = context
+    ///     .app
+    ///     .request("/login")
+    ///     .client_addr(SocketAddr::from(([192, 0, 2, 1], 4711)))
+    ///     .send()
+    ///     .await;
+    /// ```
+    #[allow(unused)]
+    pub fn client_addr(mut self, addr: SocketAddr) -> Self {
+        self.client_addr = Some(addr);
+        self
+    }
+
     /// Sends the request to the application under test.
     #[allow(unused)]
     pub async fn send(self) -> Response {
@@ -102,12 +131,20 @@ impl TestRequest {
 
         request_builder = request_builder.method(&self.method);
 
+        if let Some(addr) = self.client_addr {
+            request_builder = request_builder.extension(ConnectInfo(addr));
+        }
+
         let request = request_builder.body(self.body);
 
         self.router.oneshot(request.unwrap()).await.unwrap()
     }
 }
 
+/// The address requests are sent from unless another one is set via [`TestRequest::client_addr`].
+pub const TEST_CLIENT_ADDR: SocketAddr =
+    SocketAddr::new(std::net::IpAddr::V4(std::net::Ipv4Addr::LOCALHOST), 4711);
+
 /// Testing convenience functions for [`axum::Router`].
 pub trait RouterExt {
     /// Creates a [`TestRequest`] pointed at the application under test.
@@ -200,11 +237,41 @@ pub struct DbTestContext {
     pub app: Router,
     /// A connection pool connected to the same database that the application that is being tested uses as well.
     pub db_pool: DbPool,
+    /// The same token issuer the application that is being tested uses.
+    pub jwt: Jwt,
+    /// The same password hashing the application that is being tested uses.
+    pub password_hashing: PasswordHashing,
+    /// The same blob store the application that is being tested uses.
+    pub blob_store: Arc<dyn BlobStore>,
+    /// The mailer the application that is being tested sends emails with, keeping them in memory.
+    pub mailer: Arc<InMemoryMailer>,
+    storage_root: PathBuf,
+}
+
+impl DbTestContext {
+    /// Returns an `Authorization` header value carrying a freshly issued token for the passed user.
+    ///
+    /// Example:
+    /// ```
+    /// let user = create_user(Faker.fake(), &context.db_pool).await.unwrap();
+    ///
+    /// let response = context
+    ///     .app
+    ///     .request("/tasks")
+    ///     .method(Method::POST)
+    ///     .header(http::header::AUTHORIZATION, &context.bearer_token(&user))
+    ///     .send()
+    ///     .await;
+    /// ```
+    #[allow(unused)]
+    pub fn bearer_token(&self, user: &User) -> String {
+        format!("Bearer {}", self.jwt.get_jwt(user).unwrap())
+    }
 }
 
 /// Sets up a test and returns a [`DbTestContext`] configured for the particular test case.
 ///
-/// This function initializes a new instance of the application under test using the configuration for [`forge_api_config::Environment::Test`]. The application is configured to use the same database that is also made available to the test itself via the test context. That database is a clone of the main test database that is only used by the particular test case to ensure isolation between test cases. It is automatically torn down after the test case completes (see [`teardown`]).
+/// This function initializes a new instance of the application under test using the configuration for [`forge_api_config::Environment::Test`]. The application is configured to use the same database that is also made available to the test itself via the test context. That database is a clone of the main test database that is only used by the particular test case to ensure isolation between test cases. Likewise, blobs are stored in a temporary directory dedicated to the test case. Both are automatically torn down after the test case completes (see [`teardown`]).
 ///
 /// This function is not invoked directly but used inside of the [`forge_api_macros::db_test`] attribute macro. The test context is automatically passed to test cases marked with that macro as an argument.
 #[allow(unused)]
@@ -213,25 +280,44 @@ pub async fn setup() -> DbTestContext {
     let config = init_config.get_or_init(|| load_config(&Environment::Test).unwrap());
 
     let test_db_pool = setup_db(&config.database).await;
+    let jwt = Jwt::from_config(&config.auth).unwrap();
+    let password_hashing = PasswordHashing::from_config(&config.password_hashing).unwrap();
+    let mut config = config.clone();
+    let storage_root = std::env::temp_dir().join(format!("forge-api-test-{}", Uuid::new_v4()));
+    config.attachments.storage_root = storage_root.clone();
+    let blob_store: Arc<dyn BlobStore> = Arc::new(LocalBlobStore::new(&storage_root));
+    let mailer = Arc::new(InMemoryMailer::new());
 
     let app = init_routes(AppState {
         db_pool: test_db_pool.clone(),
-    });
+        jwt: jwt.clone(),
+        password_hashing: password_hashing.clone(),
+        config,
+        blob_store: blob_store.clone(),
+        mailer: mailer.clone(),
+    })
+    .layer(MockConnectInfo(TEST_CLIENT_ADDR));
 
     DbTestContext {
         app,
         db_pool: test_db_pool,
+        jwt,
+        password_hashing,
+        blob_store,
+        mailer,
+        storage_root,
     }
 }
 
 /// Tears down a [`DbTestContext`].
 ///
-/// This function drops the test-case specific database set up by [`setup`].
+/// This function drops the test-case specific database and blob storage directory set up by [`setup`].
 ///
 /// This function is not invoked directly but used inside of the [`forge_api_macros::db_test`] attribute macro. The test context is automatically passed to test cases marked with that macro as an argument.
 #[allow(unused)]
 pub async fn teardown(context: DbTestContext) {
     drop(context.app);
 
+    let _ = tokio::fs::remove_dir_all(&context.storage_root).await;
     teardown_db(context.db_pool);
 }
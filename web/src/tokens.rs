@@ -0,0 +1,51 @@
+use rand::{rng, RngCore};
+use sha2::{Digest, Sha256};
+
+/// Generates a random, unguessable token with 256 bits of entropy, encoded as hex.
+pub fn generate() -> String {
+    let mut bytes = [0u8; 32];
+    rng().fill_bytes(&mut bytes);
+    hex::encode(bytes)
+}
+
+/// Hashes a token for storing it at rest.
+///
+/// Tokens generated by [`generate`] carry enough entropy that a single round of SHA-256 is sufficient – unlike passwords, they don't need a slow hash. The hash is deterministic so that tokens can be looked up by it.
+pub fn hash(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+/// The start of every API key, which tells them apart from access tokens (see [`generate_api_key`]).
+pub const API_KEY_PREFIX: &str = "forge_";
+
+/// Generates a new API key of the form `forge_<lookup>_<secret>`, returning it along with its public prefix `forge_<lookup>`.
+///
+/// The prefix is stored in plain text so that keys can be looked up and told apart, while only the hash of the whole key is stored (see [`hash`]).
+pub fn generate_api_key() -> (String, String) {
+    let mut lookup = [0u8; 6];
+    rng().fill_bytes(&mut lookup);
+    let prefix = format!("{}{}", API_KEY_PREFIX, hex::encode(lookup));
+    let key = format!("{}_{}", prefix, generate());
+
+    (key, prefix)
+}
+
+/// Returns the public prefix of an API key or `None` if the passed string is not formed like one.
+pub fn api_key_prefix(key: &str) -> Option<&str> {
+    let rest = key.strip_prefix(API_KEY_PREFIX)?;
+    let (lookup, secret) = rest.split_once('_')?;
+    if lookup.is_empty() || secret.is_empty() {
+        return None;
+    }
+
+    Some(&key[..API_KEY_PREFIX.len() + lookup.len()])
+}
+
+/// Compares two strings in time that only depends on their lengths.
+pub fn constant_time_eq(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .fold(0, |difference, (a, b)| difference | (a ^ b))
+            == 0
+}
@@ -0,0 +1,382 @@
+use crate::error::Error;
+use crate::login_throttling::LoginAttempt;
+use crate::middlewares::auth::CurrentUser;
+use crate::state::{AppState, SharedAppState};
+use crate::tokens;
+use axum::{
+    extract::{ConnectInfo, Json, State},
+    http::StatusCode,
+};
+use forge_api_db::entities::{recovery_codes, users};
+use forge_api_db::transaction;
+use rand::{rng, RngCore};
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+use std::time::{SystemTime, UNIX_EPOCH};
+use totp_rs::{Algorithm, Secret, TOTP};
+use uuid::Uuid;
+use validator::{Validate, ValidationError, ValidationErrors};
+
+/// The number of seconds a TOTP code is valid for.
+const TOTP_STEP_SECS: u64 = 30;
+
+/// The number of digits of a TOTP code.
+const TOTP_DIGITS: usize = 6;
+
+/// The number of time steps before and after the current one that codes are accepted for to make up for clock drift.
+const TOTP_SKEW_STEPS: u64 = 1;
+
+/// The number of recovery codes handed out at once.
+const RECOVERY_CODE_COUNT: usize = 10;
+
+/// The characters recovery codes consist of, 32 of them so that every character carries 5 bits.
+const RECOVERY_CODE_ALPHABET: &[u8; 32] = b"abcdefghijklmnopqrstuvwxyz234567";
+
+/// The payload of requests that need to be confirmed with a second factor.
+#[derive(Deserialize, Validate, utoipa::ToSchema)]
+#[cfg_attr(feature = "test-helpers", derive(Serialize))]
+pub struct TwoFactorCodeRequest {
+    /// A code from the user's authenticator app or, unless confirming enrollment, one of their recovery codes
+    #[validate(length(min = 1))]
+    pub code: String,
+}
+
+/// Whether two-factor authentication is enabled for the current user.
+#[derive(Serialize, Deserialize, utoipa::ToSchema)]
+pub struct TwoFactorStatus {
+    /// Whether logging in requires a code from an authenticator app
+    pub enabled: bool,
+    /// The number of recovery codes that have not been used yet
+    pub recovery_codes_left: i64,
+}
+
+/// The secret to set up an authenticator app with.
+#[derive(Serialize, Deserialize, utoipa::ToSchema)]
+pub struct TotpEnrollment {
+    /// The base32 encoded secret for entering it manually
+    pub secret: String,
+    /// The `otpauth://` URI for showing it as a QR code
+    pub otpauth_uri: String,
+}
+
+/// Recovery codes that can be used instead of a code from the authenticator app, e.g. when the device is lost.
+#[derive(Serialize, Deserialize, utoipa::ToSchema)]
+pub struct RecoveryCodes {
+    /// The codes, each of which can be used once. They are only shown this one time.
+    pub recovery_codes: Vec<String>,
+}
+
+/// Returns whether the current user has enabled two-factor authentication.
+#[utoipa::path(
+    get,
+    path = "/account/2fa",
+    responses(
+        (status = OK, body = TwoFactorStatus),
+        (status = UNAUTHORIZED, description = "Not authenticated"),
+        (status = FORBIDDEN, description = "Authenticated with an API key")
+    )
+)]
+#[axum::debug_handler]
+pub async fn status(
+    State(app_state): State<SharedAppState>,
+    user: CurrentUser,
+) -> Result<Json<TwoFactorStatus>, Error> {
+    user.require_session()?;
+
+    let enabled = is_enabled(&app_state, user.id).await?;
+    let recovery_codes_left = if enabled {
+        recovery_codes::count_unused(user.id, &app_state.db_pool).await?
+    } else {
+        0
+    };
+
+    Ok(Json(TwoFactorStatus {
+        enabled,
+        recovery_codes_left,
+    }))
+}
+
+/// Starts enrolling the current user in two-factor authentication with TOTP.
+///
+/// A new secret is generated that the user adds to their authenticator app. Logging in only requires a code once enrollment has been confirmed with a code from the app via `/account/2fa/totp/confirm`. Starting over replaces the secret of an unconfirmed enrollment.
+#[utoipa::path(
+    post,
+    path = "/account/2fa/totp",
+    responses(
+        (status = OK, body = TotpEnrollment),
+        (status = UNAUTHORIZED, description = "Not authenticated"),
+        (status = FORBIDDEN, description = "Authenticated with an API key"),
+        (status = CONFLICT, description = "Two-factor authentication is enabled already")
+    )
+)]
+#[axum::debug_handler]
+pub async fn enroll(
+    State(app_state): State<SharedAppState>,
+    user: CurrentUser,
+) -> Result<Json<TotpEnrollment>, Error> {
+    user.require_session()?;
+
+    let mut secret = vec![0u8; 20];
+    rng().fill_bytes(&mut secret);
+    let totp = TOTP::new(
+        Algorithm::SHA1,
+        TOTP_DIGITS,
+        TOTP_SKEW_STEPS as u8,
+        TOTP_STEP_SECS,
+        secret,
+        Some(app_state.config.two_factor.issuer.clone()),
+        user.name.clone(),
+    )
+    .map_err(|e| anyhow::anyhow!("Invalid TOTP parameters: {}", e))?;
+    let encoded_secret = totp.get_secret_base32();
+
+    users::start_totp_enrollment(user.id, &encoded_secret, &app_state.db_pool).await?;
+
+    Ok(Json(TotpEnrollment {
+        secret: encoded_secret,
+        otpauth_uri: totp.get_url(),
+    }))
+}
+
+/// Confirms enrolling the current user in two-factor authentication with a code from their authenticator app.
+///
+/// From then on, logging in requires a code as well. The response carries the user's recovery codes.
+#[utoipa::path(
+    post,
+    path = "/account/2fa/totp/confirm",
+    request_body(content = TwoFactorCodeRequest, content_type = "application/json"),
+    responses(
+        (status = OK, body = RecoveryCodes),
+        (status = UNAUTHORIZED, description = "Not authenticated"),
+        (status = FORBIDDEN, description = "Authenticated with an API key"),
+        (status = NOT_FOUND, description = "Enrollment has not been started"),
+        (status = CONFLICT, description = "Two-factor authentication is enabled already"),
+        (status = UNPROCESSABLE_ENTITY, description = "Invalid code")
+    )
+)]
+#[axum::debug_handler]
+pub async fn confirm(
+    State(app_state): State<SharedAppState>,
+    user: CurrentUser,
+    Json(payload): Json<TwoFactorCodeRequest>,
+) -> Result<Json<RecoveryCodes>, Error> {
+    user.require_session()?;
+
+    payload.validate()?;
+
+    let totp = users::load_totp(user.id, &app_state.db_pool)
+        .await?
+        .ok_or(forge_api_db::Error::NoRecordFound)?;
+    if totp.enabled_at.is_some() {
+        return Err(forge_api_db::Error::Conflict("totp").into());
+    }
+    let step = matching_step(&totp.secret, &payload.code, None)?.ok_or_else(invalid_code)?;
+
+    let codes = generate_recovery_codes();
+    let mut tx = transaction(&app_state.db_pool).await?;
+    users::enable_totp(user.id, step, &mut *tx).await?;
+    recovery_codes::replace(user.id, &hash_recovery_codes(&codes), &mut *tx).await?;
+    tx.commit().await.map_err(forge_api_db::Error::DbError)?;
+
+    Ok(Json(RecoveryCodes {
+        recovery_codes: codes,
+    }))
+}
+
+/// Disables two-factor authentication for the current user.
+///
+/// This has to be confirmed with a code from the authenticator app or one of the recovery codes, all of which are invalidated. Wrong codes count as failed logins (see [`crate::login_throttling`]).
+#[utoipa::path(
+    post,
+    path = "/account/2fa/disable",
+    request_body(content = TwoFactorCodeRequest, content_type = "application/json"),
+    responses(
+        (status = NO_CONTENT),
+        (status = UNAUTHORIZED, description = "Not authenticated"),
+        (status = FORBIDDEN, description = "Authenticated with an API key"),
+        (status = NOT_FOUND, description = "Two-factor authentication is not enabled"),
+        (status = UNPROCESSABLE_ENTITY, description = "Invalid code"),
+        (status = TOO_MANY_REQUESTS, description = "Too many failed attempts")
+    )
+)]
+#[axum::debug_handler]
+pub async fn disable(
+    State(app_state): State<SharedAppState>,
+    ConnectInfo(client_addr): ConnectInfo<SocketAddr>,
+    user: CurrentUser,
+    Json(payload): Json<TwoFactorCodeRequest>,
+) -> Result<StatusCode, Error> {
+    user.require_session()?;
+
+    payload.validate()?;
+    confirm_with_second_factor(&app_state, &user, client_addr, &payload.code).await?;
+
+    let mut tx = transaction(&app_state.db_pool).await?;
+    users::disable_totp(user.id, &mut *tx).await?;
+    recovery_codes::delete_all(user.id, &mut *tx).await?;
+    tx.commit().await.map_err(forge_api_db::Error::DbError)?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Replaces the recovery codes of the current user with new ones.
+///
+/// This has to be confirmed with a code from the authenticator app or one of the recovery codes. All previous codes are invalidated. Wrong codes count as failed logins (see [`crate::login_throttling`]).
+#[utoipa::path(
+    post,
+    path = "/account/2fa/recovery-codes",
+    request_body(content = TwoFactorCodeRequest, content_type = "application/json"),
+    responses(
+        (status = OK, body = RecoveryCodes),
+        (status = UNAUTHORIZED, description = "Not authenticated"),
+        (status = FORBIDDEN, description = "Authenticated with an API key"),
+        (status = NOT_FOUND, description = "Two-factor authentication is not enabled"),
+        (status = UNPROCESSABLE_ENTITY, description = "Invalid code"),
+        (status = TOO_MANY_REQUESTS, description = "Too many failed attempts")
+    )
+)]
+#[axum::debug_handler]
+pub async fn regenerate_recovery_codes(
+    State(app_state): State<SharedAppState>,
+    ConnectInfo(client_addr): ConnectInfo<SocketAddr>,
+    user: CurrentUser,
+    Json(payload): Json<TwoFactorCodeRequest>,
+) -> Result<Json<RecoveryCodes>, Error> {
+    user.require_session()?;
+
+    payload.validate()?;
+    confirm_with_second_factor(&app_state, &user, client_addr, &payload.code).await?;
+
+    let codes = generate_recovery_codes();
+    recovery_codes::replace(user.id, &hash_recovery_codes(&codes), &app_state.db_pool).await?;
+
+    Ok(Json(RecoveryCodes {
+        recovery_codes: codes,
+    }))
+}
+
+/// Returns whether logging in requires a second factor for the user.
+pub async fn is_enabled(app_state: &AppState, user_id: Uuid) -> Result<bool, Error> {
+    let totp = users::load_totp(user_id, &app_state.db_pool).await?;
+
+    Ok(totp.is_some_and(|totp| totp.enabled_at.is_some()))
+}
+
+/// Checks a code presented as the second factor of a user.
+///
+/// Six-digit codes are checked against the user's authenticator app, all others against their recovery codes. Either can only be used once. Returns whether the code is valid, which it never is if the user hasn't enabled two-factor authentication.
+pub async fn verify(app_state: &AppState, user_id: Uuid, code: &str) -> Result<bool, Error> {
+    let totp = match users::load_totp(user_id, &app_state.db_pool).await? {
+        Some(totp) if totp.enabled_at.is_some() => totp,
+        _ => return Ok(false),
+    };
+
+    if code.len() == TOTP_DIGITS && code.bytes().all(|b| b.is_ascii_digit()) {
+        match matching_step(&totp.secret, code, totp.last_used_step)? {
+            Some(step) => Ok(users::use_totp_step(user_id, step, &app_state.db_pool).await?),
+            None => Ok(false),
+        }
+    } else {
+        let code_hash = tokens::hash(&normalize_recovery_code(code));
+        Ok(recovery_codes::consume(user_id, &code_hash, &app_state.db_pool).await?)
+    }
+}
+
+/// Requires a valid second factor for a change to the current user's two-factor authentication, counting wrong codes as failed logins.
+async fn confirm_with_second_factor(
+    app_state: &AppState,
+    user: &CurrentUser,
+    client_addr: SocketAddr,
+    code: &str,
+) -> Result<(), Error> {
+    if !is_enabled(app_state, user.id).await? {
+        return Err(forge_api_db::Error::NoRecordFound.into());
+    }
+
+    let attempt = LoginAttempt::new(&user.name, client_addr.ip());
+    attempt.ensure_not_locked(app_state).await?;
+    if !verify(app_state, user.id, code).await? {
+        attempt.record_failure(app_state).await?;
+        return Err(invalid_code());
+    }
+
+    Ok(())
+}
+
+/// Returns the time step the passed code is valid for if it is valid for the current step or one of the steps next to it.
+///
+/// Steps up to and including `last_used_step` are skipped so that codes can't be replayed.
+fn matching_step(
+    encoded_secret: &str,
+    code: &str,
+    last_used_step: Option<i64>,
+) -> Result<Option<i64>, anyhow::Error> {
+    let secret = Secret::Encoded(encoded_secret.to_string())
+        .to_bytes()
+        .map_err(|e| anyhow::anyhow!("Invalid TOTP secret: {}", e))?;
+    let totp = TOTP::new(
+        Algorithm::SHA1,
+        TOTP_DIGITS,
+        TOTP_SKEW_STEPS as u8,
+        TOTP_STEP_SECS,
+        secret,
+        None,
+        String::new(),
+    )
+    .map_err(|e| anyhow::anyhow!("Invalid TOTP parameters: {}", e))?;
+
+    let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+    let current_step = now / TOTP_STEP_SECS;
+    let steps = current_step.saturating_sub(TOTP_SKEW_STEPS)..=current_step + TOTP_SKEW_STEPS;
+    let matching_step = steps
+        .filter(|step| last_used_step.is_none_or(|last_used_step| *step as i64 > last_used_step))
+        .find(|step| tokens::constant_time_eq(&totp.generate(step * TOTP_STEP_SECS), code));
+
+    Ok(matching_step.map(|step| step as i64))
+}
+
+/// Generates a set of random recovery codes of the form `xxxx-xxxx-xxxx-xxxx` with 80 bits of entropy each.
+fn generate_recovery_codes() -> Vec<String> {
+    (0..RECOVERY_CODE_COUNT)
+        .map(|_| {
+            let mut bytes = [0u8; 16];
+            rng().fill_bytes(&mut bytes);
+            let chars: Vec<char> = bytes
+                .iter()
+                .map(|b| char::from(RECOVERY_CODE_ALPHABET[usize::from(b % 32)]))
+                .collect();
+            chars
+                .chunks(4)
+                .map(|chunk| chunk.iter().collect::<String>())
+                .collect::<Vec<_>>()
+                .join("-")
+        })
+        .collect()
+}
+
+/// Hashes recovery codes for storing them at rest.
+///
+/// Recovery codes carry enough entropy that they don't need a slow hash, just like tokens (see [`crate::tokens::hash`]).
+fn hash_recovery_codes(codes: &[String]) -> Vec<String> {
+    codes
+        .iter()
+        .map(|code| tokens::hash(&normalize_recovery_code(code)))
+        .collect()
+}
+
+/// Removes separators and whitespace and lowercases a recovery code so that it matches regardless of how it was typed.
+fn normalize_recovery_code(code: &str) -> String {
+    code.chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+fn invalid_code() -> Error {
+    let mut errors = ValidationErrors::new();
+    errors.add(
+        "code",
+        ValidationError::new("invalid").with_message("is invalid".into()),
+    );
+    Error::Validation(errors)
+}
@@ -0,0 +1,369 @@
+use axum::{
+    body::Body,
+    http::{self, Method},
+};
+use forge_api_db::entities::users;
+use forge_api_db::test_helpers::email_tokens::expire_all;
+use forge_api_macros::db_test;
+use forge_api_web::account_emails::{
+    ForgotPasswordRequest, ResetPasswordRequest, VerifyEmailRequest,
+};
+use forge_api_web::auth::{LoginRequest, RegisterRequest, TokenResponse};
+use forge_api_web::controllers::api_keys::CreatedApiKey;
+use forge_api_web::test_helpers::{BodyExt, DbTestContext, RouterExt};
+use googletest::prelude::*;
+use hyper::StatusCode;
+use serde_json::json;
+use std::time::Duration;
+
+const PASSWORD: &str = "s3cret-password";
+
+async fn post_json(
+    context: &DbTestContext,
+    path: &str,
+    payload: serde_json::Value,
+) -> axum::response::Response {
+    context
+        .app
+        .request(path)
+        .method(Method::POST)
+        .body(Body::from(payload.to_string()))
+        .header(http::header::CONTENT_TYPE, "application/json")
+        .send()
+        .await
+}
+
+async fn register(context: &DbTestContext, name: &str, email: &str) -> axum::response::Response {
+    let payload = json!(RegisterRequest {
+        name: String::from(name),
+        password: String::from(PASSWORD),
+        email: Some(String::from(email)),
+    });
+    post_json(context, "/register", payload).await
+}
+
+async fn login(context: &DbTestContext, name: &str, password: &str) -> axum::response::Response {
+    let payload = json!(LoginRequest {
+        name: String::from(name),
+        password: String::from(password),
+    });
+    post_json(context, "/login", payload).await
+}
+
+async fn verify_email(context: &DbTestContext, token: &str) -> StatusCode {
+    let payload = json!(VerifyEmailRequest {
+        token: String::from(token),
+    });
+    post_json(context, "/email/verify", payload).await.status()
+}
+
+async fn forgot_password(context: &DbTestContext, email: &str) -> StatusCode {
+    let payload = json!(ForgotPasswordRequest {
+        email: String::from(email),
+    });
+    post_json(context, "/password/forgot", payload)
+        .await
+        .status()
+}
+
+async fn reset_password(context: &DbTestContext, token: &str, password: &str) -> StatusCode {
+    let payload = json!(ResetPasswordRequest {
+        token: String::from(token),
+        password: String::from(password),
+    });
+    post_json(context, "/password/reset", payload)
+        .await
+        .status()
+}
+
+/// Waits for the latest email with the passed subject to the passed address and returns the token from the link in it.
+async fn token_from_email(context: &DbTestContext, to: &str, subject: &str) -> String {
+    let email = context.mailer.wait_for(to, subject).await.unwrap();
+    let (_, rest) = email.body.split_once("?token=").unwrap();
+    rest.split_whitespace().next().unwrap().to_string()
+}
+
+/// Registers a user with the passed email address and verifies it.
+async fn register_verified(context: &DbTestContext, name: &str, email: &str) {
+    let response = register(context, name, email).await;
+    assert_that!(response.status(), eq(StatusCode::CREATED));
+    let token = token_from_email(context, email, "Verify your email address").await;
+    assert_that!(
+        verify_email(context, &token).await,
+        eq(StatusCode::NO_CONTENT)
+    );
+}
+
+#[db_test]
+async fn test_register_and_verify_email(context: &DbTestContext) {
+    let response = register(context, "jane.doe", "jane@example.com").await;
+    assert_that!(response.status(), eq(StatusCode::CREATED));
+
+    let email = context
+        .mailer
+        .wait_for("jane@example.com", "Verify your email address")
+        .await
+        .unwrap();
+    assert_that!(
+        email.body,
+        contains_substring("http://localhost:3000/email/verify?token=")
+    );
+    let token = token_from_email(context, "jane@example.com", "Verify your email address").await;
+
+    // The address can only be used for resetting the password once it has been verified.
+    let user = users::find_user_by_verified_email("jane@example.com", &context.db_pool)
+        .await
+        .unwrap();
+    assert_that!(user, none());
+
+    assert_that!(
+        verify_email(context, &token).await,
+        eq(StatusCode::NO_CONTENT)
+    );
+    let user = users::find_user_by_verified_email("JANE@example.com", &context.db_pool)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_that!(user.name, eq("jane.doe"));
+
+    // Tokens can only be used once.
+    assert_that!(
+        verify_email(context, &token).await,
+        eq(StatusCode::UNPROCESSABLE_ENTITY)
+    );
+}
+
+#[db_test]
+async fn test_register_email_taken(context: &DbTestContext) {
+    register_verified(context, "jane.doe", "jane@example.com").await;
+
+    // The response doesn't reveal that the address is taken.
+    let response = register(context, "john.doe", "Jane@Example.com").await;
+    assert_that!(response.status(), eq(StatusCode::CREATED));
+
+    let email = context
+        .mailer
+        .wait_for(
+            "Jane@Example.com",
+            "Your email address is already registered",
+        )
+        .await
+        .unwrap();
+    assert_that!(email.body, not(contains_substring("token=")));
+    let user = users::find_user_by_name("john.doe", &context.db_pool)
+        .await
+        .unwrap();
+    assert_that!(user, some(anything()));
+    let user = users::find_user_by_verified_email("jane@example.com", &context.db_pool)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_that!(user.name, eq("jane.doe"));
+    let subjects: Vec<String> = context
+        .mailer
+        .sent()
+        .into_iter()
+        .map(|email| email.subject)
+        .collect();
+    assert_that!(
+        subjects,
+        elements_are![
+            eq("Verify your email address"),
+            eq("Your email address is already registered")
+        ]
+    );
+}
+
+#[db_test]
+async fn test_register_email_claimed_by_squatter(context: &DbTestContext) {
+    let response = register(context, "mallory", "JANE@example.com").await;
+    assert_that!(response.status(), eq(StatusCode::CREATED));
+    let squatter_token =
+        token_from_email(context, "JANE@example.com", "Verify your email address").await;
+
+    // The owner of the address can still register with it and verify it.
+    register_verified(context, "jane.doe", "jane@example.com").await;
+    let user = users::find_user_by_verified_email("jane@example.com", &context.db_pool)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_that!(user.name, eq("jane.doe"));
+
+    // The unverified claim of the squatter is removed once the owner has verified the address.
+    assert_that!(
+        verify_email(context, &squatter_token).await,
+        eq(StatusCode::UNPROCESSABLE_ENTITY)
+    );
+}
+
+#[db_test]
+async fn test_register_invalid_email(context: &DbTestContext) {
+    let response = register(context, "jane.doe", "not an email").await;
+
+    assert_that!(response.status(), eq(StatusCode::UNPROCESSABLE_ENTITY));
+}
+
+#[db_test]
+async fn test_verify_email_with_unknown_token(context: &DbTestContext) {
+    assert_that!(
+        verify_email(context, "unknown-token").await,
+        eq(StatusCode::UNPROCESSABLE_ENTITY)
+    );
+}
+
+#[db_test]
+async fn test_forgot_and_reset_password(context: &DbTestContext) {
+    register_verified(context, "jane.doe", "jane@example.com").await;
+    let response = login(context, "jane.doe", PASSWORD).await;
+    assert_that!(response.status(), eq(StatusCode::OK));
+    let tokens: TokenResponse = response.into_body().into_json().await;
+
+    assert_that!(
+        forgot_password(context, "Jane@Example.com").await,
+        eq(StatusCode::ACCEPTED)
+    );
+    let token = token_from_email(context, "Jane@Example.com", "Reset your password").await;
+
+    // The new password has to satisfy the same rules as on registration.
+    assert_that!(
+        reset_password(context, &token, "password").await,
+        eq(StatusCode::UNPROCESSABLE_ENTITY)
+    );
+    assert_that!(
+        reset_password(context, &token, "n3w-password").await,
+        eq(StatusCode::NO_CONTENT)
+    );
+
+    let response = login(context, "jane.doe", PASSWORD).await;
+    assert_that!(response.status(), eq(StatusCode::UNAUTHORIZED));
+    let response = login(context, "jane.doe", "n3w-password").await;
+    assert_that!(response.status(), eq(StatusCode::OK));
+
+    // All sessions are logged out.
+    let response = post_json(
+        context,
+        "/token/refresh",
+        json!({ "refresh_token": tokens.refresh_token }),
+    )
+    .await;
+    assert_that!(response.status(), eq(StatusCode::UNAUTHORIZED));
+
+    // Tokens can only be used once.
+    assert_that!(
+        reset_password(context, &token, "0ther-password").await,
+        eq(StatusCode::UNPROCESSABLE_ENTITY)
+    );
+}
+
+#[db_test]
+async fn test_reset_password_revokes_api_keys(context: &DbTestContext) {
+    register_verified(context, "jane.doe", "jane@example.com").await;
+    let response = login(context, "jane.doe", PASSWORD).await;
+    let tokens: TokenResponse = response.into_body().into_json().await;
+    let response = context
+        .app
+        .request("/account/api-keys")
+        .method(Method::POST)
+        .body(Body::from(
+            json!({ "name": "CI", "scopes": ["notes:read"] }).to_string(),
+        ))
+        .header(http::header::CONTENT_TYPE, "application/json")
+        .header(
+            http::header::AUTHORIZATION,
+            &format!("Bearer {}", tokens.token),
+        )
+        .send()
+        .await;
+    assert_that!(response.status(), eq(StatusCode::CREATED));
+    let created: CreatedApiKey = response.into_body().into_json().await;
+
+    forgot_password(context, "jane@example.com").await;
+    let token = token_from_email(context, "jane@example.com", "Reset your password").await;
+    assert_that!(
+        reset_password(context, &token, "n3w-password").await,
+        eq(StatusCode::NO_CONTENT)
+    );
+
+    let response = context
+        .app
+        .request("/notes")
+        .header(
+            http::header::AUTHORIZATION,
+            &format!("Bearer {}", created.key),
+        )
+        .send()
+        .await;
+    assert_that!(response.status(), eq(StatusCode::UNAUTHORIZED));
+}
+
+#[db_test]
+async fn test_reset_password_invalidates_other_tokens(context: &DbTestContext) {
+    register_verified(context, "jane.doe", "jane@example.com").await;
+    forgot_password(context, "jane@example.com").await;
+    let first_token = token_from_email(context, "jane@example.com", "Reset your password").await;
+    let emails_sent = context.mailer.sent().len();
+    forgot_password(context, "jane@example.com").await;
+    for _ in 0..100 {
+        if context.mailer.sent().len() > emails_sent {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    let second_token = token_from_email(context, "jane@example.com", "Reset your password").await;
+    assert_that!(second_token, not(eq(&first_token)));
+
+    assert_that!(
+        reset_password(context, &second_token, "n3w-password").await,
+        eq(StatusCode::NO_CONTENT)
+    );
+
+    assert_that!(
+        reset_password(context, &first_token, "0ther-password").await,
+        eq(StatusCode::UNPROCESSABLE_ENTITY)
+    );
+}
+
+#[db_test]
+async fn test_reset_password_with_expired_token(context: &DbTestContext) {
+    register_verified(context, "jane.doe", "jane@example.com").await;
+    forgot_password(context, "jane@example.com").await;
+    let token = token_from_email(context, "jane@example.com", "Reset your password").await;
+    expire_all(&context.db_pool).await.unwrap();
+
+    assert_that!(
+        reset_password(context, &token, "n3w-password").await,
+        eq(StatusCode::UNPROCESSABLE_ENTITY)
+    );
+    let response = login(context, "jane.doe", PASSWORD).await;
+    assert_that!(response.status(), eq(StatusCode::OK));
+}
+
+#[db_test]
+async fn test_forgot_password_for_unverified_or_unknown_email(context: &DbTestContext) {
+    let response = register(context, "jane.doe", "jane@example.com").await;
+    assert_that!(response.status(), eq(StatusCode::CREATED));
+    context
+        .mailer
+        .wait_for("jane@example.com", "Verify your email address")
+        .await
+        .unwrap();
+
+    // The response doesn't reveal whether the address belongs to a user.
+    assert_that!(
+        forgot_password(context, "jane@example.com").await,
+        eq(StatusCode::ACCEPTED)
+    );
+    assert_that!(
+        forgot_password(context, "nobody@example.com").await,
+        eq(StatusCode::ACCEPTED)
+    );
+
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    let subjects: Vec<String> = context
+        .mailer
+        .sent()
+        .into_iter()
+        .map(|email| email.subject)
+        .collect();
+    assert_that!(subjects, elements_are![eq("Verify your email address")]);
+}
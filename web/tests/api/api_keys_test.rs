@@ -0,0 +1,286 @@
+use axum::{
+    body::Body,
+    http::{self, Method},
+};
+use chrono::{Duration, Utc};
+use forge_api_db::entities::api_keys::{ApiKey, ApiKeyChangeset};
+use forge_api_db::entities::users::User;
+use forge_api_db::test_helpers::api_keys::expire_all;
+use forge_api_db::test_helpers::users::create_fake as create_fake_user;
+use forge_api_macros::db_test;
+use forge_api_web::controllers::api_keys::CreatedApiKey;
+use forge_api_web::controllers::notes::BatchResult;
+use forge_api_web::test_helpers::{BodyExt, DbTestContext, RouterExt};
+use googletest::prelude::*;
+use hyper::StatusCode;
+use serde_json::json;
+
+async fn send(
+    context: &DbTestContext,
+    method: Method,
+    uri: &str,
+    authorization: &str,
+    payload: serde_json::Value,
+) -> axum::response::Response {
+    context
+        .app
+        .request(uri)
+        .method(method)
+        .body(Body::from(payload.to_string()))
+        .header(http::header::CONTENT_TYPE, "application/json")
+        .header(http::header::AUTHORIZATION, authorization)
+        .send()
+        .await
+}
+
+async fn create_key_response(
+    context: &DbTestContext,
+    user: &User,
+    name: &str,
+    scopes: &[&str],
+) -> axum::response::Response {
+    let payload = json!(ApiKeyChangeset {
+        name: String::from(name),
+        scopes: scopes.iter().map(|scope| scope.to_string()).collect(),
+        expires_at: None,
+    });
+    send(
+        context,
+        Method::POST,
+        "/account/api-keys",
+        &context.bearer_token(user),
+        payload,
+    )
+    .await
+}
+
+async fn create_key(context: &DbTestContext, user: &User, scopes: &[&str]) -> CreatedApiKey {
+    let response = create_key_response(context, user, "CI", scopes).await;
+    assert_that!(response.status(), eq(StatusCode::CREATED));
+    response.into_body().into_json().await
+}
+
+async fn list_keys(context: &DbTestContext, user: &User) -> Vec<ApiKey> {
+    let response = send(
+        context,
+        Method::GET,
+        "/account/api-keys",
+        &context.bearer_token(user),
+        json!({}),
+    )
+    .await;
+    assert_that!(response.status(), eq(StatusCode::OK));
+    response.into_body().into_json().await
+}
+
+fn bearer(key: &str) -> String {
+    format!("Bearer {}", key)
+}
+
+#[db_test]
+async fn test_create_and_use_key(context: &DbTestContext) {
+    let user = create_fake_user(&context.db_pool).await;
+
+    let created = create_key(context, &user, &["notes:read"]).await;
+    assert_that!(
+        created.key,
+        starts_with(format!("{}_", created.api_key.prefix))
+    );
+    assert_that!(created.api_key.prefix, starts_with("forge_"));
+    assert_that!(created.api_key.scopes, elements_are![eq("notes:read")]);
+    assert_that!(created.api_key.last_used_at, none());
+
+    let response = send(
+        context,
+        Method::GET,
+        "/notes",
+        &bearer(&created.key),
+        json!({}),
+    )
+    .await;
+    assert_that!(response.status(), eq(StatusCode::OK));
+
+    let keys = list_keys(context, &user).await;
+    assert_that!(keys, len(eq(1)));
+    assert_that!(keys[0].id, eq(created.api_key.id));
+    assert_that!(keys[0].name, eq("CI"));
+    assert_that!(keys[0].last_used_at, some(anything()));
+}
+
+#[db_test]
+async fn test_key_is_limited_to_its_scopes(context: &DbTestContext) {
+    let user = create_fake_user(&context.db_pool).await;
+    let created = create_key(context, &user, &["notes:read"]).await;
+
+    let response = send(
+        context,
+        Method::POST,
+        "/notes",
+        &bearer(&created.key),
+        json!({ "text": "new note" }),
+    )
+    .await;
+    assert_that!(response.status(), eq(StatusCode::FORBIDDEN));
+
+    let response = send(
+        context,
+        Method::POST,
+        "/notes/batch",
+        &bearer(&created.key),
+        json!({ "operations": [{ "op": "create", "note": { "text": "new note" } }] }),
+    )
+    .await;
+    assert_that!(response.status(), eq(StatusCode::OK));
+    let results: Vec<BatchResult> = response.into_body().into_json().await;
+    assert_that!(results[0].status, eq(403));
+}
+
+#[db_test]
+async fn test_create_key_with_invalid_scopes(context: &DbTestContext) {
+    let user = create_fake_user(&context.db_pool).await;
+
+    let response = create_key_response(context, &user, "CI", &[]).await;
+    assert_that!(response.status(), eq(StatusCode::UNPROCESSABLE_ENTITY));
+
+    // Keys can't be granted permissions the user doesn't have.
+    let response = create_key_response(context, &user, "CI", &["notes:read", "admin:all"]).await;
+    assert_that!(response.status(), eq(StatusCode::UNPROCESSABLE_ENTITY));
+}
+
+#[db_test]
+async fn test_create_key_with_expiry_in_the_past(context: &DbTestContext) {
+    let user = create_fake_user(&context.db_pool).await;
+    let payload = json!(ApiKeyChangeset {
+        name: String::from("CI"),
+        scopes: vec![String::from("notes:read")],
+        expires_at: Some(Utc::now() - Duration::minutes(1)),
+    });
+
+    let response = send(
+        context,
+        Method::POST,
+        "/account/api-keys",
+        &context.bearer_token(&user),
+        payload,
+    )
+    .await;
+
+    assert_that!(response.status(), eq(StatusCode::UNPROCESSABLE_ENTITY));
+}
+
+#[db_test]
+async fn test_create_key_name_taken(context: &DbTestContext) {
+    let user = create_fake_user(&context.db_pool).await;
+    create_key(context, &user, &["notes:read"]).await;
+
+    let response = create_key_response(context, &user, "ci", &["notes:read"]).await;
+
+    assert_that!(response.status(), eq(StatusCode::CONFLICT));
+}
+
+#[db_test]
+async fn test_revoke_key(context: &DbTestContext) {
+    let user = create_fake_user(&context.db_pool).await;
+    let created = create_key(context, &user, &["notes:read"]).await;
+    let uri = format!("/account/api-keys/{}", created.api_key.id);
+
+    // Users can't revoke the keys of other users.
+    let other_user = create_fake_user(&context.db_pool).await;
+    let response = send(
+        context,
+        Method::DELETE,
+        &uri,
+        &context.bearer_token(&other_user),
+        json!({}),
+    )
+    .await;
+    assert_that!(response.status(), eq(StatusCode::NOT_FOUND));
+
+    let response = send(
+        context,
+        Method::DELETE,
+        &uri,
+        &context.bearer_token(&user),
+        json!({}),
+    )
+    .await;
+    assert_that!(response.status(), eq(StatusCode::NO_CONTENT));
+
+    let response = send(
+        context,
+        Method::GET,
+        "/notes",
+        &bearer(&created.key),
+        json!({}),
+    )
+    .await;
+    assert_that!(response.status(), eq(StatusCode::UNAUTHORIZED));
+    assert_that!(list_keys(context, &user).await, empty());
+
+    // The name of a revoked key can be used again.
+    create_key(context, &user, &["notes:read"]).await;
+}
+
+#[db_test]
+async fn test_expired_key(context: &DbTestContext) {
+    let user = create_fake_user(&context.db_pool).await;
+    let created = create_key(context, &user, &["notes:read"]).await;
+    expire_all(&context.db_pool).await.unwrap();
+
+    let response = send(
+        context,
+        Method::GET,
+        "/notes",
+        &bearer(&created.key),
+        json!({}),
+    )
+    .await;
+
+    assert_that!(response.status(), eq(StatusCode::UNAUTHORIZED));
+}
+
+#[db_test]
+async fn test_invalid_key(context: &DbTestContext) {
+    let user = create_fake_user(&context.db_pool).await;
+    let created = create_key(context, &user, &["notes:read"]).await;
+    let (prefix, _) = created.key.rsplit_once('_').unwrap();
+
+    for key in [
+        format!("{}_{}", prefix, "0".repeat(64)),
+        String::from("forge_"),
+        String::from("forge_unknown_key"),
+    ] {
+        let response = send(context, Method::GET, "/notes", &bearer(&key), json!({})).await;
+        assert_that!(response.status(), eq(StatusCode::UNAUTHORIZED));
+    }
+}
+
+#[db_test]
+async fn test_key_cannot_manage_account(context: &DbTestContext) {
+    let user = create_fake_user(&context.db_pool).await;
+    let created = create_key(
+        context,
+        &user,
+        &["notes:read", "notes:create", "notes:update", "notes:delete"],
+    )
+    .await;
+    let authorization = bearer(&created.key);
+
+    for (method, uri) in [
+        (Method::GET, String::from("/account/api-keys")),
+        (Method::POST, String::from("/account/api-keys")),
+        (
+            Method::DELETE,
+            format!("/account/api-keys/{}", created.api_key.id),
+        ),
+        (Method::POST, String::from("/account/2fa/totp")),
+        (Method::POST, String::from("/logout")),
+        (Method::POST, String::from("/logout/all")),
+    ] {
+        let payload = json!({ "name": "escalated", "scopes": ["notes:read"] });
+        let response = send(context, method, &uri, &authorization, payload).await;
+        assert_that!(response.status(), eq(StatusCode::FORBIDDEN));
+    }
+
+    assert_that!(list_keys(context, &user).await, len(eq(1)));
+}
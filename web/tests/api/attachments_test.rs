@@ -0,0 +1,284 @@
+use axum::{
+    body::Body,
+    http::{self, Method},
+};
+use chrono::{Duration, Utc};
+use forge_api_db::entities::attachments::{self, Attachment};
+use forge_api_db::entities::note_shares::{self, NoteShareChangeset, SharePermission};
+use forge_api_db::entities::notes::{self, Note};
+use forge_api_db::entities::users::User;
+use forge_api_db::test_helpers::notes::create as create_note;
+use forge_api_db::test_helpers::users::create_fake as create_fake_user;
+use forge_api_macros::db_test;
+use forge_api_web::jobs::purge_trashed_notes;
+use forge_api_web::test_helpers::{BodyExt, DbTestContext, RouterExt};
+use googletest::prelude::*;
+use hyper::StatusCode;
+use std::io;
+
+const BOUNDARY: &str = "attachments-test-boundary";
+
+fn multipart_body(field: &str, file_name: &str, content_type: &str, data: &[u8]) -> Body {
+    let mut body = format!(
+        "--{BOUNDARY}\r\nContent-Disposition: form-data; name=\"{field}\"; filename=\"{file_name}\"\r\nContent-Type: {content_type}\r\n\r\n"
+    )
+    .into_bytes();
+    body.extend_from_slice(data);
+    body.extend_from_slice(format!("\r\n--{BOUNDARY}--\r\n").as_bytes());
+    Body::from(body)
+}
+
+async fn send_upload(
+    context: &DbTestContext,
+    user: &User,
+    note: &Note,
+    body: Body,
+) -> axum::response::Response {
+    context
+        .app
+        .request(&format!("/notes/{}/attachments", note.id))
+        .method(Method::POST)
+        .body(body)
+        .header(
+            http::header::CONTENT_TYPE,
+            &format!("multipart/form-data; boundary={BOUNDARY}"),
+        )
+        .header(http::header::AUTHORIZATION, &context.bearer_token(user))
+        .send()
+        .await
+}
+
+async fn upload(context: &DbTestContext, user: &User, note: &Note, data: &[u8]) -> Attachment {
+    let body = multipart_body("file", "report.pdf", "application/pdf", data);
+    let response = send_upload(context, user, note, body).await;
+    assert_that!(response.status(), eq(StatusCode::CREATED));
+
+    response.into_body().into_json().await
+}
+
+async fn blob_key(context: &DbTestContext, user: &User, attachment: &Attachment) -> String {
+    attachments::load(attachment.id, attachment.note_id, user.id, &context.db_pool)
+        .await
+        .unwrap()
+        .blob_key
+}
+
+async fn blob_exists(context: &DbTestContext, key: &str) -> bool {
+    match context.blob_store.get(key).await {
+        Ok(_) => true,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => false,
+        Err(e) => panic!("Reading blob failed: {e}"),
+    }
+}
+
+#[db_test]
+async fn test_upload_and_download(context: &DbTestContext) {
+    let user = create_fake_user(&context.db_pool).await;
+    let note = create_note("text", user.id, &context.db_pool).await;
+
+    let body = multipart_body(
+        "file",
+        "../dir/Überblick 1.pdf",
+        "application/pdf",
+        b"%PDF-1.7",
+    );
+    let response = send_upload(context, &user, &note, body).await;
+    assert_that!(response.status(), eq(StatusCode::CREATED));
+    let attachment: Attachment = response.into_body().into_json().await;
+    assert_that!(attachment.note_id, eq(note.id));
+    assert_that!(attachment.file_name, eq("Überblick 1.pdf"));
+    assert_that!(attachment.content_type, eq("application/pdf"));
+    assert_that!(attachment.size, eq(8));
+
+    let response = context
+        .app
+        .request(&format!("/notes/{}/attachments", note.id))
+        .header(http::header::AUTHORIZATION, &context.bearer_token(&user))
+        .send()
+        .await;
+    assert_that!(response.status(), eq(StatusCode::OK));
+    let listed: Vec<Attachment> = response.into_body().into_json().await;
+    assert_that!(
+        listed,
+        elements_are![matches_pattern!(Attachment {
+            id: eq(&attachment.id),
+            ..
+        })]
+    );
+
+    let response = context
+        .app
+        .request(&format!("/notes/{}/attachments/{}", note.id, attachment.id))
+        .header(http::header::AUTHORIZATION, &context.bearer_token(&user))
+        .send()
+        .await;
+    assert_that!(response.status(), eq(StatusCode::OK));
+    assert_that!(
+        response.headers().get(http::header::CONTENT_TYPE).unwrap(),
+        eq("application/pdf")
+    );
+    assert_that!(
+        response
+            .headers()
+            .get(http::header::CONTENT_DISPOSITION)
+            .unwrap(),
+        eq("attachment; filename=\"_berblick 1.pdf\"; filename*=UTF-8''%C3%9Cberblick%201.pdf")
+    );
+    let body = response.into_body().into_bytes().await;
+    assert_that!(body.as_ref(), eq(b"%PDF-1.7"));
+}
+
+#[db_test]
+async fn test_upload_unsupported_type(context: &DbTestContext) {
+    let user = create_fake_user(&context.db_pool).await;
+    let note = create_note("text", user.id, &context.db_pool).await;
+
+    let body = multipart_body("file", "page.html", "text/html", b"<script></script>");
+    let response = send_upload(context, &user, &note, body).await;
+
+    assert_that!(response.status(), eq(StatusCode::UNSUPPORTED_MEDIA_TYPE));
+    let attachments = attachments::load_all(note.id, user.id, &context.db_pool)
+        .await
+        .unwrap();
+    assert_that!(attachments, empty());
+}
+
+#[db_test]
+async fn test_upload_too_large(context: &DbTestContext) {
+    let user = create_fake_user(&context.db_pool).await;
+    let note = create_note("text", user.id, &context.db_pool).await;
+
+    let data = vec![b'a'; 10 * 1024 * 1024 + 1];
+    let body = multipart_body("file", "large.txt", "text/plain", &data);
+    let response = send_upload(context, &user, &note, body).await;
+
+    assert_that!(response.status(), eq(StatusCode::PAYLOAD_TOO_LARGE));
+    let attachments = attachments::load_all(note.id, user.id, &context.db_pool)
+        .await
+        .unwrap();
+    assert_that!(attachments, empty());
+}
+
+#[db_test]
+async fn test_upload_without_file(context: &DbTestContext) {
+    let user = create_fake_user(&context.db_pool).await;
+    let note = create_note("text", user.id, &context.db_pool).await;
+
+    let body = multipart_body("other", "notes.txt", "text/plain", b"text");
+    let response = send_upload(context, &user, &note, body).await;
+
+    assert_that!(response.status(), eq(StatusCode::UNPROCESSABLE_ENTITY));
+}
+
+#[db_test]
+async fn test_access_through_shares(context: &DbTestContext) {
+    let owner = create_fake_user(&context.db_pool).await;
+    let reader = create_fake_user(&context.db_pool).await;
+    let stranger = create_fake_user(&context.db_pool).await;
+    let note = create_note("text", owner.id, &context.db_pool).await;
+    let attachment = upload(context, &owner, &note, b"%PDF-1.7").await;
+    let share = NoteShareChangeset {
+        user_name: reader.name.clone(),
+        permission: SharePermission::Read,
+    };
+    note_shares::grant(note.id, owner.id, share, &context.db_pool)
+        .await
+        .unwrap();
+    let download_path = format!("/notes/{}/attachments/{}", note.id, attachment.id);
+
+    let response = context
+        .app
+        .request(&download_path)
+        .header(http::header::AUTHORIZATION, &context.bearer_token(&reader))
+        .send()
+        .await;
+    assert_that!(response.status(), eq(StatusCode::OK));
+
+    let body = multipart_body("file", "notes.txt", "text/plain", b"text");
+    let response = send_upload(context, &reader, &note, body).await;
+    assert_that!(response.status(), eq(StatusCode::FORBIDDEN));
+
+    let response = context
+        .app
+        .request(&download_path)
+        .method(Method::DELETE)
+        .header(http::header::AUTHORIZATION, &context.bearer_token(&reader))
+        .send()
+        .await;
+    assert_that!(response.status(), eq(StatusCode::FORBIDDEN));
+
+    let response = context
+        .app
+        .request(&download_path)
+        .header(
+            http::header::AUTHORIZATION,
+            &context.bearer_token(&stranger),
+        )
+        .send()
+        .await;
+    assert_that!(response.status(), eq(StatusCode::NOT_FOUND));
+
+    let body = multipart_body("file", "notes.txt", "text/plain", b"text");
+    let response = send_upload(context, &stranger, &note, body).await;
+    assert_that!(response.status(), eq(StatusCode::NOT_FOUND));
+}
+
+#[db_test]
+async fn test_delete(context: &DbTestContext) {
+    let user = create_fake_user(&context.db_pool).await;
+    let note = create_note("text", user.id, &context.db_pool).await;
+    let attachment = upload(context, &user, &note, b"%PDF-1.7").await;
+    let key = blob_key(context, &user, &attachment).await;
+    assert_that!(blob_exists(context, &key).await, eq(true));
+
+    let response = context
+        .app
+        .request(&format!("/notes/{}/attachments/{}", note.id, attachment.id))
+        .method(Method::DELETE)
+        .header(http::header::AUTHORIZATION, &context.bearer_token(&user))
+        .send()
+        .await;
+
+    assert_that!(response.status(), eq(StatusCode::NO_CONTENT));
+    assert_that!(blob_exists(context, &key).await, eq(false));
+    let attachments = attachments::load_all(note.id, user.id, &context.db_pool)
+        .await
+        .unwrap();
+    assert_that!(attachments, empty());
+}
+
+#[db_test]
+async fn test_purge_deletes_blobs(context: &DbTestContext) {
+    let user = create_fake_user(&context.db_pool).await;
+    let note = create_note("text", user.id, &context.db_pool).await;
+    let kept_note = create_note("text", user.id, &context.db_pool).await;
+    let attachment = upload(context, &user, &note, b"purged").await;
+    let kept_attachment = upload(context, &user, &kept_note, b"kept").await;
+    let key = blob_key(context, &user, &attachment).await;
+    let kept_key = blob_key(context, &user, &kept_attachment).await;
+    notes::delete(note.id, user.id, None, &context.db_pool)
+        .await
+        .unwrap();
+
+    // Trashed notes keep their attachments so that they can be restored.
+    let count = purge_trashed_notes(
+        Utc::now() - Duration::days(1),
+        &context.db_pool,
+        context.blob_store.as_ref(),
+    )
+    .await
+    .unwrap();
+    assert_that!(count, eq(0));
+    assert_that!(blob_exists(context, &key).await, eq(true));
+
+    let count = purge_trashed_notes(
+        Utc::now() + Duration::minutes(1),
+        &context.db_pool,
+        context.blob_store.as_ref(),
+    )
+    .await
+    .unwrap();
+    assert_that!(count, eq(1));
+    assert_that!(blob_exists(context, &key).await, eq(false));
+    assert_that!(blob_exists(context, &kept_key).await, eq(true));
+}
@@ -0,0 +1,164 @@
+use axum::http::{self, Method};
+use chrono::{Duration, Utc};
+use forge_api_config::AuthConfig;
+use forge_api_db::test_helpers::users::create_fake as create_fake_user;
+use forge_api_macros::db_test;
+use forge_api_web::test_helpers::{DbTestContext, RouterExt};
+use googletest::prelude::*;
+use hyper::StatusCode;
+use jwt_lib::{Claims, Jwt};
+use uuid::Uuid;
+
+async fn read_notes_with_auth(context: &DbTestContext, auth_header: &str) -> StatusCode {
+    let response = context
+        .app
+        .request("/notes")
+        .header(http::header::AUTHORIZATION, auth_header)
+        .send()
+        .await;
+
+    response.status()
+}
+
+#[db_test]
+async fn test_missing_authorization_header(context: &DbTestContext) {
+    let response = context
+        .app
+        .request(&format!("/notes/{}", Uuid::new_v4()))
+        .method(Method::DELETE)
+        .send()
+        .await;
+
+    assert_that!(response.status(), eq(StatusCode::UNAUTHORIZED));
+}
+
+#[db_test]
+async fn test_valid_token(context: &DbTestContext) {
+    let user = create_fake_user(&context.db_pool).await;
+
+    let status = read_notes_with_auth(context, &context.bearer_token(&user)).await;
+
+    assert_that!(status, eq(StatusCode::OK));
+}
+
+#[db_test]
+async fn test_token_without_bearer_scheme(context: &DbTestContext) {
+    let user = create_fake_user(&context.db_pool).await;
+    let token = context.jwt.get_jwt(&user).unwrap();
+
+    let status = read_notes_with_auth(context, &token).await;
+
+    assert_that!(status, eq(StatusCode::UNAUTHORIZED));
+}
+
+#[db_test]
+async fn test_malformed_token(context: &DbTestContext) {
+    let status = read_notes_with_auth(context, "Bearer not-a-jwt").await;
+
+    assert_that!(status, eq(StatusCode::UNAUTHORIZED));
+}
+
+#[db_test]
+async fn test_expired_token(context: &DbTestContext) {
+    let user = create_fake_user(&context.db_pool).await;
+    let token = context
+        .jwt
+        .encode_claims(&Claims {
+            sub: user.id,
+            name: user.name,
+            jti: Uuid::new_v4(),
+            iat: (Utc::now() - Duration::hours(2)).timestamp(),
+            generation: user.token_generation,
+            exp: (Utc::now() - Duration::hours(1)).timestamp(),
+            iss: None,
+            aud: None,
+        })
+        .unwrap();
+
+    let status = read_notes_with_auth(context, &format!("Bearer {}", token)).await;
+
+    assert_that!(status, eq(StatusCode::UNAUTHORIZED));
+}
+
+#[db_test]
+async fn test_tampered_token(context: &DbTestContext) {
+    let user = create_fake_user(&context.db_pool).await;
+    let token = context.jwt.get_jwt(&user).unwrap();
+
+    let (payload, signature) = token.rsplit_once('.').unwrap();
+    let mut signature = signature.to_string();
+    let first = if signature.starts_with('A') { "B" } else { "A" };
+    signature.replace_range(0..1, first);
+
+    let status = read_notes_with_auth(context, &format!("Bearer {}.{}", payload, signature)).await;
+
+    assert_that!(status, eq(StatusCode::UNAUTHORIZED));
+}
+
+#[db_test]
+async fn test_token_signed_with_other_secret(context: &DbTestContext) {
+    let user = create_fake_user(&context.db_pool).await;
+    let other_jwt = Jwt::from_config(&AuthConfig {
+        secret: Some(String::from("some-other-secret")),
+        ..AuthConfig::default()
+    })
+    .unwrap();
+    let token = other_jwt.get_jwt(&user).unwrap();
+
+    let status = read_notes_with_auth(context, &format!("Bearer {}", token)).await;
+
+    assert_that!(status, eq(StatusCode::UNAUTHORIZED));
+}
+
+#[db_test]
+async fn test_token_for_unknown_user(context: &DbTestContext) {
+    let token = context
+        .jwt
+        .encode_claims(&Claims {
+            sub: Uuid::new_v4(),
+            name: String::from("nobody"),
+            jti: Uuid::new_v4(),
+            iat: Utc::now().timestamp(),
+            generation: 0,
+            exp: (Utc::now() + Duration::minutes(2)).timestamp(),
+            iss: None,
+            aud: None,
+        })
+        .unwrap();
+
+    let status = read_notes_with_auth(context, &format!("Bearer {}", token)).await;
+
+    assert_that!(status, eq(StatusCode::UNAUTHORIZED));
+}
+
+#[db_test]
+async fn test_token_without_configured_issuer_and_audience(context: &DbTestContext) {
+    let user = create_fake_user(&context.db_pool).await;
+    let config = AuthConfig {
+        secret: Some(String::from("shared-secret")),
+        ..AuthConfig::default()
+    };
+    let token = Jwt::from_config(&config).unwrap().get_jwt(&user).unwrap();
+
+    for (issuer, audience) in [(Some("forge-api"), None), (None, Some("forge-clients"))] {
+        let jwt = Jwt::from_config(&AuthConfig {
+            issuer: issuer.map(String::from),
+            audience: audience.map(String::from),
+            ..config.clone()
+        })
+        .unwrap();
+
+        assert_that!(jwt.decode_jwt(&token), err(anything()));
+    }
+}
+
+#[test]
+fn test_access_token_ttl_out_of_range() {
+    let result = Jwt::from_config(&AuthConfig {
+        secret: Some(String::from("shared-secret")),
+        access_token_ttl_secs: u64::MAX,
+        ..AuthConfig::default()
+    });
+
+    assert_that!(result.is_err(), eq(true));
+}
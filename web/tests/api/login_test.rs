@@ -0,0 +1,154 @@
+use axum::{
+    body::Body,
+    http::{self, Method},
+};
+use fake::{Fake, Faker};
+use forge_api_db::test_helpers::users::{create as create_user, UserChangeset};
+use forge_api_macros::db_test;
+use forge_api_web::auth::{LoginRequest, TokenResponse};
+use forge_api_web::test_helpers::{BodyExt, DbTestContext, RouterExt};
+use googletest::prelude::*;
+use hyper::StatusCode;
+use serde_json::{json, Value};
+
+const PASSWORD: &str = "correct horse battery staple";
+
+async fn login(context: &DbTestContext, payload: Value) -> axum::response::Response {
+    context
+        .app
+        .request("/login")
+        .method(Method::POST)
+        .body(Body::from(payload.to_string()))
+        .header(http::header::CONTENT_TYPE, "application/json")
+        .send()
+        .await
+}
+
+#[db_test]
+async fn test_login_success(context: &DbTestContext) {
+    let user_changeset = UserChangeset {
+        pass: bcrypt::hash(PASSWORD, 4).unwrap(),
+        ..Faker.fake()
+    };
+    let user = create_user(user_changeset, &context.db_pool).await.unwrap();
+
+    let response = login(
+        context,
+        json!(LoginRequest {
+            name: user.name.clone(),
+            password: String::from(PASSWORD),
+        }),
+    )
+    .await;
+
+    assert_that!(response.status(), eq(StatusCode::OK));
+    let tokens: TokenResponse = response.into_body().into_json().await;
+    let claims = context.jwt.decode_jwt(&tokens.token).unwrap();
+    assert_that!(claims.sub, eq(user.id));
+    assert_that!(tokens.refresh_token, not(eq("")));
+}
+
+#[db_test]
+async fn test_login_name_is_case_insensitive(context: &DbTestContext) {
+    let user_changeset = UserChangeset {
+        name: String::from("Jane.Doe"),
+        pass: bcrypt::hash(PASSWORD, 4).unwrap(),
+        ..Faker.fake()
+    };
+    create_user(user_changeset, &context.db_pool).await.unwrap();
+
+    let response = login(
+        context,
+        json!(LoginRequest {
+            name: String::from("jane.doe"),
+            password: String::from(PASSWORD),
+        }),
+    )
+    .await;
+
+    assert_that!(response.status(), eq(StatusCode::OK));
+}
+
+#[db_test]
+async fn test_login_wrong_password(context: &DbTestContext) {
+    let user_changeset = UserChangeset {
+        pass: bcrypt::hash(PASSWORD, 4).unwrap(),
+        ..Faker.fake()
+    };
+    let user = create_user(user_changeset, &context.db_pool).await.unwrap();
+
+    let response = login(
+        context,
+        json!(LoginRequest {
+            name: user.name,
+            password: String::from("wrong password"),
+        }),
+    )
+    .await;
+
+    assert_that!(response.status(), eq(StatusCode::UNAUTHORIZED));
+}
+
+#[db_test]
+async fn test_login_with_malformed_bcrypt_hash(context: &DbTestContext) {
+    let user_changeset = UserChangeset {
+        pass: String::from("$2b$04$not-a-bcrypt-hash"),
+        ..Faker.fake()
+    };
+    let user = create_user(user_changeset, &context.db_pool).await.unwrap();
+
+    let response = login(
+        context,
+        json!(LoginRequest {
+            name: user.name,
+            password: String::from(PASSWORD),
+        }),
+    )
+    .await;
+
+    assert_that!(response.status(), eq(StatusCode::UNAUTHORIZED));
+}
+
+#[db_test]
+async fn test_login_unknown_user(context: &DbTestContext) {
+    let response = login(
+        context,
+        json!(LoginRequest {
+            name: String::from("nobody"),
+            password: String::from(PASSWORD),
+        }),
+    )
+    .await;
+
+    assert_that!(response.status(), eq(StatusCode::UNAUTHORIZED));
+}
+
+#[db_test]
+async fn test_login_invalid(context: &DbTestContext) {
+    let response = login(
+        context,
+        json!(LoginRequest {
+            name: String::from(""),
+            password: String::from(PASSWORD),
+        }),
+    )
+    .await;
+    assert_that!(response.status(), eq(StatusCode::UNPROCESSABLE_ENTITY));
+
+    let response = login(context, json!({ "name": "someone" })).await;
+    assert_that!(response.status(), eq(StatusCode::UNPROCESSABLE_ENTITY));
+}
+
+#[db_test]
+async fn test_openapi_documents_auth_endpoints(context: &DbTestContext) {
+    let response = context.app.request("/api-docs/openapi.json").send().await;
+
+    assert_that!(response.status(), eq(StatusCode::OK));
+    let openapi: Value = response.into_body().into_json().await;
+    assert_that!(openapi["paths"]["/login"]["post"], not(eq(&Value::Null)));
+    assert_that!(openapi["paths"]["/register"]["post"], not(eq(&Value::Null)));
+    assert_that!(
+        openapi["components"]["schemas"]["TokenResponse"],
+        not(eq(&Value::Null))
+    );
+}
@@ -0,0 +1,160 @@
+use axum::{
+    body::Body,
+    http::{self, Method},
+};
+use fake::{Fake, Faker};
+use forge_api_db::entities::users::User;
+use forge_api_db::test_helpers::login_failures::unlock_all;
+use forge_api_db::test_helpers::users::{create as create_user, UserChangeset};
+use forge_api_macros::db_test;
+use forge_api_web::auth::LoginRequest;
+use forge_api_web::test_helpers::{DbTestContext, RouterExt, TEST_CLIENT_ADDR};
+use googletest::prelude::*;
+use hyper::StatusCode;
+use serde_json::json;
+use std::net::SocketAddr;
+
+const PASSWORD: &str = "correct horse battery staple";
+
+async fn create_test_user(context: &DbTestContext) -> User {
+    let user_changeset = UserChangeset {
+        pass: bcrypt::hash(PASSWORD, 4).unwrap(),
+        ..Faker.fake()
+    };
+    create_user(user_changeset, &context.db_pool).await.unwrap()
+}
+
+async fn login_from(
+    context: &DbTestContext,
+    client_addr: SocketAddr,
+    name: &str,
+    password: &str,
+) -> axum::response::Response {
+    let payload = json!(LoginRequest {
+        name: String::from(name),
+        password: String::from(password),
+    });
+
+    context
+        .app
+        .request("/login")
+        .method(Method::POST)
+        .body(Body::from(payload.to_string()))
+        .header(http::header::CONTENT_TYPE, "application/json")
+        .client_addr(client_addr)
+        .send()
+        .await
+}
+
+async fn login(context: &DbTestContext, name: &str, password: &str) -> axum::response::Response {
+    login_from(context, TEST_CLIENT_ADDR, name, password).await
+}
+
+fn retry_after(response: &axum::response::Response) -> u64 {
+    response
+        .headers()
+        .get(http::header::RETRY_AFTER)
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .parse()
+        .unwrap()
+}
+
+#[db_test]
+async fn test_account_is_locked_out_after_failed_logins(context: &DbTestContext) {
+    let user = create_test_user(context).await;
+
+    for i in 0..5 {
+        // Names are case-insensitive, so failures count for the same account regardless of case.
+        let name = if i % 2 == 0 {
+            user.name.to_uppercase()
+        } else {
+            user.name.clone()
+        };
+        let response = login(context, &name, "wrong password").await;
+        assert_that!(response.status(), eq(StatusCode::UNAUTHORIZED));
+    }
+
+    let response = login(context, &user.name, PASSWORD).await;
+    assert_that!(response.status(), eq(StatusCode::TOO_MANY_REQUESTS));
+    assert_that!(retry_after(&response), all!(ge(1), le(30)));
+
+    // The lockout applies to the account regardless of the client's address.
+    let other_addr = SocketAddr::from(([192, 0, 2, 1], 4711));
+    let response = login_from(context, other_addr, &user.name, PASSWORD).await;
+    assert_that!(response.status(), eq(StatusCode::TOO_MANY_REQUESTS));
+}
+
+#[db_test]
+async fn test_successful_login_after_lockout_resets_failures(context: &DbTestContext) {
+    let user = create_test_user(context).await;
+    for _ in 0..5 {
+        login(context, &user.name, "wrong password").await;
+    }
+    unlock_all(&context.db_pool).await.unwrap();
+
+    let response = login(context, &user.name, PASSWORD).await;
+    assert_that!(response.status(), eq(StatusCode::OK));
+
+    for _ in 0..4 {
+        let response = login(context, &user.name, "wrong password").await;
+        assert_that!(response.status(), eq(StatusCode::UNAUTHORIZED));
+    }
+    let response = login(context, &user.name, PASSWORD).await;
+    assert_that!(response.status(), eq(StatusCode::OK));
+}
+
+#[db_test]
+async fn test_lockouts_grow_exponentially(context: &DbTestContext) {
+    let user = create_test_user(context).await;
+    for _ in 0..5 {
+        login(context, &user.name, "wrong password").await;
+    }
+    unlock_all(&context.db_pool).await.unwrap();
+
+    let response = login(context, &user.name, "wrong password").await;
+    assert_that!(response.status(), eq(StatusCode::UNAUTHORIZED));
+
+    let response = login(context, &user.name, PASSWORD).await;
+    assert_that!(response.status(), eq(StatusCode::TOO_MANY_REQUESTS));
+    assert_that!(retry_after(&response), all!(gt(30), le(60)));
+}
+
+#[db_test]
+async fn test_unknown_names_are_locked_out_like_accounts(context: &DbTestContext) {
+    for _ in 0..5 {
+        let response = login(context, "no.such.user", "wrong password").await;
+        assert_that!(response.status(), eq(StatusCode::UNAUTHORIZED));
+    }
+
+    let response = login(context, "no.such.user", "wrong password").await;
+    assert_that!(response.status(), eq(StatusCode::TOO_MANY_REQUESTS));
+}
+
+#[db_test]
+async fn test_ip_address_is_locked_out_after_failed_logins(context: &DbTestContext) {
+    let user = create_test_user(context).await;
+    let attacker_addr = SocketAddr::from(([198, 51, 100, 7], 4711));
+
+    for i in 0..20 {
+        let response = login_from(
+            context,
+            attacker_addr,
+            &format!("guessed.user.{i}"),
+            "wrong password",
+        )
+        .await;
+        assert_that!(response.status(), eq(StatusCode::UNAUTHORIZED));
+    }
+
+    let response = login_from(context, attacker_addr, &user.name, PASSWORD).await;
+    assert_that!(response.status(), eq(StatusCode::TOO_MANY_REQUESTS));
+
+    let response = login(context, &user.name, PASSWORD).await;
+    assert_that!(response.status(), eq(StatusCode::OK));
+
+    // A successful login for an account doesn't lift the lockout of the address.
+    let response = login_from(context, attacker_addr, &user.name, PASSWORD).await;
+    assert_that!(response.status(), eq(StatusCode::TOO_MANY_REQUESTS));
+}
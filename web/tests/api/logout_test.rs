@@ -0,0 +1,195 @@
+use axum::{
+    body::Body,
+    http::{self, Method},
+};
+use chrono::{Duration, Utc};
+use forge_api_db::entities::{refresh_tokens, revoked_tokens, users};
+use forge_api_db::test_helpers::users::create_fake as create_fake_user;
+use forge_api_macros::db_test;
+use forge_api_web::test_helpers::{DbTestContext, RouterExt};
+use forge_api_web::tokens;
+use googletest::prelude::*;
+use hyper::StatusCode;
+use serde_json::json;
+use uuid::Uuid;
+
+async fn read_notes_as(context: &DbTestContext, auth_header: &str) -> StatusCode {
+    let response = context
+        .app
+        .request("/notes")
+        .header(http::header::AUTHORIZATION, auth_header)
+        .send()
+        .await;
+
+    response.status()
+}
+
+#[db_test]
+async fn test_logout_requires_authentication(context: &DbTestContext) {
+    let response = context
+        .app
+        .request("/logout")
+        .method(Method::POST)
+        .send()
+        .await;
+
+    assert_that!(response.status(), eq(StatusCode::UNAUTHORIZED));
+}
+
+#[db_test]
+async fn test_logout_revokes_current_token(context: &DbTestContext) {
+    let user = create_fake_user(&context.db_pool).await;
+    let token = context.bearer_token(&user);
+    let other_token = context.bearer_token(&user);
+
+    let response = context
+        .app
+        .request("/logout")
+        .method(Method::POST)
+        .header(http::header::AUTHORIZATION, &token)
+        .send()
+        .await;
+    assert_that!(response.status(), eq(StatusCode::NO_CONTENT));
+
+    assert_that!(
+        read_notes_as(context, &token).await,
+        eq(StatusCode::UNAUTHORIZED)
+    );
+    assert_that!(
+        read_notes_as(context, &other_token).await,
+        eq(StatusCode::OK)
+    );
+}
+
+#[db_test]
+async fn test_logout_revokes_passed_refresh_token(context: &DbTestContext) {
+    let user = create_fake_user(&context.db_pool).await;
+    let refresh_token = tokens::generate();
+    let stored = refresh_tokens::create(
+        user.id,
+        Uuid::new_v4(),
+        &tokens::hash(&refresh_token),
+        Utc::now() + Duration::days(1),
+        &context.db_pool,
+    )
+    .await
+    .unwrap();
+
+    let response = context
+        .app
+        .request("/logout")
+        .method(Method::POST)
+        .body(Body::from(
+            json!({ "refresh_token": refresh_token }).to_string(),
+        ))
+        .header(http::header::CONTENT_TYPE, "application/json")
+        .header(http::header::AUTHORIZATION, &context.bearer_token(&user))
+        .send()
+        .await;
+    assert_that!(response.status(), eq(StatusCode::NO_CONTENT));
+
+    let stored = refresh_tokens::load_by_hash(&stored.token_hash, &context.db_pool)
+        .await
+        .unwrap();
+    assert_that!(stored.revoked_at, some(anything()));
+}
+
+#[db_test]
+async fn test_logout_all_revokes_all_tokens(context: &DbTestContext) {
+    let user = create_fake_user(&context.db_pool).await;
+    let token = context.bearer_token(&user);
+    let other_token = context.bearer_token(&user);
+    let stored = refresh_tokens::create(
+        user.id,
+        Uuid::new_v4(),
+        &tokens::hash(&tokens::generate()),
+        Utc::now() + Duration::days(1),
+        &context.db_pool,
+    )
+    .await
+    .unwrap();
+
+    let response = context
+        .app
+        .request("/logout/all")
+        .method(Method::POST)
+        .header(http::header::AUTHORIZATION, &token)
+        .send()
+        .await;
+    assert_that!(response.status(), eq(StatusCode::NO_CONTENT));
+
+    assert_that!(
+        read_notes_as(context, &token).await,
+        eq(StatusCode::UNAUTHORIZED)
+    );
+    assert_that!(
+        read_notes_as(context, &other_token).await,
+        eq(StatusCode::UNAUTHORIZED)
+    );
+    let stored = refresh_tokens::load_by_hash(&stored.token_hash, &context.db_pool)
+        .await
+        .unwrap();
+    assert_that!(stored.revoked_at, some(anything()));
+}
+
+#[db_test]
+async fn test_token_issued_right_after_logout_all(context: &DbTestContext) {
+    let user = create_fake_user(&context.db_pool).await;
+
+    let response = context
+        .app
+        .request("/logout/all")
+        .method(Method::POST)
+        .header(http::header::AUTHORIZATION, &context.bearer_token(&user))
+        .send()
+        .await;
+    assert_that!(response.status(), eq(StatusCode::NO_CONTENT));
+
+    // A new session started within the same second as logging out everywhere isn't revoked.
+    let user = users::load(user.id, &context.db_pool)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_that!(
+        read_notes_as(context, &context.bearer_token(&user)).await,
+        eq(StatusCode::OK)
+    );
+}
+
+#[db_test]
+async fn test_delete_expired_revocations(context: &DbTestContext) {
+    let expired_jti = Uuid::new_v4();
+    let active_jti = Uuid::new_v4();
+    revoked_tokens::revoke(
+        expired_jti,
+        Utc::now() - Duration::minutes(1),
+        &context.db_pool,
+    )
+    .await
+    .unwrap();
+    revoked_tokens::revoke(
+        active_jti,
+        Utc::now() + Duration::minutes(1),
+        &context.db_pool,
+    )
+    .await
+    .unwrap();
+
+    let count = revoked_tokens::delete_expired(&context.db_pool)
+        .await
+        .unwrap();
+
+    assert_that!(count, eq(1));
+    assert_that!(
+        revoked_tokens::is_revoked(expired_jti, &context.db_pool)
+            .await
+            .unwrap(),
+        eq(false)
+    );
+    assert_that!(
+        revoked_tokens::is_revoked(active_jti, &context.db_pool)
+            .await
+            .unwrap(),
+        eq(true)
+    );
+}
@@ -1,3 +1,25 @@
 #![allow(missing_docs)]
 
+mod account_emails_test;
+mod api_keys_test;
+mod attachments_test;
+mod auth_test;
+mod login_test;
+mod login_throttling_test;
+mod logout_test;
+mod migrations_test;
+mod note_links_test;
+mod note_revisions_test;
+mod note_shares_test;
+mod notes_batch_test;
+mod notes_html_test;
+mod notes_patch_test;
+mod notes_search_test;
 mod notes_test;
+mod notes_trash_test;
+mod passwords_test;
+mod permissions_test;
+mod refresh_token_test;
+mod register_test;
+mod tags_test;
+mod two_factor_test;
@@ -0,0 +1,100 @@
+use forge_api_config::{load_config, Config, Environment};
+use forge_api_db::test_helpers::{migrate_to, setup_empty_db, teardown_db};
+use forge_api_db::DbPool;
+use googletest::prelude::*;
+
+async fn setup() -> DbPool {
+    let config: Config = load_config(&Environment::Test).unwrap();
+    setup_empty_db(&config.database).await
+}
+
+async fn insert_user(name: &str, db_pool: &DbPool) {
+    sqlx::query("INSERT INTO users (name, token, pass) VALUES ($1, '', '')")
+        .bind(name)
+        .execute(db_pool)
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_unique_user_names_renames_names_differing_by_case() {
+    let db_pool = setup().await;
+    migrate_to(1750197148, &db_pool).await;
+    for name in ["jane", "Jane", "JANE", "john"] {
+        insert_user(name, &db_pool).await;
+    }
+
+    migrate_to(1760000200, &db_pool).await;
+
+    let names: Vec<String> = sqlx::query_scalar("SELECT name FROM users")
+        .fetch_all(&db_pool)
+        .await
+        .unwrap();
+    assert_that!(
+        names,
+        unordered_elements_are![
+            eq("JANE"),
+            matches_regex("^Jane-[0-9a-f]{8}$"),
+            matches_regex("^jane-[0-9a-f]{8}$"),
+            eq("john"),
+        ]
+    );
+
+    teardown_db(db_pool).await;
+}
+
+#[tokio::test]
+async fn test_add_owner_to_notes_keeps_existing_notes() {
+    let db_pool = setup().await;
+    migrate_to(1760000200, &db_pool).await;
+    insert_user("jane", &db_pool).await;
+    for text in ["first note", "second note"] {
+        sqlx::query("INSERT INTO notes (text) VALUES ($1)")
+            .bind(text)
+            .execute(&db_pool)
+            .await
+            .unwrap();
+    }
+
+    migrate_to(1760000300, &db_pool).await;
+
+    let notes: Vec<(String, String)> = sqlx::query_as(
+        "SELECT notes.text, users.name FROM notes JOIN users ON users.id = notes.owner_id",
+    )
+    .fetch_all(&db_pool)
+    .await
+    .unwrap();
+    assert_that!(
+        notes,
+        unordered_elements_are![
+            (
+                eq("first note"),
+                matches_regex("^legacy-notes-[0-9a-f]{8}$")
+            ),
+            (
+                eq("second note"),
+                matches_regex("^legacy-notes-[0-9a-f]{8}$")
+            ),
+        ]
+    );
+    assert_that!(notes[0].1, eq(&notes[1].1));
+
+    teardown_db(db_pool).await;
+}
+
+#[tokio::test]
+async fn test_add_owner_to_notes_without_existing_notes() {
+    let db_pool = setup().await;
+    migrate_to(1760000200, &db_pool).await;
+    insert_user("jane", &db_pool).await;
+
+    migrate_to(1760000300, &db_pool).await;
+
+    let names: Vec<String> = sqlx::query_scalar("SELECT name FROM users")
+        .fetch_all(&db_pool)
+        .await
+        .unwrap();
+    assert_that!(names, elements_are![eq("jane")]);
+
+    teardown_db(db_pool).await;
+}
@@ -0,0 +1,239 @@
+use axum::{
+    body::Body,
+    http::{self, Method},
+};
+use chrono::{Duration, Utc};
+use forge_api_db::entities::note_links::{LinkedNote, NoteLink};
+use forge_api_db::entities::notes::{self, Note, NoteChangeset, NoteFormat};
+use forge_api_db::entities::users::User;
+use forge_api_db::test_helpers::note_links::expire;
+use forge_api_db::test_helpers::users::create_fake as create_fake_user;
+use forge_api_macros::db_test;
+use forge_api_web::controllers::note_links::CreatedNoteLink;
+use forge_api_web::test_helpers::{BodyExt, DbTestContext, RouterExt};
+use googletest::prelude::*;
+use hyper::StatusCode;
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+async fn create_note(context: &DbTestContext, user: &User) -> Note {
+    let changeset = NoteChangeset {
+        text: String::from("*text*"),
+        tags: Some(vec![String::from("private")]),
+        format: Some(NoteFormat::Markdown),
+    };
+    notes::create(changeset, user.id, &context.db_pool)
+        .await
+        .unwrap()
+}
+
+async fn send_create(
+    context: &DbTestContext,
+    user: &User,
+    note: &Note,
+    payload: Value,
+) -> axum::response::Response {
+    context
+        .app
+        .request(&format!("/notes/{}/links", note.id))
+        .method(Method::POST)
+        .body(Body::from(payload.to_string()))
+        .header(http::header::CONTENT_TYPE, "application/json")
+        .header(http::header::AUTHORIZATION, &context.bearer_token(user))
+        .send()
+        .await
+}
+
+async fn create_link(
+    context: &DbTestContext,
+    user: &User,
+    note: &Note,
+    payload: Value,
+) -> CreatedNoteLink {
+    let response = send_create(context, user, note, payload).await;
+    assert_that!(response.status(), eq(StatusCode::CREATED));
+
+    response.into_body().into_json().await
+}
+
+async fn send_view(context: &DbTestContext, token: &str) -> axum::response::Response {
+    context.app.request(&format!("/s/{}", token)).send().await
+}
+
+#[db_test]
+async fn test_create_and_view(context: &DbTestContext) {
+    let user = create_fake_user(&context.db_pool).await;
+    let note = create_note(context, &user).await;
+
+    let created = create_link(context, &user, &note, json!({})).await;
+    assert_that!(created.token.len(), eq(64));
+    assert_that!(created.link.note_id, eq(note.id));
+    assert_that!(created.link.view_count, eq(0));
+
+    let response = send_view(context, &created.token).await;
+    assert_that!(response.status(), eq(StatusCode::OK));
+    let body: Value = response.into_body().into_json().await;
+    assert_that!(body.get("owner_id"), none());
+    assert_that!(body.get("tags"), none());
+    let linked: LinkedNote = serde_json::from_value(body).unwrap();
+    assert_that!(linked.id, eq(note.id));
+    assert_that!(linked.text, eq("*text*"));
+
+    let response = context
+        .app
+        .request(&format!("/s/{}", created.token))
+        .header(http::header::ACCEPT, "text/html")
+        .send()
+        .await;
+    assert_that!(response.status(), eq(StatusCode::OK));
+    let body = response.into_body().into_bytes().await;
+    assert_that!(
+        String::from_utf8(body.to_vec()).unwrap(),
+        eq("<p><em>text</em></p>\n")
+    );
+
+    let response = context
+        .app
+        .request(&format!("/notes/{}/links", note.id))
+        .header(http::header::AUTHORIZATION, &context.bearer_token(&user))
+        .send()
+        .await;
+    assert_that!(response.status(), eq(StatusCode::OK));
+    let body: Value = response.into_body().into_json().await;
+    assert_that!(body[0].get("token"), none());
+    let links: Vec<NoteLink> = serde_json::from_value(body).unwrap();
+    assert_that!(
+        links,
+        elements_are![matches_pattern!(NoteLink {
+            id: eq(&created.link.id),
+            view_count: eq(&2),
+            ..
+        })]
+    );
+}
+
+#[db_test]
+async fn test_view_unknown_token(context: &DbTestContext) {
+    let response = send_view(context, &"0".repeat(64)).await;
+
+    assert_that!(response.status(), eq(StatusCode::NOT_FOUND));
+}
+
+#[db_test]
+async fn test_view_limit(context: &DbTestContext) {
+    let user = create_fake_user(&context.db_pool).await;
+    let note = create_note(context, &user).await;
+    let created = create_link(context, &user, &note, json!({ "max_views": 2 })).await;
+
+    for _ in 0..2 {
+        let response = send_view(context, &created.token).await;
+        assert_that!(response.status(), eq(StatusCode::OK));
+    }
+
+    let response = send_view(context, &created.token).await;
+    assert_that!(response.status(), eq(StatusCode::NOT_FOUND));
+}
+
+#[db_test]
+async fn test_expiry(context: &DbTestContext) {
+    let user = create_fake_user(&context.db_pool).await;
+    let note = create_note(context, &user).await;
+    let expires_at = Utc::now() + Duration::hours(1);
+    let created = create_link(context, &user, &note, json!({ "expires_at": expires_at })).await;
+
+    let response = send_view(context, &created.token).await;
+    assert_that!(response.status(), eq(StatusCode::OK));
+
+    expire(created.link.id, &context.db_pool).await.unwrap();
+
+    let response = send_view(context, &created.token).await;
+    assert_that!(response.status(), eq(StatusCode::NOT_FOUND));
+}
+
+#[db_test]
+async fn test_create_invalid(context: &DbTestContext) {
+    let user = create_fake_user(&context.db_pool).await;
+    let note = create_note(context, &user).await;
+    let past = Utc::now() - Duration::minutes(1);
+
+    for payload in [json!({ "expires_at": past }), json!({ "max_views": 0 })] {
+        let response = send_create(context, &user, &note, payload).await;
+        assert_that!(response.status(), eq(StatusCode::UNPROCESSABLE_ENTITY));
+    }
+}
+
+#[db_test]
+async fn test_create_for_other_users_note(context: &DbTestContext) {
+    let user = create_fake_user(&context.db_pool).await;
+    let other_user = create_fake_user(&context.db_pool).await;
+    let note = create_note(context, &other_user).await;
+
+    let response = send_create(context, &user, &note, json!({})).await;
+
+    assert_that!(response.status(), eq(StatusCode::NOT_FOUND));
+}
+
+#[db_test]
+async fn test_read_all_for_other_users_note(context: &DbTestContext) {
+    let user = create_fake_user(&context.db_pool).await;
+    let other_user = create_fake_user(&context.db_pool).await;
+    let note = create_note(context, &other_user).await;
+    create_link(context, &other_user, &note, json!({})).await;
+
+    for id in [note.id, Uuid::new_v4()] {
+        let response = context
+            .app
+            .request(&format!("/notes/{}/links", id))
+            .header(http::header::AUTHORIZATION, &context.bearer_token(&user))
+            .send()
+            .await;
+        assert_that!(response.status(), eq(StatusCode::NOT_FOUND));
+    }
+}
+
+#[db_test]
+async fn test_revoke(context: &DbTestContext) {
+    let user = create_fake_user(&context.db_pool).await;
+    let other_user = create_fake_user(&context.db_pool).await;
+    let note = create_note(context, &user).await;
+    let created = create_link(context, &user, &note, json!({})).await;
+    let path = format!("/notes/{}/links/{}", note.id, created.link.id);
+
+    let response = context
+        .app
+        .request(&path)
+        .method(Method::DELETE)
+        .header(
+            http::header::AUTHORIZATION,
+            &context.bearer_token(&other_user),
+        )
+        .send()
+        .await;
+    assert_that!(response.status(), eq(StatusCode::NOT_FOUND));
+
+    let response = context
+        .app
+        .request(&path)
+        .method(Method::DELETE)
+        .header(http::header::AUTHORIZATION, &context.bearer_token(&user))
+        .send()
+        .await;
+    assert_that!(response.status(), eq(StatusCode::NO_CONTENT));
+
+    let response = send_view(context, &created.token).await;
+    assert_that!(response.status(), eq(StatusCode::NOT_FOUND));
+}
+
+#[db_test]
+async fn test_view_trashed_note(context: &DbTestContext) {
+    let user = create_fake_user(&context.db_pool).await;
+    let note = create_note(context, &user).await;
+    let created = create_link(context, &user, &note, json!({})).await;
+
+    notes::delete(note.id, user.id, None, &context.db_pool)
+        .await
+        .unwrap();
+
+    let response = send_view(context, &created.token).await;
+    assert_that!(response.status(), eq(StatusCode::NOT_FOUND));
+}
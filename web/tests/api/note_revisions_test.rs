@@ -0,0 +1,299 @@
+use axum::{
+    body::Body,
+    http::{self, Method},
+};
+use forge_api_db::entities::note_revisions::NoteRevision;
+use forge_api_db::entities::note_shares::{self, NoteShareChangeset, SharePermission};
+use forge_api_db::entities::notes::{self, Note};
+use forge_api_db::entities::users::User;
+use forge_api_db::test_helpers::notes::create as create_note;
+use forge_api_db::test_helpers::users::create_fake as create_fake_user;
+use forge_api_macros::db_test;
+use forge_api_web::controllers::note_revisions::{LineChange, RevisionDiff};
+use forge_api_web::test_helpers::{BodyExt, DbTestContext, RouterExt};
+use googletest::prelude::*;
+use hyper::StatusCode;
+use serde_json::json;
+use uuid::Uuid;
+
+async fn update_note(
+    context: &DbTestContext,
+    user: &User,
+    id: Uuid,
+    text: &str,
+    if_match: &str,
+) -> StatusCode {
+    let response = context
+        .app
+        .request(&format!("/notes/{}", id))
+        .method(Method::PUT)
+        .body(Body::from(json!({ "text": text }).to_string()))
+        .header(http::header::CONTENT_TYPE, "application/json")
+        .header(http::header::IF_MATCH, if_match)
+        .header(http::header::AUTHORIZATION, &context.bearer_token(user))
+        .send()
+        .await;
+
+    response.status()
+}
+
+async fn request(
+    context: &DbTestContext,
+    user: &User,
+    method: Method,
+    uri: &str,
+) -> axum::response::Response {
+    context
+        .app
+        .request(uri)
+        .method(method)
+        .header(http::header::AUTHORIZATION, &context.bearer_token(user))
+        .send()
+        .await
+}
+
+#[db_test]
+async fn test_update_records_revisions(context: &DbTestContext) {
+    let user = create_fake_user(&context.db_pool).await;
+    let note = create_note("first", user.id, &context.db_pool).await;
+    update_note(context, &user, note.id, "second", "*").await;
+    update_note(context, &user, note.id, "third", "*").await;
+
+    let response = request(
+        context,
+        &user,
+        Method::GET,
+        &format!("/notes/{}/revisions", note.id),
+    )
+    .await;
+
+    assert_that!(response.status(), eq(StatusCode::OK));
+    let revisions: Vec<NoteRevision> = response.into_body().into_json().await;
+    let revisions: Vec<(i32, String)> = revisions
+        .into_iter()
+        .map(|revision| (revision.version, revision.text))
+        .collect();
+    assert_that!(
+        revisions,
+        eq(&vec![
+            (2, String::from("second")),
+            (1, String::from("first"))
+        ])
+    );
+}
+
+#[db_test]
+async fn test_failed_update_records_no_revision(context: &DbTestContext) {
+    let user = create_fake_user(&context.db_pool).await;
+    let note = create_note("first", user.id, &context.db_pool).await;
+
+    let status = update_note(context, &user, note.id, "second", "\"7\"").await;
+    assert_that!(status, eq(StatusCode::PRECONDITION_FAILED));
+    let status = update_note(context, &user, note.id, "", "*").await;
+    assert_that!(status, eq(StatusCode::UNPROCESSABLE_ENTITY));
+
+    let response = request(
+        context,
+        &user,
+        Method::GET,
+        &format!("/notes/{}/revisions", note.id),
+    )
+    .await;
+    let revisions: Vec<NoteRevision> = response.into_body().into_json().await;
+    assert_that!(revisions, empty());
+}
+
+#[db_test]
+async fn test_revisions_of_other_users_notes(context: &DbTestContext) {
+    let user = create_fake_user(&context.db_pool).await;
+    let other_user = create_fake_user(&context.db_pool).await;
+    let note = create_note("first", other_user.id, &context.db_pool).await;
+    update_note(context, &other_user, note.id, "second", "*").await;
+
+    for uri in [
+        format!("/notes/{}/revisions", note.id),
+        format!("/notes/{}/revisions/1/diff", note.id),
+    ] {
+        let response = request(context, &user, Method::GET, &uri).await;
+        assert_that!(response.status(), eq(StatusCode::NOT_FOUND));
+    }
+
+    let response = request(
+        context,
+        &user,
+        Method::POST,
+        &format!("/notes/{}/revisions/1/revert", note.id),
+    )
+    .await;
+    assert_that!(response.status(), eq(StatusCode::NOT_FOUND));
+}
+
+#[db_test]
+async fn test_revisions_of_shared_notes(context: &DbTestContext) {
+    let owner = create_fake_user(&context.db_pool).await;
+    let user = create_fake_user(&context.db_pool).await;
+    let note = create_note("first", owner.id, &context.db_pool).await;
+    update_note(context, &owner, note.id, "second", "*").await;
+    let changeset = NoteShareChangeset {
+        user_name: user.name.clone(),
+        permission: SharePermission::Read,
+    };
+    note_shares::grant(note.id, owner.id, changeset, &context.db_pool)
+        .await
+        .unwrap();
+
+    let response = request(
+        context,
+        &user,
+        Method::GET,
+        &format!("/notes/{}/revisions", note.id),
+    )
+    .await;
+    assert_that!(response.status(), eq(StatusCode::OK));
+    let revisions: Vec<NoteRevision> = response.into_body().into_json().await;
+    assert_that!(
+        revisions,
+        elements_are![matches_pattern!(NoteRevision {
+            version: eq(&1),
+            text: eq("first"),
+            ..
+        })]
+    );
+
+    let response = request(
+        context,
+        &user,
+        Method::GET,
+        &format!("/notes/{}/revisions/1/diff", note.id),
+    )
+    .await;
+    assert_that!(response.status(), eq(StatusCode::OK));
+
+    let response = request(
+        context,
+        &user,
+        Method::POST,
+        &format!("/notes/{}/revisions/1/revert", note.id),
+    )
+    .await;
+    assert_that!(response.status(), eq(StatusCode::FORBIDDEN));
+}
+
+#[db_test]
+async fn test_revisions_of_unknown_note(context: &DbTestContext) {
+    let user = create_fake_user(&context.db_pool).await;
+
+    let response = request(
+        context,
+        &user,
+        Method::GET,
+        &format!("/notes/{}/revisions", Uuid::new_v4()),
+    )
+    .await;
+    assert_that!(response.status(), eq(StatusCode::NOT_FOUND));
+}
+
+#[db_test]
+async fn test_diff(context: &DbTestContext) {
+    let user = create_fake_user(&context.db_pool).await;
+    let note = create_note("milk\neggs\nbread", user.id, &context.db_pool).await;
+    update_note(context, &user, note.id, "milk\nbread\nbutter", "*").await;
+
+    let response = request(
+        context,
+        &user,
+        Method::GET,
+        &format!("/notes/{}/revisions/1/diff", note.id),
+    )
+    .await;
+
+    assert_that!(response.status(), eq(StatusCode::OK));
+    let diff: RevisionDiff = response.into_body().into_json().await;
+    assert_that!(diff.from_version, eq(1));
+    assert_that!(diff.to_version, eq(2));
+    let lines: Vec<(LineChange, String)> = diff
+        .lines
+        .into_iter()
+        .map(|line| (line.change, line.text))
+        .collect();
+    assert_that!(
+        lines,
+        eq(&vec![
+            (LineChange::Equal, String::from("milk")),
+            (LineChange::Delete, String::from("eggs")),
+            (LineChange::Equal, String::from("bread")),
+            (LineChange::Insert, String::from("butter")),
+        ])
+    );
+}
+
+#[db_test]
+async fn test_diff_unknown_revision(context: &DbTestContext) {
+    let user = create_fake_user(&context.db_pool).await;
+    let note = create_note("first", user.id, &context.db_pool).await;
+
+    let response = request(
+        context,
+        &user,
+        Method::GET,
+        &format!("/notes/{}/revisions/1/diff", note.id),
+    )
+    .await;
+
+    assert_that!(response.status(), eq(StatusCode::NOT_FOUND));
+}
+
+#[db_test]
+async fn test_revert(context: &DbTestContext) {
+    let user = create_fake_user(&context.db_pool).await;
+    let note = create_note("first", user.id, &context.db_pool).await;
+    update_note(context, &user, note.id, "second", "*").await;
+
+    let response = context
+        .app
+        .request(&format!("/notes/{}/revisions/1/revert", note.id))
+        .method(Method::POST)
+        .header(http::header::IF_MATCH, "\"2\"")
+        .header(http::header::AUTHORIZATION, &context.bearer_token(&user))
+        .send()
+        .await;
+
+    assert_that!(response.status(), eq(StatusCode::OK));
+    assert_that!(response.headers()[http::header::ETAG], eq("\"3\""));
+    let reverted: Note = response.into_body().into_json().await;
+    assert_that!(reverted.text, eq("first"));
+    assert_that!(reverted.version, eq(3));
+
+    let response = request(
+        context,
+        &user,
+        Method::GET,
+        &format!("/notes/{}/revisions", note.id),
+    )
+    .await;
+    let revisions: Vec<NoteRevision> = response.into_body().into_json().await;
+    assert_that!(revisions, len(eq(2)));
+    assert_that!(revisions[0].text, eq("second"));
+}
+
+#[db_test]
+async fn test_revert_stale(context: &DbTestContext) {
+    let user = create_fake_user(&context.db_pool).await;
+    let note = create_note("first", user.id, &context.db_pool).await;
+    update_note(context, &user, note.id, "second", "*").await;
+
+    let response = context
+        .app
+        .request(&format!("/notes/{}/revisions/1/revert", note.id))
+        .method(Method::POST)
+        .header(http::header::IF_MATCH, "\"1\"")
+        .header(http::header::AUTHORIZATION, &context.bearer_token(&user))
+        .send()
+        .await;
+
+    assert_that!(response.status(), eq(StatusCode::PRECONDITION_FAILED));
+    let note = notes::load(note.id, user.id, &context.db_pool)
+        .await
+        .unwrap();
+    assert_that!(note.text, eq("second"));
+}
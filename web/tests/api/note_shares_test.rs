@@ -0,0 +1,329 @@
+use axum::{
+    body::Body,
+    http::{self, Method},
+};
+use fake::{Fake, Faker};
+use forge_api_db::entities::note_shares::{self, NoteShare, NoteShareChangeset, SharePermission};
+use forge_api_db::entities::notes::{self, Note, NoteChangeset};
+use forge_api_db::entities::users::User;
+use forge_api_db::test_helpers::users::create_fake as create_fake_user;
+use forge_api_macros::db_test;
+use forge_api_web::test_helpers::{BodyExt, DbTestContext, RouterExt};
+use googletest::prelude::*;
+use hyper::StatusCode;
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+async fn create_note(context: &DbTestContext, user: &User) -> Note {
+    let changeset = NoteChangeset {
+        text: String::from("text"),
+        tags: Some(vec![String::from("tag")]),
+        ..Faker.fake()
+    };
+    notes::create(changeset, user.id, &context.db_pool)
+        .await
+        .unwrap()
+}
+
+async fn share(
+    context: &DbTestContext,
+    note: &Note,
+    user: &User,
+    permission: SharePermission,
+) -> NoteShare {
+    let changeset = NoteShareChangeset {
+        user_name: user.name.clone(),
+        permission,
+    };
+    note_shares::grant(note.id, note.owner_id, changeset, &context.db_pool)
+        .await
+        .unwrap()
+}
+
+async fn send_read(context: &DbTestContext, user: &User, note: &Note) -> axum::response::Response {
+    context
+        .app
+        .request(&format!("/notes/{}", note.id))
+        .header(http::header::AUTHORIZATION, &context.bearer_token(user))
+        .send()
+        .await
+}
+
+async fn send_update(
+    context: &DbTestContext,
+    user: &User,
+    note: &Note,
+    payload: Value,
+) -> axum::response::Response {
+    context
+        .app
+        .request(&format!("/notes/{}", note.id))
+        .method(Method::PUT)
+        .body(Body::from(payload.to_string()))
+        .header(http::header::CONTENT_TYPE, "application/json")
+        .header(http::header::AUTHORIZATION, &context.bearer_token(user))
+        .send()
+        .await
+}
+
+async fn send_patch(
+    context: &DbTestContext,
+    user: &User,
+    note: &Note,
+    payload: Value,
+) -> axum::response::Response {
+    context
+        .app
+        .request(&format!("/notes/{}", note.id))
+        .method(Method::PATCH)
+        .body(Body::from(payload.to_string()))
+        .header(http::header::CONTENT_TYPE, "application/merge-patch+json")
+        .header(http::header::AUTHORIZATION, &context.bearer_token(user))
+        .send()
+        .await
+}
+
+#[db_test]
+async fn test_owner_access(context: &DbTestContext) {
+    let owner = create_fake_user(&context.db_pool).await;
+    let note = create_note(context, &owner).await;
+
+    let response = send_read(context, &owner, &note).await;
+    assert_that!(response.status(), eq(StatusCode::OK));
+
+    let response = send_update(context, &owner, &note, json!({ "text": "changed" })).await;
+    assert_that!(response.status(), eq(StatusCode::OK));
+
+    let response = send_patch(context, &owner, &note, json!({ "text": "patched" })).await;
+    assert_that!(response.status(), eq(StatusCode::OK));
+}
+
+#[db_test]
+async fn test_no_share(context: &DbTestContext) {
+    let owner = create_fake_user(&context.db_pool).await;
+    let user = create_fake_user(&context.db_pool).await;
+    let note = create_note(context, &owner).await;
+
+    let response = send_read(context, &user, &note).await;
+    assert_that!(response.status(), eq(StatusCode::NOT_FOUND));
+
+    let response = send_update(context, &user, &note, json!({ "text": "changed" })).await;
+    assert_that!(response.status(), eq(StatusCode::NOT_FOUND));
+
+    let response = send_patch(context, &user, &note, json!({ "text": "patched" })).await;
+    assert_that!(response.status(), eq(StatusCode::NOT_FOUND));
+}
+
+#[db_test]
+async fn test_read_share(context: &DbTestContext) {
+    let owner = create_fake_user(&context.db_pool).await;
+    let user = create_fake_user(&context.db_pool).await;
+    let note = create_note(context, &owner).await;
+    share(context, &note, &user, SharePermission::Read).await;
+
+    let response = send_read(context, &user, &note).await;
+    assert_that!(response.status(), eq(StatusCode::OK));
+    let read: Note = response.into_body().into_json().await;
+    assert_that!(read.text, eq("text"));
+    assert_that!(read.owner_id, eq(owner.id));
+
+    let response = send_update(context, &user, &note, json!({ "text": "changed" })).await;
+    assert_that!(response.status(), eq(StatusCode::FORBIDDEN));
+
+    let response = send_patch(context, &user, &note, json!({ "text": "patched" })).await;
+    assert_that!(response.status(), eq(StatusCode::FORBIDDEN));
+
+    let note = notes::load(note.id, owner.id, &context.db_pool)
+        .await
+        .unwrap();
+    assert_that!(note.text, eq("text"));
+    assert_that!(note.version, eq(1));
+}
+
+#[db_test]
+async fn test_write_share(context: &DbTestContext) {
+    let owner = create_fake_user(&context.db_pool).await;
+    let user = create_fake_user(&context.db_pool).await;
+    let note = create_note(context, &owner).await;
+    share(context, &note, &user, SharePermission::Write).await;
+
+    let response = send_read(context, &user, &note).await;
+    assert_that!(response.status(), eq(StatusCode::OK));
+
+    let response = send_update(
+        context,
+        &user,
+        &note,
+        json!({ "text": "changed", "tags": ["shared"] }),
+    )
+    .await;
+    assert_that!(response.status(), eq(StatusCode::OK));
+    let updated: Note = response.into_body().into_json().await;
+    assert_that!(updated.text, eq("changed"));
+    assert_that!(updated.owner_id, eq(owner.id));
+    assert_that!(updated.tags, elements_are![eq("shared")]);
+
+    let response = send_patch(context, &user, &note, json!({ "text": "patched" })).await;
+    assert_that!(response.status(), eq(StatusCode::OK));
+
+    // The note and its tags still belong to the owner.
+    let note = notes::load(note.id, owner.id, &context.db_pool)
+        .await
+        .unwrap();
+    assert_that!(note.text, eq("patched"));
+    assert_that!(note.version, eq(3));
+    assert_that!(note.tags, elements_are![eq("shared")]);
+    let user_notes = notes::load_all(user.id, &context.db_pool).await.unwrap();
+    assert_that!(user_notes, empty());
+
+    // Only the owner can delete the note.
+    let response = context
+        .app
+        .request(&format!("/notes/{}", note.id))
+        .method(Method::DELETE)
+        .header(http::header::AUTHORIZATION, &context.bearer_token(&user))
+        .send()
+        .await;
+    assert_that!(response.status(), eq(StatusCode::NOT_FOUND));
+}
+
+#[db_test]
+async fn test_create_share(context: &DbTestContext) {
+    let owner = create_fake_user(&context.db_pool).await;
+    let user = create_fake_user(&context.db_pool).await;
+    let note = create_note(context, &owner).await;
+
+    let response = context
+        .app
+        .request(&format!("/notes/{}/shares", note.id))
+        .method(Method::POST)
+        .body(Body::from(
+            json!({ "user_name": user.name.to_uppercase(), "permission": "read" }).to_string(),
+        ))
+        .header(http::header::CONTENT_TYPE, "application/json")
+        .header(http::header::AUTHORIZATION, &context.bearer_token(&owner))
+        .send()
+        .await;
+    assert_that!(response.status(), eq(StatusCode::OK));
+    let created: NoteShare = response.into_body().into_json().await;
+    assert_that!(created.user_id, eq(user.id));
+    assert_that!(created.user_name, eq(&user.name));
+    assert_that!(created.permission, eq(SharePermission::Read));
+
+    // Sharing again changes the permission.
+    let upgraded = share(context, &note, &user, SharePermission::Write).await;
+    assert_that!(upgraded.created_at, eq(created.created_at));
+
+    let response = context
+        .app
+        .request(&format!("/notes/{}/shares", note.id))
+        .header(http::header::AUTHORIZATION, &context.bearer_token(&owner))
+        .send()
+        .await;
+    assert_that!(response.status(), eq(StatusCode::OK));
+    let shares: Vec<NoteShare> = response.into_body().into_json().await;
+    assert_that!(
+        shares,
+        elements_are![matches_pattern!(NoteShare {
+            user_id: eq(&user.id),
+            permission: eq(&SharePermission::Write),
+            ..
+        })]
+    );
+}
+
+#[db_test]
+async fn test_create_share_invalid(context: &DbTestContext) {
+    let owner = create_fake_user(&context.db_pool).await;
+    let user = create_fake_user(&context.db_pool).await;
+    let note = create_note(context, &owner).await;
+
+    for user_name in [String::from("nobody at all"), owner.name.clone()] {
+        let response = context
+            .app
+            .request(&format!("/notes/{}/shares", note.id))
+            .method(Method::POST)
+            .body(Body::from(
+                json!({ "user_name": user_name, "permission": "read" }).to_string(),
+            ))
+            .header(http::header::CONTENT_TYPE, "application/json")
+            .header(http::header::AUTHORIZATION, &context.bearer_token(&owner))
+            .send()
+            .await;
+        assert_that!(response.status(), eq(StatusCode::UNPROCESSABLE_ENTITY));
+    }
+
+    // Someone the note is shared with can't share it any further.
+    share(context, &note, &user, SharePermission::Write).await;
+    let response = context
+        .app
+        .request(&format!("/notes/{}/shares", note.id))
+        .method(Method::POST)
+        .body(Body::from(
+            json!({ "user_name": owner.name, "permission": "read" }).to_string(),
+        ))
+        .header(http::header::CONTENT_TYPE, "application/json")
+        .header(http::header::AUTHORIZATION, &context.bearer_token(&user))
+        .send()
+        .await;
+    assert_that!(response.status(), eq(StatusCode::NOT_FOUND));
+
+    let response = context
+        .app
+        .request(&format!("/notes/{}/shares", note.id))
+        .header(http::header::AUTHORIZATION, &context.bearer_token(&user))
+        .send()
+        .await;
+    assert_that!(response.status(), eq(StatusCode::NOT_FOUND));
+}
+
+#[db_test]
+async fn test_read_shares_of_unknown_note(context: &DbTestContext) {
+    let user = create_fake_user(&context.db_pool).await;
+
+    let response = context
+        .app
+        .request(&format!("/notes/{}/shares", Uuid::new_v4()))
+        .header(http::header::AUTHORIZATION, &context.bearer_token(&user))
+        .send()
+        .await;
+    assert_that!(response.status(), eq(StatusCode::NOT_FOUND));
+}
+
+#[db_test]
+async fn test_delete_share(context: &DbTestContext) {
+    let owner = create_fake_user(&context.db_pool).await;
+    let user = create_fake_user(&context.db_pool).await;
+    let note = create_note(context, &owner).await;
+    share(context, &note, &user, SharePermission::Read).await;
+
+    let response = context
+        .app
+        .request(&format!("/notes/{}/shares/{}", note.id, user.id))
+        .method(Method::DELETE)
+        .header(http::header::AUTHORIZATION, &context.bearer_token(&user))
+        .send()
+        .await;
+    assert_that!(response.status(), eq(StatusCode::NOT_FOUND));
+
+    let response = context
+        .app
+        .request(&format!("/notes/{}/shares/{}", note.id, user.id))
+        .method(Method::DELETE)
+        .header(http::header::AUTHORIZATION, &context.bearer_token(&owner))
+        .send()
+        .await;
+    assert_that!(response.status(), eq(StatusCode::NO_CONTENT));
+
+    let response = send_read(context, &user, &note).await;
+    assert_that!(response.status(), eq(StatusCode::NOT_FOUND));
+
+    let response = context
+        .app
+        .request(&format!("/notes/{}/shares/{}", note.id, user.id))
+        .method(Method::DELETE)
+        .header(http::header::AUTHORIZATION, &context.bearer_token(&owner))
+        .send()
+        .await;
+    assert_that!(response.status(), eq(StatusCode::NOT_FOUND));
+}
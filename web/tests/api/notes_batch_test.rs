@@ -0,0 +1,190 @@
+use axum::{
+    body::Body,
+    http::{self, Method},
+};
+use forge_api_db::entities::notes::{self};
+use forge_api_db::entities::{roles, users::User};
+use forge_api_db::test_helpers::notes::create as create_note;
+use forge_api_db::test_helpers::roles::create as create_role;
+use forge_api_db::test_helpers::users::create_fake as create_fake_user;
+use forge_api_macros::db_test;
+use forge_api_web::controllers::notes::BatchResult;
+use forge_api_web::test_helpers::{BodyExt, DbTestContext, RouterExt};
+use googletest::prelude::*;
+use hyper::StatusCode;
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+async fn send_batch(context: &DbTestContext, user: &User, payload: Value) -> Vec<BatchResult> {
+    let response = context
+        .app
+        .request("/notes/batch")
+        .method(Method::POST)
+        .body(Body::from(payload.to_string()))
+        .header(http::header::CONTENT_TYPE, "application/json")
+        .header(http::header::AUTHORIZATION, &context.bearer_token(user))
+        .send()
+        .await;
+    assert_that!(response.status(), eq(StatusCode::OK));
+
+    response.into_body().into_json().await
+}
+
+fn statuses(results: &[BatchResult]) -> Vec<u16> {
+    results.iter().map(|result| result.status).collect()
+}
+
+async fn texts(context: &DbTestContext, user: &User) -> Vec<String> {
+    notes::load_all(user.id, &context.db_pool)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|note| note.text)
+        .collect()
+}
+
+#[db_test]
+async fn test_batch_unauthorized(context: &DbTestContext) {
+    let payload = json!({ "operations": [{ "op": "create", "note": { "text": "new" } }] });
+
+    let response = context
+        .app
+        .request("/notes/batch")
+        .method(Method::POST)
+        .body(Body::from(payload.to_string()))
+        .header(http::header::CONTENT_TYPE, "application/json")
+        .send()
+        .await;
+
+    assert_that!(response.status(), eq(StatusCode::UNAUTHORIZED));
+}
+
+#[db_test]
+async fn test_batch_invalid(context: &DbTestContext) {
+    let user = create_fake_user(&context.db_pool).await;
+
+    let response = context
+        .app
+        .request("/notes/batch")
+        .method(Method::POST)
+        .body(Body::from(json!({ "operations": [] }).to_string()))
+        .header(http::header::CONTENT_TYPE, "application/json")
+        .header(http::header::AUTHORIZATION, &context.bearer_token(&user))
+        .send()
+        .await;
+
+    assert_that!(response.status(), eq(StatusCode::UNPROCESSABLE_ENTITY));
+}
+
+#[db_test]
+async fn test_batch_all_or_nothing(context: &DbTestContext) {
+    let user = create_fake_user(&context.db_pool).await;
+    let updated = create_note("old", user.id, &context.db_pool).await;
+    let deleted = create_note("deleted", user.id, &context.db_pool).await;
+
+    let results = send_batch(
+        context,
+        &user,
+        json!({ "operations": [
+            { "op": "create", "note": { "text": "new" } },
+            { "op": "update", "id": updated.id, "version": 1, "note": { "text": "updated" } },
+            { "op": "delete", "id": deleted.id }
+        ] }),
+    )
+    .await;
+
+    assert_that!(
+        statuses(&results),
+        elements_are![eq(&201), eq(&200), eq(&204)]
+    );
+    assert_that!(results[0].note.as_ref().unwrap().text, eq("new"));
+    assert_that!(results[1].note.as_ref().unwrap().version, eq(2));
+    assert_that!(
+        texts(context, &user).await,
+        unordered_elements_are![eq("updated"), eq("new")]
+    );
+}
+
+#[db_test]
+async fn test_batch_all_or_nothing_rolls_back(context: &DbTestContext) {
+    let user = create_fake_user(&context.db_pool).await;
+    let note = create_note("old", user.id, &context.db_pool).await;
+
+    let results = send_batch(
+        context,
+        &user,
+        json!({ "operations": [
+            { "op": "create", "note": { "text": "new" } },
+            { "op": "update", "id": note.id, "version": 2, "note": { "text": "updated" } },
+            { "op": "delete", "id": note.id }
+        ] }),
+    )
+    .await;
+
+    assert_that!(
+        statuses(&results),
+        elements_are![eq(&424), eq(&412), eq(&424)]
+    );
+    assert_that!(texts(context, &user).await, elements_are![eq("old")]);
+}
+
+#[db_test]
+async fn test_batch_best_effort(context: &DbTestContext) {
+    let user = create_fake_user(&context.db_pool).await;
+    let other_user = create_fake_user(&context.db_pool).await;
+    let other_note = create_note("other", other_user.id, &context.db_pool).await;
+
+    let results = send_batch(
+        context,
+        &user,
+        json!({ "mode": "best_effort", "operations": [
+            { "op": "create", "note": { "text": "new" } },
+            { "op": "update", "id": other_note.id, "note": { "text": "stolen" } },
+            { "op": "create", "note": { "text": "" } },
+            { "op": "delete", "id": Uuid::new_v4() }
+        ] }),
+    )
+    .await;
+
+    assert_that!(
+        statuses(&results),
+        elements_are![eq(&201), eq(&404), eq(&422), eq(&404)]
+    );
+    assert_that!(results[2].error, some(anything()));
+    assert_that!(texts(context, &user).await, elements_are![eq("new")]);
+    assert_that!(
+        texts(context, &other_user).await,
+        elements_are![eq("other")]
+    );
+}
+
+#[db_test]
+async fn test_batch_checks_permissions(context: &DbTestContext) {
+    let user = create_fake_user(&context.db_pool).await;
+    roles::unassign(user.id, roles::DEFAULT_ROLE, &context.db_pool)
+        .await
+        .unwrap();
+    let role = create_role(&["notes:create"], &context.db_pool)
+        .await
+        .unwrap();
+    roles::assign(user.id, &role, &context.db_pool)
+        .await
+        .unwrap();
+    let note = create_note("kept", user.id, &context.db_pool).await;
+
+    let results = send_batch(
+        context,
+        &user,
+        json!({ "mode": "best_effort", "operations": [
+            { "op": "create", "note": { "text": "new" } },
+            { "op": "delete", "id": note.id }
+        ] }),
+    )
+    .await;
+
+    assert_that!(statuses(&results), elements_are![eq(&201), eq(&403)]);
+    assert_that!(
+        texts(context, &user).await,
+        unordered_elements_are![eq("kept"), eq("new")]
+    );
+}
@@ -0,0 +1,238 @@
+use axum::{
+    body::Body,
+    http::{self, Method},
+};
+use fake::{Fake, Faker};
+use forge_api_db::entities::notes::{self, Note, NoteChangeset, NoteFormat};
+use forge_api_db::entities::users::User;
+use forge_api_db::test_helpers::users::create_fake as create_fake_user;
+use forge_api_macros::db_test;
+use forge_api_web::test_helpers::{BodyExt, DbTestContext, RouterExt};
+use googletest::prelude::*;
+use hyper::StatusCode;
+use serde_json::json;
+
+async fn create_note(context: &DbTestContext, user: &User, text: &str, format: NoteFormat) -> Note {
+    let changeset = NoteChangeset {
+        text: String::from(text),
+        format: Some(format),
+        ..Faker.fake()
+    };
+    notes::create(changeset, user.id, &context.db_pool)
+        .await
+        .unwrap()
+}
+
+async fn read_html(context: &DbTestContext, user: &User, note: &Note) -> String {
+    let response = context
+        .app
+        .request(&format!("/notes/{}", note.id))
+        .header(http::header::ACCEPT, "text/html,application/json;q=0.9")
+        .header(http::header::AUTHORIZATION, &context.bearer_token(user))
+        .send()
+        .await;
+
+    assert_that!(response.status(), eq(StatusCode::OK));
+    assert_that!(
+        response
+            .headers()
+            .get(http::header::CONTENT_TYPE)
+            .unwrap()
+            .to_str()
+            .unwrap(),
+        starts_with("text/html")
+    );
+    let body = response.into_body().into_bytes().await;
+    String::from_utf8(body.to_vec()).unwrap()
+}
+
+#[db_test]
+async fn test_read_one_as_html_renders_markdown(context: &DbTestContext) {
+    let user = create_fake_user(&context.db_pool).await;
+    let note = create_note(
+        context,
+        &user,
+        "# Title\n\nSome *emphasis*\n\n<script>alert(1)</script>\n\n[link](javascript:alert(1))",
+        NoteFormat::Markdown,
+    )
+    .await;
+
+    let html = read_html(context, &user, &note).await;
+
+    assert_that!(
+        html,
+        starts_with("<h1>Title</h1>\n<p>Some <em>emphasis</em></p>")
+    );
+    assert_that!(html, not(contains_substring("<script")));
+    assert_that!(html, not(contains_substring("href=\"javascript:")));
+}
+
+#[db_test]
+async fn test_read_one_as_html_escapes_plain_text(context: &DbTestContext) {
+    let user = create_fake_user(&context.db_pool).await;
+    let note = create_note(
+        context,
+        &user,
+        "a <b> & c\nnext line\n\n*not emphasis*",
+        NoteFormat::Plain,
+    )
+    .await;
+
+    let html = read_html(context, &user, &note).await;
+
+    assert_that!(
+        html,
+        eq("<p>a &lt;b&gt; &amp; c<br>\nnext line</p>\n<p>*not emphasis*</p>\n")
+    );
+}
+
+#[db_test]
+async fn test_read_one_as_json_by_default(context: &DbTestContext) {
+    let user = create_fake_user(&context.db_pool).await;
+    let note = create_note(context, &user, "*text*", NoteFormat::Markdown).await;
+
+    for accept in [None, Some("application/json"), Some("*/*")] {
+        let mut request = context
+            .app
+            .request(&format!("/notes/{}", note.id))
+            .header(http::header::AUTHORIZATION, &context.bearer_token(&user));
+        if let Some(accept) = accept {
+            request = request.header(http::header::ACCEPT, accept);
+        }
+        let response = request.send().await;
+
+        assert_that!(response.status(), eq(StatusCode::OK));
+        let note: Note = response.into_body().into_json().await;
+        assert_that!(note.format, eq(NoteFormat::Markdown));
+    }
+}
+
+#[db_test]
+async fn test_read_one_as_html_renders_task_lists(context: &DbTestContext) {
+    let user = create_fake_user(&context.db_pool).await;
+    let note = create_note(
+        context,
+        &user,
+        "- [x] done\n- [ ] open\n\n<input type=\"text\" name=\"password\">",
+        NoteFormat::Markdown,
+    )
+    .await;
+
+    let html = read_html(context, &user, &note).await;
+
+    // The order of the attributes of the checkboxes isn't stable, so they are checked one by one.
+    assert_that!(
+        html,
+        matches_regex(
+            r#"(?s)^<ul>\n<li><input [^>]+>\ndone</li>\n<li><input [^>]+>\nopen</li>\n</ul>\n<input [^>]+>$"#
+        )
+    );
+    let inputs: Vec<&str> = html
+        .split("<input ")
+        .skip(1)
+        .map(|input| input.split_once('>').unwrap().0)
+        .collect();
+    assert_that!(
+        inputs,
+        each(all!(
+            contains_substring("type=\"checkbox\""),
+            contains_substring("disabled=\"\"")
+        ))
+    );
+    assert_that!(
+        inputs,
+        elements_are![
+            contains_substring("checked=\"\""),
+            not(contains_substring("checked")),
+            not(contains_substring("checked"))
+        ]
+    );
+    // Inputs other than the checkboxes of task lists are turned into disabled checkboxes as well.
+    assert_that!(html, not(contains_substring("type=\"text\"")));
+    assert_that!(html, not(contains_substring("name=")));
+}
+
+#[db_test]
+async fn test_read_one_negotiates_content_type(context: &DbTestContext) {
+    let user = create_fake_user(&context.db_pool).await;
+    let note = create_note(context, &user, "*text*", NoteFormat::Markdown).await;
+
+    for (accept, expected_content_type) in [
+        ("text/*", "text/html"),
+        ("text/*;q=0.9, application/json;q=0.5", "text/html"),
+        ("application/json;q=0.5, */*", "text/html"),
+        ("text/html;q=0.5, */*", "application/json"),
+        (
+            "text/html;q=0.5, text/*, application/json;q=0.8",
+            "application/json",
+        ),
+        ("*/*;q=0.5, text/html;Q=0.6", "text/html"),
+        ("text/html, application/json", "application/json"),
+    ] {
+        let response = context
+            .app
+            .request(&format!("/notes/{}", note.id))
+            .header(http::header::ACCEPT, accept)
+            .header(http::header::AUTHORIZATION, &context.bearer_token(&user))
+            .send()
+            .await;
+
+        assert_that!(response.status(), eq(StatusCode::OK));
+        assert_that!(
+            response
+                .headers()
+                .get(http::header::CONTENT_TYPE)
+                .unwrap()
+                .to_str()
+                .unwrap(),
+            starts_with(expected_content_type),
+            "Accept: {}",
+            accept
+        );
+    }
+}
+
+#[db_test]
+async fn test_html_cache_is_invalidated_on_update(context: &DbTestContext) {
+    let user = create_fake_user(&context.db_pool).await;
+    let note = create_note(context, &user, "*first*", NoteFormat::Markdown).await;
+
+    let html = read_html(context, &user, &note).await;
+    assert_that!(html, eq("<p><em>first</em></p>\n"));
+    let (_, cached) = notes::load_with_html(note.id, user.id, &context.db_pool)
+        .await
+        .unwrap();
+    assert_that!(cached, some(eq("<p><em>first</em></p>\n")));
+
+    let response = context
+        .app
+        .request(&format!("/notes/{}", note.id))
+        .method(Method::PUT)
+        .body(Body::from(json!({ "text": "*second*" }).to_string()))
+        .header(http::header::CONTENT_TYPE, "application/json")
+        .header(http::header::AUTHORIZATION, &context.bearer_token(&user))
+        .send()
+        .await;
+    assert_that!(response.status(), eq(StatusCode::OK));
+    let (_, cached) = notes::load_with_html(note.id, user.id, &context.db_pool)
+        .await
+        .unwrap();
+    assert_that!(cached, none());
+
+    let html = read_html(context, &user, &note).await;
+    assert_that!(html, eq("<p><em>second</em></p>\n"));
+
+    let response = context
+        .app
+        .request(&format!("/notes/{}", note.id))
+        .method(Method::PATCH)
+        .body(Body::from(json!({ "format": "plain" }).to_string()))
+        .header(http::header::CONTENT_TYPE, "application/merge-patch+json")
+        .header(http::header::AUTHORIZATION, &context.bearer_token(&user))
+        .send()
+        .await;
+    assert_that!(response.status(), eq(StatusCode::OK));
+
+    let html = read_html(context, &user, &note).await;
+    assert_that!(html, eq("<p>*second*</p>\n"));
+}
@@ -0,0 +1,148 @@
+use axum::{
+    body::Body,
+    http::{self, Method},
+};
+use fake::{Fake, Faker};
+use forge_api_db::entities::note_revisions;
+use forge_api_db::entities::notes::{self, Note, NoteChangeset};
+use forge_api_db::entities::users::User;
+use forge_api_db::test_helpers::users::create_fake as create_fake_user;
+use forge_api_macros::db_test;
+use forge_api_web::test_helpers::{BodyExt, DbTestContext, RouterExt};
+use googletest::prelude::*;
+use hyper::StatusCode;
+use serde_json::{json, Value};
+
+async fn create_note(context: &DbTestContext, user: &User) -> Note {
+    let changeset = NoteChangeset {
+        text: String::from("text"),
+        tags: Some(vec![String::from("tag")]),
+        ..Faker.fake()
+    };
+    notes::create(changeset, user.id, &context.db_pool)
+        .await
+        .unwrap()
+}
+
+async fn send_patch(
+    context: &DbTestContext,
+    user: &User,
+    note: &Note,
+    payload: Value,
+) -> axum::response::Response {
+    context
+        .app
+        .request(&format!("/notes/{}", note.id))
+        .method(Method::PATCH)
+        .body(Body::from(payload.to_string()))
+        .header(http::header::CONTENT_TYPE, "application/merge-patch+json")
+        .header(http::header::AUTHORIZATION, &context.bearer_token(user))
+        .send()
+        .await
+}
+
+#[db_test]
+async fn test_patch_text(context: &DbTestContext) {
+    let user = create_fake_user(&context.db_pool).await;
+    let note = create_note(context, &user).await;
+
+    let response = send_patch(context, &user, &note, json!({ "text": "changed" })).await;
+
+    assert_that!(response.status(), eq(StatusCode::OK));
+    assert_that!(
+        response.headers().get(http::header::ETAG).unwrap(),
+        eq("\"2\"")
+    );
+    let patched: Note = response.into_body().into_json().await;
+    assert_that!(patched.text, eq("changed"));
+    assert_that!(patched.tags, elements_are![eq("tag")]);
+
+    let revisions = note_revisions::load_all(note.id, user.id, &context.db_pool)
+        .await
+        .unwrap();
+    assert_that!(revisions, len(eq(1)));
+    assert_that!(revisions[0].text, eq("text"));
+}
+
+#[db_test]
+async fn test_patch_tags(context: &DbTestContext) {
+    let user = create_fake_user(&context.db_pool).await;
+    let note = create_note(context, &user).await;
+
+    let response = send_patch(context, &user, &note, json!({ "tags": ["a", "b"] })).await;
+    assert_that!(response.status(), eq(StatusCode::OK));
+    let patched: Note = response.into_body().into_json().await;
+    assert_that!(patched.text, eq("text"));
+    assert_that!(patched.tags, elements_are![eq("a"), eq("b")]);
+
+    let response = send_patch(context, &user, &note, json!({ "tags": null })).await;
+    assert_that!(response.status(), eq(StatusCode::OK));
+    let patched: Note = response.into_body().into_json().await;
+    assert_that!(patched.tags, empty());
+}
+
+#[db_test]
+async fn test_patch_invalid(context: &DbTestContext) {
+    let user = create_fake_user(&context.db_pool).await;
+    let note = create_note(context, &user).await;
+
+    let response = send_patch(context, &user, &note, json!({ "text": "" })).await;
+    assert_that!(response.status(), eq(StatusCode::UNPROCESSABLE_ENTITY));
+
+    let response = send_patch(context, &user, &note, json!({ "text": null })).await;
+    assert_that!(response.status(), eq(StatusCode::UNPROCESSABLE_ENTITY));
+
+    let note = notes::load(note.id, user.id, &context.db_pool)
+        .await
+        .unwrap();
+    assert_that!(note.text, eq("text"));
+    assert_that!(note.version, eq(1));
+}
+
+#[db_test]
+async fn test_patch_requires_merge_patch_content_type(context: &DbTestContext) {
+    let user = create_fake_user(&context.db_pool).await;
+    let note = create_note(context, &user).await;
+
+    let response = context
+        .app
+        .request(&format!("/notes/{}", note.id))
+        .method(Method::PATCH)
+        .body(Body::from(json!({ "text": "changed" }).to_string()))
+        .header(http::header::CONTENT_TYPE, "application/json")
+        .header(http::header::AUTHORIZATION, &context.bearer_token(&user))
+        .send()
+        .await;
+
+    assert_that!(response.status(), eq(StatusCode::UNSUPPORTED_MEDIA_TYPE));
+}
+
+#[db_test]
+async fn test_patch_if_match(context: &DbTestContext) {
+    let user = create_fake_user(&context.db_pool).await;
+    let note = create_note(context, &user).await;
+
+    let response = context
+        .app
+        .request(&format!("/notes/{}", note.id))
+        .method(Method::PATCH)
+        .body(Body::from(json!({ "text": "changed" }).to_string()))
+        .header(http::header::CONTENT_TYPE, "application/merge-patch+json")
+        .header(http::header::IF_MATCH, "\"2\"")
+        .header(http::header::AUTHORIZATION, &context.bearer_token(&user))
+        .send()
+        .await;
+
+    assert_that!(response.status(), eq(StatusCode::PRECONDITION_FAILED));
+}
+
+#[db_test]
+async fn test_patch_other_users_note(context: &DbTestContext) {
+    let user = create_fake_user(&context.db_pool).await;
+    let other_user = create_fake_user(&context.db_pool).await;
+    let note = create_note(context, &other_user).await;
+
+    let response = send_patch(context, &user, &note, json!({ "text": "changed" })).await;
+
+    assert_that!(response.status(), eq(StatusCode::NOT_FOUND));
+}
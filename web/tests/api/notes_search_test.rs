@@ -0,0 +1,149 @@
+use axum::http;
+use forge_api_db::entities::notes::SearchResult;
+use forge_api_db::entities::users::User;
+use forge_api_db::test_helpers::notes::create as create_note;
+use forge_api_db::test_helpers::users::create_fake as create_fake_user;
+use forge_api_macros::db_test;
+use forge_api_web::test_helpers::{BodyExt, DbTestContext, RouterExt};
+use googletest::prelude::*;
+use hyper::StatusCode;
+use serde_json::Value;
+
+async fn search(context: &DbTestContext, user: &User, query: &str) -> (StatusCode, Vec<String>) {
+    let response = context
+        .app
+        .request(&format!("/notes/search?{}", query))
+        .header(http::header::AUTHORIZATION, &context.bearer_token(user))
+        .send()
+        .await;
+
+    let status = response.status();
+    if status == StatusCode::OK {
+        let results: Vec<SearchResult> = response.into_body().into_json().await;
+        (status, results.into_iter().map(|r| r.note.text).collect())
+    } else {
+        (status, vec![])
+    }
+}
+
+#[db_test]
+async fn test_search_words(context: &DbTestContext) {
+    let user = create_fake_user(&context.db_pool).await;
+    create_note("Walked the dogs in the park", user.id, &context.db_pool).await;
+    create_note("Feed the dog", user.id, &context.db_pool).await;
+    create_note("Park the car", user.id, &context.db_pool).await;
+
+    let (status, texts) = search(context, &user, "q=dog").await;
+    assert_that!(status, eq(StatusCode::OK));
+    assert_that!(
+        texts,
+        unordered_elements_are![eq("Walked the dogs in the park"), eq("Feed the dog")]
+    );
+
+    let (_, texts) = search(context, &user, "q=park%20dog").await;
+    assert_that!(texts, elements_are![eq("Walked the dogs in the park")]);
+}
+
+#[db_test]
+async fn test_search_phrase(context: &DbTestContext) {
+    let user = create_fake_user(&context.db_pool).await;
+    create_note("the quick brown fox", user.id, &context.db_pool).await;
+    create_note("the brown and quick fox", user.id, &context.db_pool).await;
+
+    let (_, texts) = search(context, &user, "q=%22quick%20brown%22").await;
+
+    assert_that!(texts, elements_are![eq("the quick brown fox")]);
+}
+
+#[db_test]
+async fn test_search_prefix(context: &DbTestContext) {
+    let user = create_fake_user(&context.db_pool).await;
+    create_note("Deploy the application", user.id, &context.db_pool).await;
+    create_note("Apply for the job", user.id, &context.db_pool).await;
+    create_note("Buy apples", user.id, &context.db_pool).await;
+
+    let (_, texts) = search(context, &user, "q=appl*").await;
+
+    assert_that!(
+        texts,
+        unordered_elements_are![
+            eq("Deploy the application"),
+            eq("Apply for the job"),
+            eq("Buy apples")
+        ]
+    );
+}
+
+#[db_test]
+async fn test_search_ranking_and_snippets(context: &DbTestContext) {
+    let user = create_fake_user(&context.db_pool).await;
+    create_note("milk <b>bread</b>", user.id, &context.db_pool).await;
+    create_note("milk milk milk", user.id, &context.db_pool).await;
+
+    let response = context
+        .app
+        .request("/notes/search?q=milk")
+        .header(http::header::AUTHORIZATION, &context.bearer_token(&user))
+        .send()
+        .await;
+    let results: Vec<SearchResult> = response.into_body().into_json().await;
+
+    assert_that!(results, len(eq(2)));
+    assert_that!(results[0].note.text, eq("milk milk milk"));
+    assert_that!(results[0].rank, gt(results[1].rank));
+    assert_that!(
+        results[1].snippet,
+        eq("<mark>milk</mark> &lt;b&gt;bread&lt;/b&gt;")
+    );
+}
+
+#[db_test]
+async fn test_search_only_own_notes(context: &DbTestContext) {
+    let user = create_fake_user(&context.db_pool).await;
+    let other_user = create_fake_user(&context.db_pool).await;
+    create_note("secret plans", other_user.id, &context.db_pool).await;
+
+    let (status, texts) = search(context, &user, "q=secret").await;
+
+    assert_that!(status, eq(StatusCode::OK));
+    assert_that!(texts, empty());
+}
+
+#[db_test]
+async fn test_search_operators_are_ignored(context: &DbTestContext) {
+    let user = create_fake_user(&context.db_pool).await;
+    create_note("cats and dogs", user.id, &context.db_pool).await;
+
+    let (status, texts) = search(context, &user, "q=cats%20%7C%20!(%27dogs").await;
+
+    assert_that!(status, eq(StatusCode::OK));
+    assert_that!(texts, elements_are![eq("cats and dogs")]);
+}
+
+#[db_test]
+async fn test_search_invalid(context: &DbTestContext) {
+    let user = create_fake_user(&context.db_pool).await;
+
+    for query in ["q=", "q=%22%20*%20%22", "q=milk&limit=0"] {
+        let (status, _) = search(context, &user, query).await;
+        assert_that!(status, eq(StatusCode::UNPROCESSABLE_ENTITY));
+    }
+
+    let (status, _) = search(context, &user, "").await;
+    assert_that!(status, eq(StatusCode::BAD_REQUEST));
+}
+
+#[db_test]
+async fn test_openapi_documents_search(context: &DbTestContext) {
+    let response = context.app.request("/api-docs/openapi.json").send().await;
+
+    let openapi: Value = response.into_body().into_json().await;
+    assert_that!(
+        openapi["paths"]["/notes/search"]["get"],
+        not(eq(&Value::Null))
+    );
+    assert_that!(
+        openapi["components"]["schemas"]["SearchResult"],
+        not(eq(&Value::Null))
+    );
+}
@@ -4,19 +4,32 @@ use axum::{
 };
 use fake::{Fake, Faker};
 use forge_api_db::entities;
-use forge_api_db::test_helpers::users::{create as create_user, UserChangeset};
+use forge_api_db::entities::users::User;
+use forge_api_db::test_helpers::notes::create as create_note;
+use forge_api_db::test_helpers::users::create_fake as create_fake_user;
 use forge_api_macros::db_test;
+use forge_api_web::controllers::notes::NotesPage;
 use forge_api_web::test_helpers::{BodyExt, DbTestContext, RouterExt};
 use googletest::prelude::*;
 use hyper::StatusCode;
 
-use serde_json::json;
+use serde_json::{json, Value};
 use uuid::Uuid;
 
+#[db_test]
+async fn test_read_all_unauthorized(context: &DbTestContext) {
+    let response = context.app.request("/notes").send().await;
+
+    assert_that!(response.status(), eq(StatusCode::UNAUTHORIZED));
+}
+
 #[db_test]
 async fn test_create_invalid(context: &DbTestContext) {
+    let user = create_fake_user(&context.db_pool).await;
     let payload = json!(entities::notes::NoteChangeset {
-        text: String::from("")
+        text: String::from(""),
+        tags: None,
+        format: None,
     });
 
     let response = context
@@ -25,6 +38,7 @@ async fn test_create_invalid(context: &DbTestContext) {
         .method(Method::POST)
         .body(Body::from(payload.to_string()))
         .header(http::header::CONTENT_TYPE, "application/json")
+        .header(http::header::AUTHORIZATION, &context.bearer_token(&user))
         .send()
         .await;
 
@@ -33,6 +47,7 @@ async fn test_create_invalid(context: &DbTestContext) {
 
 #[db_test]
 async fn test_create_success(context: &DbTestContext) {
+    let user = create_fake_user(&context.db_pool).await;
     let changeset: entities::notes::NoteChangeset = Faker.fake();
     let payload = json!(changeset);
 
@@ -42,40 +57,183 @@ async fn test_create_success(context: &DbTestContext) {
         .method(Method::POST)
         .body(Body::from(payload.to_string()))
         .header(http::header::CONTENT_TYPE, "application/json")
+        .header(http::header::AUTHORIZATION, &context.bearer_token(&user))
         .send()
         .await;
 
     assert_that!(response.status(), eq(StatusCode::CREATED));
 
-    let notes = entities::notes::load_all(&context.db_pool).await.unwrap();
+    let notes = entities::notes::load_all(user.id, &context.db_pool)
+        .await
+        .unwrap();
     assert_that!(notes, len(eq(1)));
     assert_that!(notes.first().unwrap().text, eq(&changeset.text));
+    assert_that!(notes.first().unwrap().owner_id, eq(user.id));
 }
 
 #[db_test]
 async fn test_read_all(context: &DbTestContext) {
+    let user = create_fake_user(&context.db_pool).await;
+    let other_user = create_fake_user(&context.db_pool).await;
     let changeset: entities::notes::NoteChangeset = Faker.fake();
-    entities::notes::create(changeset.clone(), &context.db_pool)
+    entities::notes::create(changeset.clone(), user.id, &context.db_pool)
+        .await
+        .unwrap();
+    let other_changeset: entities::notes::NoteChangeset = Faker.fake();
+    entities::notes::create(other_changeset, other_user.id, &context.db_pool)
         .await
         .unwrap();
 
-    let response = context.app.request("/notes").send().await;
+    let response = context
+        .app
+        .request("/notes")
+        .header(http::header::AUTHORIZATION, &context.bearer_token(&user))
+        .send()
+        .await;
 
     assert_that!(response.status(), eq(StatusCode::OK));
 
-    let notes: Vec<entities::notes::Note> = response
-        .into_body()
-        .into_json::<Vec<entities::notes::Note>>()
+    let page: NotesPage = response.into_body().into_json::<NotesPage>().await;
+    assert_that!(page.items, len(eq(1)));
+    assert_that!(page.items.first().unwrap().text, eq(&changeset.text));
+    assert_that!(page.next_cursor, none());
+}
+
+async fn read_page(context: &DbTestContext, user: &User, query: &str) -> NotesPage {
+    let response = context
+        .app
+        .request(&format!("/notes?{}", query))
+        .header(http::header::AUTHORIZATION, &context.bearer_token(user))
+        .send()
         .await;
-    assert_that!(notes, len(eq(1)));
-    assert_that!(notes.first().unwrap().text, eq(&changeset.text));
+    assert_that!(response.status(), eq(StatusCode::OK));
+
+    response.into_body().into_json::<NotesPage>().await
+}
+
+async fn create_notes(context: &DbTestContext, user: &User, texts: &[&str]) -> Vec<Uuid> {
+    let mut ids = vec![];
+    for text in texts {
+        let note = create_note(text, user.id, &context.db_pool).await;
+        ids.push(note.id);
+    }
+    ids
+}
+
+#[db_test]
+async fn test_read_all_paginated(context: &DbTestContext) {
+    let user = create_fake_user(&context.db_pool).await;
+    let ids = create_notes(context, &user, &["one", "two", "three", "four", "five"]).await;
+
+    let first = read_page(context, &user, "limit=2").await;
+    let second = read_page(
+        context,
+        &user,
+        &format!("limit=2&cursor={}", first.next_cursor.as_ref().unwrap()),
+    )
+    .await;
+    let third = read_page(
+        context,
+        &user,
+        &format!("limit=2&cursor={}", second.next_cursor.as_ref().unwrap()),
+    )
+    .await;
+
+    let page_ids = |page: &NotesPage| page.items.iter().map(|n| n.id).collect::<Vec<_>>();
+    assert_that!(page_ids(&first), eq(&vec![ids[4], ids[3]]));
+    assert_that!(page_ids(&second), eq(&vec![ids[2], ids[1]]));
+    assert_that!(page_ids(&third), eq(&vec![ids[0]]));
+    assert_that!(third.next_cursor, none());
+}
+
+#[db_test]
+async fn test_read_all_ascending(context: &DbTestContext) {
+    let user = create_fake_user(&context.db_pool).await;
+    let ids = create_notes(context, &user, &["one", "two", "three"]).await;
+
+    let first = read_page(context, &user, "limit=2&order=asc").await;
+    let second = read_page(
+        context,
+        &user,
+        &format!(
+            "limit=2&order=asc&cursor={}",
+            first.next_cursor.as_ref().unwrap()
+        ),
+    )
+    .await;
+
+    let page_ids = |page: &NotesPage| page.items.iter().map(|n| n.id).collect::<Vec<_>>();
+    assert_that!(page_ids(&first), eq(&vec![ids[0], ids[1]]));
+    assert_that!(page_ids(&second), eq(&vec![ids[2]]));
+    assert_that!(second.next_cursor, none());
+}
+
+#[db_test]
+async fn test_read_all_filtered(context: &DbTestContext) {
+    let user = create_fake_user(&context.db_pool).await;
+    create_notes(
+        context,
+        &user,
+        &["Buy milk", "buy bread", "100% done", "call mom"],
+    )
+    .await;
+
+    let page = read_page(context, &user, "text=BUY").await;
+    let mut texts: Vec<String> = page.items.into_iter().map(|n| n.text).collect();
+    texts.sort();
+    assert_that!(
+        texts,
+        eq(&vec![String::from("Buy milk"), String::from("buy bread")])
+    );
+
+    let page = read_page(context, &user, "text=%25").await;
+    assert_that!(page.items, len(eq(1)));
+    assert_that!(page.items.first().unwrap().text, eq("100% done"));
+}
+
+#[db_test]
+async fn test_read_all_invalid_query(context: &DbTestContext) {
+    let user = create_fake_user(&context.db_pool).await;
+
+    for query in ["limit=0", "limit=101", "cursor=nonsense", "cursor=6162"] {
+        let response = context
+            .app
+            .request(&format!("/notes?{}", query))
+            .header(http::header::AUTHORIZATION, &context.bearer_token(&user))
+            .send()
+            .await;
+
+        assert_that!(response.status(), eq(StatusCode::UNPROCESSABLE_ENTITY));
+    }
 }
 
 #[db_test]
 async fn test_read_one_nonexistent(context: &DbTestContext) {
+    let user = create_fake_user(&context.db_pool).await;
+
     let response = context
         .app
         .request(&format!("/notes/{}", Uuid::new_v4()))
+        .header(http::header::AUTHORIZATION, &context.bearer_token(&user))
+        .send()
+        .await;
+
+    assert_that!(response.status(), eq(StatusCode::NOT_FOUND));
+}
+
+#[db_test]
+async fn test_read_one_other_owner(context: &DbTestContext) {
+    let user = create_fake_user(&context.db_pool).await;
+    let other_user = create_fake_user(&context.db_pool).await;
+    let note_changeset: entities::notes::NoteChangeset = Faker.fake();
+    let note = entities::notes::create(note_changeset, other_user.id, &context.db_pool)
+        .await
+        .unwrap();
+
+    let response = context
+        .app
+        .request(&format!("/notes/{}", note.id))
+        .header(http::header::AUTHORIZATION, &context.bearer_token(&user))
         .send()
         .await;
 
@@ -84,8 +242,9 @@ async fn test_read_one_nonexistent(context: &DbTestContext) {
 
 #[db_test]
 async fn test_read_one_success(context: &DbTestContext) {
+    let user = create_fake_user(&context.db_pool).await;
     let note_changeset: entities::notes::NoteChangeset = Faker.fake();
-    let note = entities::notes::create(note_changeset.clone(), &context.db_pool)
+    let note = entities::notes::create(note_changeset.clone(), user.id, &context.db_pool)
         .await
         .unwrap();
     let note_id = note.id;
@@ -93,6 +252,7 @@ async fn test_read_one_success(context: &DbTestContext) {
     let response = context
         .app
         .request(&format!("/notes/{}", note_id))
+        .header(http::header::AUTHORIZATION, &context.bearer_token(&user))
         .send()
         .await;
 
@@ -108,33 +268,31 @@ async fn test_read_one_success(context: &DbTestContext) {
 
 #[db_test]
 async fn test_update_invalid(context: &DbTestContext) {
+    let user = create_fake_user(&context.db_pool).await;
     let note_changeset: entities::notes::NoteChangeset = Faker.fake();
-    let note = entities::notes::create(note_changeset.clone(), &context.db_pool)
+    let note = entities::notes::create(note_changeset.clone(), user.id, &context.db_pool)
         .await
         .unwrap();
 
     let payload = json!(entities::notes::NoteChangeset {
-        text: String::from("")
+        text: String::from(""),
+        tags: None,
+        format: None,
     });
 
-    let user_changeset: UserChangeset = Faker.fake();
-    create_user(user_changeset.clone(), &context.db_pool)
-        .await
-        .unwrap();
-
     let response = context
         .app
         .request(&format!("/notes/{}", note.id))
         .method(Method::PUT)
         .body(Body::from(payload.to_string()))
-        .header(http::header::AUTHORIZATION, &user_changeset.token)
+        .header(http::header::AUTHORIZATION, &context.bearer_token(&user))
         .header(http::header::CONTENT_TYPE, "application/json")
         .send()
         .await;
 
     assert_that!(response.status(), eq(StatusCode::UNPROCESSABLE_ENTITY));
 
-    let note_after = entities::notes::load(note.id, &context.db_pool)
+    let note_after = entities::notes::load(note.id, user.id, &context.db_pool)
         .await
         .unwrap();
     assert_that!(note_after.text, eq(&note.text));
@@ -142,21 +300,17 @@ async fn test_update_invalid(context: &DbTestContext) {
 
 #[db_test]
 async fn test_update_nonexistent(context: &DbTestContext) {
+    let user = create_fake_user(&context.db_pool).await;
     let note_changeset: entities::notes::NoteChangeset = Faker.fake();
     let payload = json!(note_changeset);
 
-    let user_changeset: UserChangeset = Faker.fake();
-    create_user(user_changeset.clone(), &context.db_pool)
-        .await
-        .unwrap();
-
     let response = context
         .app
         .request(&format!("/notes/{}", Uuid::new_v4()))
         .method(Method::PUT)
         .body(Body::from(payload.to_string()))
         .header(http::header::CONTENT_TYPE, "application/json")
-        .header(http::header::AUTHORIZATION, &user_changeset.token)
+        .header(http::header::AUTHORIZATION, &context.bearer_token(&user))
         .send()
         .await;
 
@@ -164,25 +318,51 @@ async fn test_update_nonexistent(context: &DbTestContext) {
 }
 
 #[db_test]
-async fn test_update_success(context: &DbTestContext) {
+async fn test_update_other_owner(context: &DbTestContext) {
+    let user = create_fake_user(&context.db_pool).await;
+    let other_user = create_fake_user(&context.db_pool).await;
     let note_changeset: entities::notes::NoteChangeset = Faker.fake();
-    let note = entities::notes::create(note_changeset.clone(), &context.db_pool)
+    let note = entities::notes::create(note_changeset, other_user.id, &context.db_pool)
         .await
         .unwrap();
 
     let note_changeset: entities::notes::NoteChangeset = Faker.fake();
     let payload = json!(note_changeset);
 
-    let user_changeset: UserChangeset = Faker.fake();
-    create_user(user_changeset.clone(), &context.db_pool)
+    let response = context
+        .app
+        .request(&format!("/notes/{}", note.id))
+        .method(Method::PUT)
+        .body(Body::from(payload.to_string()))
+        .header(http::header::CONTENT_TYPE, "application/json")
+        .header(http::header::AUTHORIZATION, &context.bearer_token(&user))
+        .send()
+        .await;
+
+    assert_that!(response.status(), eq(StatusCode::NOT_FOUND));
+
+    let note_after = entities::notes::load(note.id, other_user.id, &context.db_pool)
+        .await
+        .unwrap();
+    assert_that!(note_after.text, eq(&note.text));
+}
+
+#[db_test]
+async fn test_update_success(context: &DbTestContext) {
+    let user = create_fake_user(&context.db_pool).await;
+    let note_changeset: entities::notes::NoteChangeset = Faker.fake();
+    let note = entities::notes::create(note_changeset.clone(), user.id, &context.db_pool)
         .await
         .unwrap();
 
+    let note_changeset: entities::notes::NoteChangeset = Faker.fake();
+    let payload = json!(note_changeset);
+
     let response = context
         .app
         .request(&format!("/notes/{}", note.id))
         .method(Method::PUT)
-        .header(http::header::AUTHORIZATION, &user_changeset.token)
+        .header(http::header::AUTHORIZATION, &context.bearer_token(&user))
         .body(Body::from(payload.to_string()))
         .header(http::header::CONTENT_TYPE, "application/json")
         .send()
@@ -196,7 +376,7 @@ async fn test_update_success(context: &DbTestContext) {
         .await;
     assert_that!(note.text, eq(&note_changeset.text.clone()));
 
-    let note = entities::notes::load(note.id, &context.db_pool)
+    let note = entities::notes::load(note.id, user.id, &context.db_pool)
         .await
         .unwrap();
     assert_that!(note.text, eq(&note_changeset.text));
@@ -204,16 +384,13 @@ async fn test_update_success(context: &DbTestContext) {
 
 #[db_test]
 async fn test_delete_nonexistent(context: &DbTestContext) {
-    let user_changeset: UserChangeset = Faker.fake();
-    create_user(user_changeset.clone(), &context.db_pool)
-        .await
-        .unwrap();
+    let user = create_fake_user(&context.db_pool).await;
 
     let response = context
         .app
         .request(&format!("/notes/{}", Uuid::new_v4()))
         .method(Method::DELETE)
-        .header(http::header::AUTHORIZATION, &user_changeset.token)
+        .header(http::header::AUTHORIZATION, &context.bearer_token(&user))
         .send()
         .await;
 
@@ -221,14 +398,33 @@ async fn test_delete_nonexistent(context: &DbTestContext) {
 }
 
 #[db_test]
-async fn test_delete_success(context: &DbTestContext) {
+async fn test_delete_other_owner(context: &DbTestContext) {
+    let user = create_fake_user(&context.db_pool).await;
+    let other_user = create_fake_user(&context.db_pool).await;
     let note_changeset: entities::notes::NoteChangeset = Faker.fake();
-    let note = entities::notes::create(note_changeset.clone(), &context.db_pool)
+    let note = entities::notes::create(note_changeset, other_user.id, &context.db_pool)
         .await
         .unwrap();
 
-    let user_changeset: UserChangeset = Faker.fake();
-    create_user(user_changeset.clone(), &context.db_pool)
+    let response = context
+        .app
+        .request(&format!("/notes/{}", note.id))
+        .method(Method::DELETE)
+        .header(http::header::AUTHORIZATION, &context.bearer_token(&user))
+        .send()
+        .await;
+
+    assert_that!(response.status(), eq(StatusCode::NOT_FOUND));
+
+    let result = entities::notes::load(note.id, other_user.id, &context.db_pool).await;
+    assert_that!(result, ok(anything()));
+}
+
+#[db_test]
+async fn test_delete_success(context: &DbTestContext) {
+    let user = create_fake_user(&context.db_pool).await;
+    let note_changeset: entities::notes::NoteChangeset = Faker.fake();
+    let note = entities::notes::create(note_changeset.clone(), user.id, &context.db_pool)
         .await
         .unwrap();
 
@@ -236,12 +432,163 @@ async fn test_delete_success(context: &DbTestContext) {
         .app
         .request(&format!("/notes/{}", note.id))
         .method(Method::DELETE)
-        .header(http::header::AUTHORIZATION, &user_changeset.token)
+        .header(http::header::AUTHORIZATION, &context.bearer_token(&user))
         .send()
         .await;
 
     assert_that!(response.status(), eq(StatusCode::NO_CONTENT));
 
-    let result = entities::notes::load(note.id, &context.db_pool).await;
+    let result = entities::notes::load(note.id, user.id, &context.db_pool).await;
     assert_that!(result, err(anything()));
 }
+
+#[db_test]
+async fn test_openapi_documents_pagination(context: &DbTestContext) {
+    let response = context.app.request("/api-docs/openapi.json").send().await;
+
+    assert_that!(response.status(), eq(StatusCode::OK));
+    let openapi: Value = response.into_body().into_json().await;
+    let params: Vec<&str> = openapi["paths"]["/notes"]["get"]["parameters"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|param| param["name"].as_str().unwrap())
+        .collect();
+    assert_that!(
+        params,
+        unordered_elements_are![
+            eq(&"limit"),
+            eq(&"cursor"),
+            eq(&"order"),
+            eq(&"text"),
+            eq(&"tag"),
+            eq(&"tag_mode")
+        ]
+    );
+    assert_that!(
+        openapi["components"]["schemas"]["NotesPage"],
+        not(eq(&Value::Null))
+    );
+}
+
+#[db_test]
+async fn test_read_one_etag(context: &DbTestContext) {
+    let user = create_fake_user(&context.db_pool).await;
+    let note_changeset: entities::notes::NoteChangeset = Faker.fake();
+    let note = entities::notes::create(note_changeset, user.id, &context.db_pool)
+        .await
+        .unwrap();
+
+    let response = context
+        .app
+        .request(&format!("/notes/{}", note.id))
+        .header(http::header::AUTHORIZATION, &context.bearer_token(&user))
+        .send()
+        .await;
+
+    assert_that!(response.status(), eq(StatusCode::OK));
+    assert_that!(response.headers()[http::header::ETAG], eq("\"1\""));
+}
+
+async fn update_if_match(
+    context: &DbTestContext,
+    user: &User,
+    id: Uuid,
+    if_match: &str,
+) -> axum::response::Response {
+    let note_changeset: entities::notes::NoteChangeset = Faker.fake();
+
+    context
+        .app
+        .request(&format!("/notes/{}", id))
+        .method(Method::PUT)
+        .body(Body::from(json!(note_changeset).to_string()))
+        .header(http::header::CONTENT_TYPE, "application/json")
+        .header(http::header::IF_MATCH, if_match)
+        .header(http::header::AUTHORIZATION, &context.bearer_token(user))
+        .send()
+        .await
+}
+
+#[db_test]
+async fn test_update_if_match(context: &DbTestContext) {
+    let user = create_fake_user(&context.db_pool).await;
+    let note_changeset: entities::notes::NoteChangeset = Faker.fake();
+    let note = entities::notes::create(note_changeset, user.id, &context.db_pool)
+        .await
+        .unwrap();
+
+    let response = update_if_match(context, &user, note.id, "\"1\"").await;
+
+    assert_that!(response.status(), eq(StatusCode::OK));
+    assert_that!(response.headers()[http::header::ETAG], eq("\"2\""));
+    let updated: entities::notes::Note = response.into_body().into_json().await;
+    assert_that!(updated.version, eq(2));
+    assert_that!(updated.created_at, eq(note.created_at));
+    assert_that!(updated.updated_at, gt(note.updated_at));
+
+    let response = update_if_match(context, &user, note.id, "\"0\", \"2\"").await;
+    assert_that!(response.status(), eq(StatusCode::OK));
+
+    let response = update_if_match(context, &user, note.id, "*").await;
+    assert_that!(response.status(), eq(StatusCode::OK));
+}
+
+#[db_test]
+async fn test_update_if_match_stale(context: &DbTestContext) {
+    let user = create_fake_user(&context.db_pool).await;
+    let note_changeset: entities::notes::NoteChangeset = Faker.fake();
+    let note = entities::notes::create(note_changeset, user.id, &context.db_pool)
+        .await
+        .unwrap();
+    update_if_match(context, &user, note.id, "\"1\"").await;
+
+    for if_match in ["\"1\"", "W/\"2\"", "nonsense"] {
+        let response = update_if_match(context, &user, note.id, if_match).await;
+        assert_that!(response.status(), eq(StatusCode::PRECONDITION_FAILED));
+    }
+
+    let note_after = entities::notes::load(note.id, user.id, &context.db_pool)
+        .await
+        .unwrap();
+    assert_that!(note_after.version, eq(2));
+}
+
+#[db_test]
+async fn test_update_if_match_nonexistent(context: &DbTestContext) {
+    let user = create_fake_user(&context.db_pool).await;
+
+    let response = update_if_match(context, &user, Uuid::new_v4(), "\"1\"").await;
+
+    assert_that!(response.status(), eq(StatusCode::NOT_FOUND));
+}
+
+#[db_test]
+async fn test_delete_if_match(context: &DbTestContext) {
+    let user = create_fake_user(&context.db_pool).await;
+    let note_changeset: entities::notes::NoteChangeset = Faker.fake();
+    let note = entities::notes::create(note_changeset, user.id, &context.db_pool)
+        .await
+        .unwrap();
+    update_if_match(context, &user, note.id, "\"1\"").await;
+
+    let response = context
+        .app
+        .request(&format!("/notes/{}", note.id))
+        .method(Method::DELETE)
+        .header(http::header::IF_MATCH, "\"1\"")
+        .header(http::header::AUTHORIZATION, &context.bearer_token(&user))
+        .send()
+        .await;
+    assert_that!(response.status(), eq(StatusCode::PRECONDITION_FAILED));
+
+    let response = context
+        .app
+        .request(&format!("/notes/{}", note.id))
+        .method(Method::DELETE)
+        .header(http::header::IF_MATCH, "\"2\"")
+        .header(http::header::AUTHORIZATION, &context.bearer_token(&user))
+        .send()
+        .await;
+    assert_that!(response.status(), eq(StatusCode::NO_CONTENT));
+}
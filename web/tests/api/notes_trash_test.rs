@@ -0,0 +1,176 @@
+use axum::http::{self, Method};
+use chrono::{Duration, Utc};
+use fake::{Fake, Faker};
+use forge_api_db::entities::notes::{self, Note, NoteChangeset, TrashedNote};
+use forge_api_db::entities::users::User;
+use forge_api_db::test_helpers::users::create_fake as create_fake_user;
+use forge_api_macros::db_test;
+use forge_api_web::controllers::notes::NotesPage;
+use forge_api_web::test_helpers::{BodyExt, DbTestContext, RouterExt};
+use googletest::prelude::*;
+use hyper::StatusCode;
+
+async fn create_trashed_note(context: &DbTestContext, user: &User) -> Note {
+    let note_changeset: NoteChangeset = Faker.fake();
+    let note = notes::create(note_changeset, user.id, &context.db_pool)
+        .await
+        .unwrap();
+    notes::delete(note.id, user.id, None, &context.db_pool)
+        .await
+        .unwrap();
+    note
+}
+
+async fn request(
+    context: &DbTestContext,
+    user: &User,
+    method: Method,
+    uri: &str,
+) -> axum::response::Response {
+    context
+        .app
+        .request(uri)
+        .method(method)
+        .header(http::header::AUTHORIZATION, &context.bearer_token(user))
+        .send()
+        .await
+}
+
+#[db_test]
+async fn test_delete_moves_to_trash(context: &DbTestContext) {
+    let user = create_fake_user(&context.db_pool).await;
+    let note_changeset: NoteChangeset = Faker.fake();
+    let note = notes::create(note_changeset, user.id, &context.db_pool)
+        .await
+        .unwrap();
+
+    let response = request(
+        context,
+        &user,
+        Method::DELETE,
+        &format!("/notes/{}", note.id),
+    )
+    .await;
+    assert_that!(response.status(), eq(StatusCode::NO_CONTENT));
+
+    let response = request(context, &user, Method::GET, &format!("/notes/{}", note.id)).await;
+    assert_that!(response.status(), eq(StatusCode::NOT_FOUND));
+
+    let response = request(context, &user, Method::GET, "/notes").await;
+    let page: NotesPage = response.into_body().into_json().await;
+    assert_that!(page.items, empty());
+
+    let response = request(context, &user, Method::GET, "/notes/trash").await;
+    assert_that!(response.status(), eq(StatusCode::OK));
+    let trash: Vec<TrashedNote> = response.into_body().into_json().await;
+    assert_that!(trash, len(eq(1)));
+    assert_that!(trash[0].note.id, eq(note.id));
+}
+
+#[db_test]
+async fn test_trashed_notes_cannot_be_changed(context: &DbTestContext) {
+    let user = create_fake_user(&context.db_pool).await;
+    let note = create_trashed_note(context, &user).await;
+
+    let result = notes::update(note.id, Faker.fake(), user.id, None, &context.db_pool).await;
+    assert_that!(result, err(anything()));
+
+    let response = request(
+        context,
+        &user,
+        Method::DELETE,
+        &format!("/notes/{}", note.id),
+    )
+    .await;
+    assert_that!(response.status(), eq(StatusCode::NOT_FOUND));
+}
+
+#[db_test]
+async fn test_trash_only_own_notes(context: &DbTestContext) {
+    let user = create_fake_user(&context.db_pool).await;
+    let other_user = create_fake_user(&context.db_pool).await;
+    let note = create_trashed_note(context, &other_user).await;
+
+    let response = request(context, &user, Method::GET, "/notes/trash").await;
+    let trash: Vec<TrashedNote> = response.into_body().into_json().await;
+    assert_that!(trash, empty());
+
+    let response = request(
+        context,
+        &user,
+        Method::POST,
+        &format!("/notes/{}/restore", note.id),
+    )
+    .await;
+    assert_that!(response.status(), eq(StatusCode::NOT_FOUND));
+}
+
+#[db_test]
+async fn test_restore(context: &DbTestContext) {
+    let user = create_fake_user(&context.db_pool).await;
+    let note = create_trashed_note(context, &user).await;
+
+    let response = request(
+        context,
+        &user,
+        Method::POST,
+        &format!("/notes/{}/restore", note.id),
+    )
+    .await;
+    assert_that!(response.status(), eq(StatusCode::OK));
+    let restored: Note = response.into_body().into_json().await;
+    assert_that!(restored.text, eq(&note.text));
+
+    let response = request(context, &user, Method::GET, &format!("/notes/{}", note.id)).await;
+    assert_that!(response.status(), eq(StatusCode::OK));
+
+    let response = request(context, &user, Method::GET, "/notes/trash").await;
+    let trash: Vec<TrashedNote> = response.into_body().into_json().await;
+    assert_that!(trash, empty());
+}
+
+#[db_test]
+async fn test_restore_not_trashed(context: &DbTestContext) {
+    let user = create_fake_user(&context.db_pool).await;
+    let note_changeset: NoteChangeset = Faker.fake();
+    let note = notes::create(note_changeset, user.id, &context.db_pool)
+        .await
+        .unwrap();
+
+    let response = request(
+        context,
+        &user,
+        Method::POST,
+        &format!("/notes/{}/restore", note.id),
+    )
+    .await;
+
+    assert_that!(response.status(), eq(StatusCode::NOT_FOUND));
+}
+
+#[db_test]
+async fn test_purge_trashed(context: &DbTestContext) {
+    let user = create_fake_user(&context.db_pool).await;
+    let note = create_trashed_note(context, &user).await;
+    let note_changeset: NoteChangeset = Faker.fake();
+    let kept = notes::create(note_changeset, user.id, &context.db_pool)
+        .await
+        .unwrap();
+
+    let purged = notes::purge_trashed(Utc::now() - Duration::days(1), &context.db_pool)
+        .await
+        .unwrap();
+    assert_that!(purged.count, eq(0));
+
+    let purged = notes::purge_trashed(Utc::now() + Duration::minutes(1), &context.db_pool)
+        .await
+        .unwrap();
+    assert_that!(purged.count, eq(1));
+
+    let trash = notes::load_trash(user.id, &context.db_pool).await.unwrap();
+    assert_that!(trash, empty());
+    let result = notes::restore(note.id, user.id, &context.db_pool).await;
+    assert_that!(result, err(anything()));
+    let result = notes::load(kept.id, user.id, &context.db_pool).await;
+    assert_that!(result, ok(anything()));
+}
@@ -0,0 +1,146 @@
+use axum::{
+    body::Body,
+    http::{self, Method},
+};
+use fake::{Fake, Faker};
+use forge_api_config::PasswordHashingConfig;
+use forge_api_db::entities::users::{self, User};
+use forge_api_db::test_helpers::users::{create as create_user, UserChangeset};
+use forge_api_macros::db_test;
+use forge_api_web::auth::LoginRequest;
+use forge_api_web::passwords::{PasswordHashing, Verification};
+use forge_api_web::test_helpers::{DbTestContext, RouterExt};
+use googletest::prelude::*;
+use hyper::StatusCode;
+use serde_json::json;
+
+const PASSWORD: &str = "correct horse battery staple";
+
+async fn create_user_with_hash(context: &DbTestContext, pass: String) -> User {
+    let user_changeset = UserChangeset {
+        pass,
+        ..Faker.fake()
+    };
+    create_user(user_changeset, &context.db_pool).await.unwrap()
+}
+
+async fn login(context: &DbTestContext, user: &User, password: &str) -> StatusCode {
+    let payload = json!(LoginRequest {
+        name: user.name.clone(),
+        password: String::from(password),
+    });
+
+    context
+        .app
+        .request("/login")
+        .method(Method::POST)
+        .body(Body::from(payload.to_string()))
+        .header(http::header::CONTENT_TYPE, "application/json")
+        .send()
+        .await
+        .status()
+}
+
+async fn stored_hash(context: &DbTestContext, user: &User) -> String {
+    users::find_user_by_name(&user.name, &context.db_pool)
+        .await
+        .unwrap()
+        .unwrap()
+        .pass
+}
+
+#[db_test]
+async fn test_login_upgrades_bcrypt_hash(context: &DbTestContext) {
+    let user = create_user_with_hash(context, bcrypt::hash(PASSWORD, 4).unwrap()).await;
+
+    assert_that!(login(context, &user, PASSWORD).await, eq(StatusCode::OK));
+
+    let hash = stored_hash(context, &user).await;
+    assert_that!(hash, starts_with("$argon2id$"));
+    assert_that!(
+        context
+            .password_hashing
+            .verify(String::from(PASSWORD), Some(hash))
+            .await
+            .unwrap(),
+        eq(Verification::Valid)
+    );
+    assert_that!(login(context, &user, PASSWORD).await, eq(StatusCode::OK));
+}
+
+#[db_test]
+async fn test_login_upgrades_hash_with_outdated_parameters(context: &DbTestContext) {
+    let outdated = PasswordHashing::from_config(&PasswordHashingConfig {
+        memory_kib: 2048,
+        iterations: 1,
+        parallelism: 1,
+    })
+    .unwrap();
+    let outdated_hash = outdated.hash(String::from(PASSWORD)).await.unwrap();
+    let user = create_user_with_hash(context, outdated_hash.clone()).await;
+
+    assert_that!(login(context, &user, PASSWORD).await, eq(StatusCode::OK));
+
+    let hash = stored_hash(context, &user).await;
+    assert_that!(hash, not(eq(&outdated_hash)));
+    assert_that!(
+        context
+            .password_hashing
+            .verify(String::from(PASSWORD), Some(hash))
+            .await
+            .unwrap(),
+        eq(Verification::Valid)
+    );
+}
+
+#[db_test]
+async fn test_login_keeps_current_hash(context: &DbTestContext) {
+    let current_hash = context
+        .password_hashing
+        .hash(String::from(PASSWORD))
+        .await
+        .unwrap();
+    let user = create_user_with_hash(context, current_hash.clone()).await;
+
+    assert_that!(login(context, &user, PASSWORD).await, eq(StatusCode::OK));
+
+    assert_that!(stored_hash(context, &user).await, eq(&current_hash));
+}
+
+#[db_test]
+async fn test_failed_login_keeps_legacy_hash(context: &DbTestContext) {
+    let legacy_hash = bcrypt::hash(PASSWORD, 4).unwrap();
+    let user = create_user_with_hash(context, legacy_hash.clone()).await;
+
+    assert_that!(
+        login(context, &user, "wrong password").await,
+        eq(StatusCode::UNAUTHORIZED)
+    );
+
+    assert_that!(stored_hash(context, &user).await, eq(&legacy_hash));
+}
+
+#[db_test]
+async fn test_login_with_corrupted_hash(context: &DbTestContext) {
+    let current_hash = context
+        .password_hashing
+        .hash(String::from(PASSWORD))
+        .await
+        .unwrap();
+
+    for corrupted_hash in [
+        String::new(),
+        String::from("not a hash"),
+        current_hash.replace("$argon2id$", "$pbkdf2-sha256$"),
+        current_hash.replace("m=", "m=x"),
+        current_hash[..current_hash.len() - 10].to_string(),
+    ] {
+        let user = create_user_with_hash(context, corrupted_hash.clone()).await;
+
+        assert_that!(
+            login(context, &user, PASSWORD).await,
+            eq(StatusCode::UNAUTHORIZED)
+        );
+        assert_that!(stored_hash(context, &user).await, eq(&corrupted_hash));
+    }
+}
@@ -0,0 +1,207 @@
+use axum::{
+    body::Body,
+    http::{self, Method},
+};
+use fake::{Fake, Faker};
+use forge_api_db::entities::{self, roles, users::User};
+use forge_api_db::test_helpers::roles::create as create_role;
+use forge_api_db::test_helpers::users::create_fake as create_fake_user;
+use forge_api_macros::db_test;
+use forge_api_web::test_helpers::{DbTestContext, RouterExt};
+use googletest::prelude::*;
+use hyper::StatusCode;
+use serde_json::json;
+
+const NOTES_PERMISSIONS: [&str; 4] = ["notes:read", "notes:create", "notes:update", "notes:delete"];
+
+async fn create_user_with_permissions(context: &DbTestContext, permissions: &[&str]) -> User {
+    let user = create_fake_user(&context.db_pool).await;
+    roles::unassign(user.id, roles::DEFAULT_ROLE, &context.db_pool)
+        .await
+        .unwrap();
+
+    let role = create_role(permissions, &context.db_pool).await.unwrap();
+    roles::assign(user.id, &role, &context.db_pool)
+        .await
+        .unwrap();
+
+    user
+}
+
+async fn create_user_without_permission(context: &DbTestContext, permission: &str) -> User {
+    let permissions: Vec<&str> = NOTES_PERMISSIONS
+        .into_iter()
+        .filter(|p| *p != permission)
+        .collect();
+    create_user_with_permissions(context, &permissions).await
+}
+
+async fn create_note(context: &DbTestContext, user: &User) -> entities::notes::Note {
+    let note_changeset: entities::notes::NoteChangeset = Faker.fake();
+    entities::notes::create(note_changeset, user.id, &context.db_pool)
+        .await
+        .unwrap()
+}
+
+async fn send(context: &DbTestContext, user: &User, method: Method, uri: &str) -> StatusCode {
+    let note_changeset: entities::notes::NoteChangeset = Faker.fake();
+
+    let response = context
+        .app
+        .request(uri)
+        .method(method)
+        .body(Body::from(json!(note_changeset).to_string()))
+        .header(http::header::CONTENT_TYPE, "application/json")
+        .header(http::header::AUTHORIZATION, &context.bearer_token(user))
+        .send()
+        .await;
+
+    response.status()
+}
+
+#[db_test]
+async fn test_no_roles(context: &DbTestContext) {
+    let user = create_user_with_permissions(context, &[]).await;
+
+    let status = send(context, &user, Method::GET, "/notes").await;
+
+    assert_that!(status, eq(StatusCode::FORBIDDEN));
+}
+
+#[db_test]
+async fn test_read_all_requires_read_permission(context: &DbTestContext) {
+    let user = create_user_without_permission(context, "notes:read").await;
+    let status = send(context, &user, Method::GET, "/notes").await;
+    assert_that!(status, eq(StatusCode::FORBIDDEN));
+
+    let user = create_user_with_permissions(context, &["notes:read"]).await;
+    let status = send(context, &user, Method::GET, "/notes").await;
+    assert_that!(status, eq(StatusCode::OK));
+}
+
+#[db_test]
+async fn test_create_requires_create_permission(context: &DbTestContext) {
+    let user = create_user_without_permission(context, "notes:create").await;
+    let status = send(context, &user, Method::POST, "/notes").await;
+    assert_that!(status, eq(StatusCode::FORBIDDEN));
+
+    let user = create_user_with_permissions(context, &["notes:create"]).await;
+    let status = send(context, &user, Method::POST, "/notes").await;
+    assert_that!(status, eq(StatusCode::CREATED));
+}
+
+#[db_test]
+async fn test_read_one_requires_read_permission(context: &DbTestContext) {
+    let user = create_user_without_permission(context, "notes:read").await;
+    let note = create_note(context, &user).await;
+    let status = send(context, &user, Method::GET, &format!("/notes/{}", note.id)).await;
+    assert_that!(status, eq(StatusCode::FORBIDDEN));
+
+    let user = create_user_with_permissions(context, &["notes:read"]).await;
+    let note = create_note(context, &user).await;
+    let status = send(context, &user, Method::GET, &format!("/notes/{}", note.id)).await;
+    assert_that!(status, eq(StatusCode::OK));
+}
+
+#[db_test]
+async fn test_update_requires_update_permission(context: &DbTestContext) {
+    let user = create_user_without_permission(context, "notes:update").await;
+    let note = create_note(context, &user).await;
+    let status = send(context, &user, Method::PUT, &format!("/notes/{}", note.id)).await;
+    assert_that!(status, eq(StatusCode::FORBIDDEN));
+
+    let user = create_user_with_permissions(context, &["notes:update"]).await;
+    let note = create_note(context, &user).await;
+    let status = send(context, &user, Method::PUT, &format!("/notes/{}", note.id)).await;
+    assert_that!(status, eq(StatusCode::OK));
+}
+
+#[db_test]
+async fn test_delete_requires_delete_permission(context: &DbTestContext) {
+    let user = create_user_without_permission(context, "notes:delete").await;
+    let note = create_note(context, &user).await;
+    let status = send(
+        context,
+        &user,
+        Method::DELETE,
+        &format!("/notes/{}", note.id),
+    )
+    .await;
+    assert_that!(status, eq(StatusCode::FORBIDDEN));
+
+    let user = create_user_with_permissions(context, &["notes:delete"]).await;
+    let note = create_note(context, &user).await;
+    let status = send(
+        context,
+        &user,
+        Method::DELETE,
+        &format!("/notes/{}", note.id),
+    )
+    .await;
+    assert_that!(status, eq(StatusCode::NO_CONTENT));
+}
+
+#[db_test]
+async fn test_search_requires_read_permission(context: &DbTestContext) {
+    let user = create_user_without_permission(context, "notes:read").await;
+    let status = send(context, &user, Method::GET, "/notes/search?q=milk").await;
+    assert_that!(status, eq(StatusCode::FORBIDDEN));
+
+    let user = create_user_with_permissions(context, &["notes:read"]).await;
+    let status = send(context, &user, Method::GET, "/notes/search?q=milk").await;
+    assert_that!(status, eq(StatusCode::OK));
+}
+
+#[db_test]
+async fn test_read_trash_requires_read_permission(context: &DbTestContext) {
+    let user = create_user_without_permission(context, "notes:read").await;
+    let status = send(context, &user, Method::GET, "/notes/trash").await;
+    assert_that!(status, eq(StatusCode::FORBIDDEN));
+
+    let user = create_user_with_permissions(context, &["notes:read"]).await;
+    let status = send(context, &user, Method::GET, "/notes/trash").await;
+    assert_that!(status, eq(StatusCode::OK));
+}
+
+#[db_test]
+async fn test_restore_requires_delete_permission(context: &DbTestContext) {
+    let user = create_user_without_permission(context, "notes:delete").await;
+    let note = create_note(context, &user).await;
+    entities::notes::delete(note.id, user.id, None, &context.db_pool)
+        .await
+        .unwrap();
+    let status = send(
+        context,
+        &user,
+        Method::POST,
+        &format!("/notes/{}/restore", note.id),
+    )
+    .await;
+    assert_that!(status, eq(StatusCode::FORBIDDEN));
+
+    let user = create_user_with_permissions(context, &["notes:delete"]).await;
+    let note = create_note(context, &user).await;
+    entities::notes::delete(note.id, user.id, None, &context.db_pool)
+        .await
+        .unwrap();
+    let status = send(
+        context,
+        &user,
+        Method::POST,
+        &format!("/notes/{}/restore", note.id),
+    )
+    .await;
+    assert_that!(status, eq(StatusCode::OK));
+}
+
+#[db_test]
+async fn test_unauthenticated(context: &DbTestContext) {
+    let response = context
+        .app
+        .request("/notes")
+        .method(Method::POST)
+        .send()
+        .await;
+
+    assert_that!(response.status(), eq(StatusCode::UNAUTHORIZED));
+}
@@ -0,0 +1,131 @@
+use axum::{
+    body::Body,
+    http::{self, Method},
+};
+use chrono::{Duration, Utc};
+use fake::{Fake, Faker};
+use forge_api_db::entities::refresh_tokens;
+use forge_api_db::test_helpers::users::{
+    create as create_user, create_fake as create_fake_user, UserChangeset,
+};
+use forge_api_macros::db_test;
+use forge_api_web::auth::TokenResponse;
+use forge_api_web::test_helpers::{BodyExt, DbTestContext, RouterExt};
+use forge_api_web::tokens;
+use googletest::prelude::*;
+use hyper::StatusCode;
+use serde_json::json;
+use uuid::Uuid;
+
+async fn login(context: &DbTestContext) -> String {
+    let password = "correct horse battery staple";
+    let user_changeset = UserChangeset {
+        pass: bcrypt::hash(password, 4).unwrap(),
+        ..Faker.fake()
+    };
+    let user = create_user(user_changeset, &context.db_pool).await.unwrap();
+
+    let response = context
+        .app
+        .request("/login")
+        .method(Method::POST)
+        .body(Body::from(
+            json!({ "name": user.name, "password": password }).to_string(),
+        ))
+        .header(http::header::CONTENT_TYPE, "application/json")
+        .send()
+        .await;
+    assert_that!(response.status(), eq(StatusCode::OK));
+
+    let tokens: TokenResponse = response.into_body().into_json().await;
+    tokens.refresh_token
+}
+
+async fn refresh(
+    context: &DbTestContext,
+    refresh_token: &str,
+) -> (StatusCode, Option<TokenResponse>) {
+    let response = context
+        .app
+        .request("/token/refresh")
+        .method(Method::POST)
+        .body(Body::from(
+            json!({ "refresh_token": refresh_token }).to_string(),
+        ))
+        .header(http::header::CONTENT_TYPE, "application/json")
+        .send()
+        .await;
+
+    let status = response.status();
+    if status == StatusCode::OK {
+        (status, Some(response.into_body().into_json().await))
+    } else {
+        (status, None)
+    }
+}
+
+#[db_test]
+async fn test_refresh_rotates_token(context: &DbTestContext) {
+    let refresh_token = login(context).await;
+
+    let (status, tokens) = refresh(context, &refresh_token).await;
+
+    assert_that!(status, eq(StatusCode::OK));
+    let tokens = tokens.unwrap();
+    assert_that!(tokens.token, not(eq("")));
+    assert_that!(tokens.refresh_token, not(eq(&refresh_token)));
+
+    let (status, _) = refresh(context, &tokens.refresh_token).await;
+    assert_that!(status, eq(StatusCode::OK));
+}
+
+#[db_test]
+async fn test_refresh_reuse_revokes_family(context: &DbTestContext) {
+    let refresh_token = login(context).await;
+    let (_, tokens) = refresh(context, &refresh_token).await;
+    let rotated_token = tokens.unwrap().refresh_token;
+
+    let (status, _) = refresh(context, &refresh_token).await;
+    assert_that!(status, eq(StatusCode::UNAUTHORIZED));
+
+    let (status, _) = refresh(context, &rotated_token).await;
+    assert_that!(status, eq(StatusCode::UNAUTHORIZED));
+}
+
+#[db_test]
+async fn test_refresh_reuse_keeps_other_families(context: &DbTestContext) {
+    let refresh_token = login(context).await;
+    let other_refresh_token = login(context).await;
+    refresh(context, &refresh_token).await;
+
+    refresh(context, &refresh_token).await;
+
+    let (status, _) = refresh(context, &other_refresh_token).await;
+    assert_that!(status, eq(StatusCode::OK));
+}
+
+#[db_test]
+async fn test_refresh_expired(context: &DbTestContext) {
+    let user = create_fake_user(&context.db_pool).await;
+    let refresh_token = tokens::generate();
+    refresh_tokens::create(
+        user.id,
+        Uuid::new_v4(),
+        &tokens::hash(&refresh_token),
+        Utc::now() - Duration::minutes(1),
+        &context.db_pool,
+    )
+    .await
+    .unwrap();
+
+    let (status, _) = refresh(context, &refresh_token).await;
+
+    assert_that!(status, eq(StatusCode::UNAUTHORIZED));
+}
+
+#[db_test]
+async fn test_refresh_unknown(context: &DbTestContext) {
+    let (status, _) = refresh(context, &tokens::generate()).await;
+
+    assert_that!(status, eq(StatusCode::UNAUTHORIZED));
+}
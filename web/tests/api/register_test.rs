@@ -0,0 +1,97 @@
+use axum::{
+    body::Body,
+    http::{self, Method},
+};
+use forge_api_db::entities::{roles, users};
+use forge_api_macros::db_test;
+use forge_api_web::auth::{RegisterRequest, UserResponse};
+use forge_api_web::passwords::Verification;
+use forge_api_web::test_helpers::{BodyExt, DbTestContext, RouterExt};
+use googletest::prelude::*;
+use hyper::StatusCode;
+use serde_json::json;
+
+async fn register(context: &DbTestContext, name: &str, password: &str) -> axum::response::Response {
+    let payload = json!(RegisterRequest {
+        name: String::from(name),
+        password: String::from(password),
+        email: None,
+    });
+
+    context
+        .app
+        .request("/register")
+        .method(Method::POST)
+        .body(Body::from(payload.to_string()))
+        .header(http::header::CONTENT_TYPE, "application/json")
+        .send()
+        .await
+}
+
+#[db_test]
+async fn test_register_success(context: &DbTestContext) {
+    let response = register(context, "jane.doe", "s3cret-password").await;
+
+    assert_that!(response.status(), eq(StatusCode::CREATED));
+    let user: UserResponse = response.into_body().into_json().await;
+    assert_that!(user.name, eq("jane.doe"));
+
+    let stored = users::find_user_by_name("jane.doe", &context.db_pool)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_that!(stored.id, eq(user.id));
+    assert_that!(stored.pass, starts_with("$argon2id$"));
+    assert_that!(
+        context
+            .password_hashing
+            .verify(String::from("s3cret-password"), Some(stored.pass))
+            .await
+            .unwrap(),
+        eq(Verification::Valid)
+    );
+    assert_that!(
+        roles::has_permission(stored.id, "notes:read", &context.db_pool)
+            .await
+            .unwrap(),
+        eq(true)
+    );
+}
+
+#[db_test]
+async fn test_register_name_taken(context: &DbTestContext) {
+    let response = register(context, "jane.doe", "s3cret-password").await;
+    assert_that!(response.status(), eq(StatusCode::CREATED));
+
+    let response = register(context, "Jane.Doe", "0ther-password").await;
+
+    assert_that!(response.status(), eq(StatusCode::CONFLICT));
+    let body = String::from_utf8(response.into_body().into_bytes().await.to_vec()).unwrap();
+    assert_that!(body, starts_with("name: "));
+}
+
+#[db_test]
+async fn test_register_invalid_name(context: &DbTestContext) {
+    let response = register(context, "jd", "s3cret-password").await;
+    assert_that!(response.status(), eq(StatusCode::UNPROCESSABLE_ENTITY));
+
+    let response = register(context, "jane doe", "s3cret-password").await;
+    assert_that!(response.status(), eq(StatusCode::UNPROCESSABLE_ENTITY));
+}
+
+#[db_test]
+async fn test_register_invalid_password(context: &DbTestContext) {
+    let response = register(context, "jane.doe", "s3cret").await;
+    assert_that!(response.status(), eq(StatusCode::UNPROCESSABLE_ENTITY));
+
+    let response = register(context, "jane.doe", "secret-password").await;
+    assert_that!(response.status(), eq(StatusCode::UNPROCESSABLE_ENTITY));
+
+    let response = register(context, "jane.doe", "123456789").await;
+    assert_that!(response.status(), eq(StatusCode::UNPROCESSABLE_ENTITY));
+
+    let stored = users::find_user_by_name("jane.doe", &context.db_pool)
+        .await
+        .unwrap();
+    assert_that!(stored, none());
+}
@@ -0,0 +1,181 @@
+use axum::{
+    body::Body,
+    http::{self, Method},
+};
+use fake::{Fake, Faker};
+use forge_api_db::entities::notes::{self, Note, NoteChangeset};
+use forge_api_db::entities::tags::TagUsage;
+use forge_api_db::entities::users::User;
+use forge_api_db::test_helpers::users::create_fake as create_fake_user;
+use forge_api_macros::db_test;
+use forge_api_web::controllers::notes::NotesPage;
+use forge_api_web::test_helpers::{BodyExt, DbTestContext, RouterExt};
+use googletest::prelude::*;
+use hyper::StatusCode;
+use serde_json::json;
+
+async fn create_note(context: &DbTestContext, user: &User, text: &str, tags: &[&str]) -> Note {
+    let changeset = NoteChangeset {
+        text: String::from(text),
+        tags: Some(tags.iter().map(|tag| String::from(*tag)).collect()),
+        ..Faker.fake()
+    };
+    notes::create(changeset, user.id, &context.db_pool)
+        .await
+        .unwrap()
+}
+
+async fn read_texts(context: &DbTestContext, user: &User, query: &str) -> Vec<String> {
+    let response = context
+        .app
+        .request(&format!("/notes?order=asc&{}", query))
+        .header(http::header::AUTHORIZATION, &context.bearer_token(user))
+        .send()
+        .await;
+    assert_that!(response.status(), eq(StatusCode::OK));
+
+    let page: NotesPage = response.into_body().into_json().await;
+    page.items.into_iter().map(|note| note.text).collect()
+}
+
+#[db_test]
+async fn test_create_with_tags(context: &DbTestContext) {
+    let user = create_fake_user(&context.db_pool).await;
+    create_note(context, &user, "first", &["Work"]).await;
+    let payload = json!({ "text": "second", "tags": ["urgent", " work ", "URGENT"] });
+
+    let response = context
+        .app
+        .request("/notes")
+        .method(Method::POST)
+        .body(Body::from(payload.to_string()))
+        .header(http::header::CONTENT_TYPE, "application/json")
+        .header(http::header::AUTHORIZATION, &context.bearer_token(&user))
+        .send()
+        .await;
+
+    assert_that!(response.status(), eq(StatusCode::CREATED));
+    let note: Note = response.into_body().into_json().await;
+    // Existing tags keep their spelling and duplicates are dropped.
+    assert_that!(note.tags, elements_are![eq("urgent"), eq("Work")]);
+
+    let note = notes::load(note.id, user.id, &context.db_pool)
+        .await
+        .unwrap();
+    assert_that!(note.tags, elements_are![eq("urgent"), eq("Work")]);
+}
+
+#[db_test]
+async fn test_create_with_invalid_tags(context: &DbTestContext) {
+    let user = create_fake_user(&context.db_pool).await;
+    let payload = json!({ "text": "note", "tags": ["a".repeat(33)] });
+
+    let response = context
+        .app
+        .request("/notes")
+        .method(Method::POST)
+        .body(Body::from(payload.to_string()))
+        .header(http::header::CONTENT_TYPE, "application/json")
+        .header(http::header::AUTHORIZATION, &context.bearer_token(&user))
+        .send()
+        .await;
+
+    assert_that!(response.status(), eq(StatusCode::UNPROCESSABLE_ENTITY));
+    let notes = notes::load_all(user.id, &context.db_pool).await.unwrap();
+    assert_that!(notes, empty());
+}
+
+#[db_test]
+async fn test_update_tags(context: &DbTestContext) {
+    let user = create_fake_user(&context.db_pool).await;
+    let note = create_note(context, &user, "note", &["a", "b"]).await;
+
+    let updated = notes::update(
+        note.id,
+        NoteChangeset {
+            text: String::from("changed"),
+            tags: None,
+            format: None,
+        },
+        user.id,
+        None,
+        &context.db_pool,
+    )
+    .await
+    .unwrap();
+    assert_that!(updated.tags, elements_are![eq("a"), eq("b")]);
+
+    let payload = json!({ "text": "changed again", "tags": ["b", "c"] });
+    let response = context
+        .app
+        .request(&format!("/notes/{}", note.id))
+        .method(Method::PUT)
+        .body(Body::from(payload.to_string()))
+        .header(http::header::CONTENT_TYPE, "application/json")
+        .header(http::header::AUTHORIZATION, &context.bearer_token(&user))
+        .send()
+        .await;
+
+    assert_that!(response.status(), eq(StatusCode::OK));
+    let updated: Note = response.into_body().into_json().await;
+    assert_that!(updated.tags, elements_are![eq("b"), eq("c")]);
+}
+
+#[db_test]
+async fn test_read_tags(context: &DbTestContext) {
+    let user = create_fake_user(&context.db_pool).await;
+    let other_user = create_fake_user(&context.db_pool).await;
+    create_note(context, &user, "one", &["work", "home"]).await;
+    create_note(context, &user, "two", &["Work"]).await;
+    let trashed = create_note(context, &user, "three", &["home", "later"]).await;
+    notes::delete(trashed.id, user.id, None, &context.db_pool)
+        .await
+        .unwrap();
+    create_note(context, &other_user, "other", &["work", "private"]).await;
+
+    let response = context
+        .app
+        .request("/tags")
+        .header(http::header::AUTHORIZATION, &context.bearer_token(&user))
+        .send()
+        .await;
+
+    assert_that!(response.status(), eq(StatusCode::OK));
+    let tags: Vec<TagUsage> = response.into_body().into_json().await;
+    assert_that!(
+        tags,
+        elements_are![
+            matches_pattern!(TagUsage {
+                name: eq("home"),
+                note_count: eq(&1)
+            }),
+            matches_pattern!(TagUsage {
+                name: eq("work"),
+                note_count: eq(&2)
+            })
+        ]
+    );
+}
+
+#[db_test]
+async fn test_read_all_filtered_by_tags(context: &DbTestContext) {
+    let user = create_fake_user(&context.db_pool).await;
+    let other_user = create_fake_user(&context.db_pool).await;
+    create_note(context, &user, "both", &["a", "b"]).await;
+    create_note(context, &user, "only a", &["a"]).await;
+    create_note(context, &user, "only b", &["B"]).await;
+    create_note(context, &user, "none", &[]).await;
+    create_note(context, &other_user, "other", &["a", "b"]).await;
+
+    let texts = read_texts(context, &user, "tag=a").await;
+    assert_that!(texts, elements_are![eq("both"), eq("only a")]);
+
+    let texts = read_texts(context, &user, "tag=a&tag=b").await;
+    assert_that!(texts, elements_are![eq("both")]);
+
+    let texts = read_texts(context, &user, "tag=a&tag=b&tag_mode=any").await;
+    assert_that!(texts, elements_are![eq("both"), eq("only a"), eq("only b")]);
+
+    let texts = read_texts(context, &user, "tag=unknown").await;
+    assert_that!(texts, empty());
+}
@@ -0,0 +1,322 @@
+use axum::{
+    body::Body,
+    http::{self, Method},
+};
+use fake::{Fake, Faker};
+use forge_api_db::entities::users::User;
+use forge_api_db::test_helpers::login_challenges::expire_all;
+use forge_api_db::test_helpers::users::{create as create_user, UserChangeset};
+use forge_api_macros::db_test;
+use forge_api_web::auth::{LoginRequest, TokenResponse, TwoFactorChallenge, TwoFactorLoginRequest};
+use forge_api_web::test_helpers::{BodyExt, DbTestContext, RouterExt};
+use forge_api_web::two_factor::{
+    RecoveryCodes, TotpEnrollment, TwoFactorCodeRequest, TwoFactorStatus,
+};
+use googletest::prelude::*;
+use hyper::StatusCode;
+use serde_json::json;
+use std::time::{SystemTime, UNIX_EPOCH};
+use totp_rs::{Algorithm, Secret, TOTP};
+
+const PASSWORD: &str = "correct horse battery staple";
+
+async fn create_test_user(context: &DbTestContext) -> User {
+    let user_changeset = UserChangeset {
+        name: format!("user.{}", (1000..9999).fake::<u32>()),
+        pass: bcrypt::hash(PASSWORD, 4).unwrap(),
+        ..Faker.fake()
+    };
+    create_user(user_changeset, &context.db_pool).await.unwrap()
+}
+
+/// Generates the code for the current time step or, with an offset of 1, for the next one.
+///
+/// Codes for earlier steps are avoided as they could already be rejected if the step changes while a test runs.
+fn totp_code(secret: &str, step_offset: u64) -> String {
+    let totp = TOTP::new(
+        Algorithm::SHA1,
+        6,
+        1,
+        30,
+        Secret::Encoded(secret.to_string()).to_bytes().unwrap(),
+        None,
+        String::new(),
+    )
+    .unwrap();
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    totp.generate(now + step_offset * 30)
+}
+
+async fn post_json(
+    context: &DbTestContext,
+    path: &str,
+    user: Option<&User>,
+    payload: serde_json::Value,
+) -> axum::response::Response {
+    let mut request = context
+        .app
+        .request(path)
+        .method(Method::POST)
+        .body(Body::from(payload.to_string()))
+        .header(http::header::CONTENT_TYPE, "application/json");
+    if let Some(user) = user {
+        request = request.header(http::header::AUTHORIZATION, &context.bearer_token(user));
+    }
+    request.send().await
+}
+
+async fn login(context: &DbTestContext, user: &User) -> axum::response::Response {
+    let payload = json!(LoginRequest {
+        name: user.name.clone(),
+        password: String::from(PASSWORD),
+    });
+    post_json(context, "/login", None, payload).await
+}
+
+async fn challenge(context: &DbTestContext, user: &User) -> String {
+    let response = login(context, user).await;
+    assert_that!(response.status(), eq(StatusCode::ACCEPTED));
+    let challenge: TwoFactorChallenge = response.into_body().into_json().await;
+    challenge.challenge_token
+}
+
+async fn login_two_factor(
+    context: &DbTestContext,
+    challenge_token: &str,
+    code: &str,
+) -> axum::response::Response {
+    let payload = json!(TwoFactorLoginRequest {
+        challenge_token: String::from(challenge_token),
+        code: String::from(code),
+    });
+    post_json(context, "/login/2fa", None, payload).await
+}
+
+fn code_payload(code: &str) -> serde_json::Value {
+    json!(TwoFactorCodeRequest {
+        code: String::from(code),
+    })
+}
+
+async fn status(context: &DbTestContext, user: &User) -> TwoFactorStatus {
+    let response = context
+        .app
+        .request("/account/2fa")
+        .header(http::header::AUTHORIZATION, &context.bearer_token(user))
+        .send()
+        .await;
+    assert_that!(response.status(), eq(StatusCode::OK));
+    response.into_body().into_json().await
+}
+
+async fn enroll(context: &DbTestContext, user: &User) -> TotpEnrollment {
+    let response = post_json(context, "/account/2fa/totp", Some(user), json!({})).await;
+    assert_that!(response.status(), eq(StatusCode::OK));
+    response.into_body().into_json().await
+}
+
+/// Enables two-factor authentication for the user with the code for the current step, returning the secret and the recovery codes.
+async fn enable(context: &DbTestContext, user: &User) -> (String, Vec<String>) {
+    let enrollment = enroll(context, user).await;
+    let response = post_json(
+        context,
+        "/account/2fa/totp/confirm",
+        Some(user),
+        code_payload(&totp_code(&enrollment.secret, 0)),
+    )
+    .await;
+    assert_that!(response.status(), eq(StatusCode::OK));
+    let codes: RecoveryCodes = response.into_body().into_json().await;
+
+    (enrollment.secret, codes.recovery_codes)
+}
+
+#[db_test]
+async fn test_enroll_and_login(context: &DbTestContext) {
+    let user = create_test_user(context).await;
+
+    let enrollment = enroll(context, &user).await;
+    assert_that!(
+        enrollment.otpauth_uri,
+        starts_with(format!("otpauth://totp/forge-api:{}?", user.name))
+    );
+    assert_that!(
+        enrollment.otpauth_uri,
+        contains_substring(enrollment.secret.clone())
+    );
+
+    // Enrollment only takes effect once it has been confirmed.
+    assert_that!(status(context, &user).await.enabled, eq(false));
+    let response = login(context, &user).await;
+    assert_that!(response.status(), eq(StatusCode::OK));
+
+    let code = totp_code(&enrollment.secret, 0);
+    let wrong_code = if code == "000000" { "111111" } else { "000000" };
+    let response = post_json(
+        context,
+        "/account/2fa/totp/confirm",
+        Some(&user),
+        code_payload(wrong_code),
+    )
+    .await;
+    assert_that!(response.status(), eq(StatusCode::UNPROCESSABLE_ENTITY));
+
+    let response = post_json(
+        context,
+        "/account/2fa/totp/confirm",
+        Some(&user),
+        code_payload(&code),
+    )
+    .await;
+    assert_that!(response.status(), eq(StatusCode::OK));
+    let codes: RecoveryCodes = response.into_body().into_json().await;
+    assert_that!(codes.recovery_codes, len(eq(10)));
+    let status = status(context, &user).await;
+    assert_that!(status.enabled, eq(true));
+    assert_that!(status.recovery_codes_left, eq(10));
+
+    let challenge_token = challenge(context, &user).await;
+
+    // The code used for confirming enrollment can't be used again.
+    let response = login_two_factor(context, &challenge_token, &code).await;
+    assert_that!(response.status(), eq(StatusCode::UNAUTHORIZED));
+
+    let response =
+        login_two_factor(context, &challenge_token, &totp_code(&enrollment.secret, 1)).await;
+    assert_that!(response.status(), eq(StatusCode::OK));
+    let tokens: TokenResponse = response.into_body().into_json().await;
+    let claims = context.jwt.decode_jwt(&tokens.token).unwrap();
+    assert_that!(claims.sub, eq(user.id));
+
+    // Challenges can only be passed once.
+    let response = login_two_factor(context, &challenge_token, &codes.recovery_codes[0]).await;
+    assert_that!(response.status(), eq(StatusCode::UNAUTHORIZED));
+}
+
+#[db_test]
+async fn test_enroll_when_enabled(context: &DbTestContext) {
+    let user = create_test_user(context).await;
+    enable(context, &user).await;
+
+    let response = post_json(context, "/account/2fa/totp", Some(&user), json!({})).await;
+
+    assert_that!(response.status(), eq(StatusCode::CONFLICT));
+}
+
+#[db_test]
+async fn test_enroll_unauthenticated(context: &DbTestContext) {
+    let response = post_json(context, "/account/2fa/totp", None, json!({})).await;
+
+    assert_that!(response.status(), eq(StatusCode::UNAUTHORIZED));
+}
+
+#[db_test]
+async fn test_login_with_recovery_code(context: &DbTestContext) {
+    let user = create_test_user(context).await;
+    let (_, recovery_codes) = enable(context, &user).await;
+
+    // Recovery codes are accepted regardless of case and separators.
+    let typed_code = recovery_codes[3].to_uppercase().replace('-', "");
+    let challenge_token = challenge(context, &user).await;
+    let response = login_two_factor(context, &challenge_token, &typed_code).await;
+    assert_that!(response.status(), eq(StatusCode::OK));
+
+    let challenge_token = challenge(context, &user).await;
+    let response = login_two_factor(context, &challenge_token, &recovery_codes[3]).await;
+    assert_that!(response.status(), eq(StatusCode::UNAUTHORIZED));
+
+    assert_that!(status(context, &user).await.recovery_codes_left, eq(9));
+}
+
+#[db_test]
+async fn test_login_with_expired_challenge(context: &DbTestContext) {
+    let user = create_test_user(context).await;
+    let (_, recovery_codes) = enable(context, &user).await;
+    let challenge_token = challenge(context, &user).await;
+    expire_all(&context.db_pool).await.unwrap();
+
+    let response = login_two_factor(context, &challenge_token, &recovery_codes[0]).await;
+
+    assert_that!(response.status(), eq(StatusCode::UNAUTHORIZED));
+}
+
+#[db_test]
+async fn test_wrong_codes_lock_out_account(context: &DbTestContext) {
+    let user = create_test_user(context).await;
+    let (secret, _) = enable(context, &user).await;
+    let challenge_token = challenge(context, &user).await;
+
+    for i in 0..5 {
+        let response = login_two_factor(context, &challenge_token, &format!("wrong-{i}")).await;
+        assert_that!(response.status(), eq(StatusCode::UNAUTHORIZED));
+    }
+
+    let response = login_two_factor(context, &challenge_token, &totp_code(&secret, 1)).await;
+    assert_that!(response.status(), eq(StatusCode::TOO_MANY_REQUESTS));
+}
+
+#[db_test]
+async fn test_disable(context: &DbTestContext) {
+    let user = create_test_user(context).await;
+    let (secret, recovery_codes) = enable(context, &user).await;
+
+    let response = post_json(
+        context,
+        "/account/2fa/disable",
+        Some(&user),
+        code_payload("wrong-code"),
+    )
+    .await;
+    assert_that!(response.status(), eq(StatusCode::UNPROCESSABLE_ENTITY));
+
+    let response = post_json(
+        context,
+        "/account/2fa/disable",
+        Some(&user),
+        code_payload(&totp_code(&secret, 1)),
+    )
+    .await;
+    assert_that!(response.status(), eq(StatusCode::NO_CONTENT));
+
+    let status = status(context, &user).await;
+    assert_that!(status.enabled, eq(false));
+    assert_that!(status.recovery_codes_left, eq(0));
+    let response = login(context, &user).await;
+    assert_that!(response.status(), eq(StatusCode::OK));
+
+    let response = post_json(
+        context,
+        "/account/2fa/disable",
+        Some(&user),
+        code_payload(&recovery_codes[0]),
+    )
+    .await;
+    assert_that!(response.status(), eq(StatusCode::NOT_FOUND));
+}
+
+#[db_test]
+async fn test_regenerate_recovery_codes(context: &DbTestContext) {
+    let user = create_test_user(context).await;
+    let (_, old_codes) = enable(context, &user).await;
+
+    let response = post_json(
+        context,
+        "/account/2fa/recovery-codes",
+        Some(&user),
+        code_payload(&old_codes[0]),
+    )
+    .await;
+    assert_that!(response.status(), eq(StatusCode::OK));
+    let new_codes: RecoveryCodes = response.into_body().into_json().await;
+    assert_that!(new_codes.recovery_codes, len(eq(10)));
+    assert_that!(status(context, &user).await.recovery_codes_left, eq(10));
+
+    let challenge_token = challenge(context, &user).await;
+    let response = login_two_factor(context, &challenge_token, &old_codes[1]).await;
+    assert_that!(response.status(), eq(StatusCode::UNAUTHORIZED));
+    let response = login_two_factor(context, &challenge_token, &new_codes.recovery_codes[0]).await;
+    assert_that!(response.status(), eq(StatusCode::OK));
+}